@@ -1,9 +1,17 @@
+use std::fmt;
+
 use crate::u256::U256;
 
 const P_XX      : u64  = 64;
 const P_XX_MAX  : U256 = U256([0xFFFFFFFFFFFFFFFFu64, 0, 0, 0]);
 const P_XX_ONE  : U256 = U256([1, 0, 0, 0]);
 
+// Internal precision of the precision-parameterised log2/pow2 variants. Values within [1, 2) are kept below 2**128, so that
+// the product of two of them (< 2**256) never overflows.
+const P_HP      : usize = 127;
+const ONE_HP    : U256  = U256([0, 0x8000000000000000u64, 0, 0]);   // 2**127
+const TWO_HP    : U256  = U256([0, 0, 1, 0]);                       // 2**128
+
 pub const ZERO_X64 : U256 = U256([0, 0, 0, 0]);
 pub const ONE_X64  : U256 = U256([0, 1, 0, 0]);
 pub const LN2_X64  : U256 = U256([12786308645202655660, 0, 0, 0]);
@@ -13,6 +21,9 @@ pub const U256_MAX : U256 = U256([0xFFFFFFFFFFFFFFFFu64, 0xFFFFFFFFFFFFFFFFu64,
 // TODO write explanation here
 const TWO_TWO_MINUS_I       : [U256; 20] = [U256([7640891576956012809, 1, 0, 0]), U256([3490255227380126431, 1, 0, 0]), U256([1669572981167730126, 1, 0, 0]), U256([816707133613602346, 1, 0, 0]), U256([403931097166463918, 1, 0, 0]), U256([200871872941133543, 1, 0, 0]), U256([100163996173424344, 1, 0, 0]), U256([50014196964519265, 1, 0, 0]), U256([24990171141283490, 1, 0, 0]), U256([12490856599448656, 1, 0, 0]), U256([6244371414720417, 1, 0, 0]), U256([3121921530820282, 1, 0, 0]), U256([1560894726863213, 1, 0, 0]), U256([780430854493330, 1, 0, 0]), U256([390211300099399, 1, 0, 0]), U256([195104618273796, 1, 0, 0]), U256([97552051194286, 1, 0, 0]), U256([48775961111661, 1, 0, 0]), U256([24387964434481, 1, 0, 0]), U256([12193978186906, 1, 0, 0])];
 
+// 2**(2**-i) for i = 1..64 (x127). Generated with solana/programs/TestProgramFixedPointMath/utils/lookupTablesGenerators.ts
+const TWO_TWO_MINUS_I_X127  : [U256; 64] = [U256([6448461645324402335, 13043817825332782212, 0, 0]), U256([5092765340388226806, 10968499650544839023, 0, 0]), U256([18150740731657272464, 10058158527438640870, 0, 0]), U256([14252023153203397042, 9631725603661576980, 0, 0]), U256([4479471236720757759, 9425337585438007767, 0, 0]), U256([8927653057682267893, 9323807973325342579, 0, 0]), U256([2680581971169967392, 9273454034941487980, 0, 0]), U256([10724629777457498786, 9248379135337035440, 0, 0]), U256([18414349482185962446, 9235867122425417552, 0, 0]), U256([3931888646332993544, 9229617465154500136, 0, 0]), U256([10691075897984348912, 9226494222562136016, 0, 0]), U256([2049572381911534483, 9224932997620185949, 0, 0]), U256([13138805892315659807, 9224152484218207414, 0, 0]), U256([16738804605411161050, 9223762252282022472, 0, 0]), U256([12259570784998297619, 9223567142504825507, 0, 0]), U256([219655644736602622, 9223469589163912706, 0, 0]), U256([1690750868018325462, 9223420812880372951, 0, 0]), U256([5939091593247841253, 9223396424835331638, 0, 0]), U256([9441516707657345207, 9223384230836993048, 0, 0]), U256([17076603554485537714, 9223378133843869260, 0, 0]), U256([14698875116786682644, 9223375085348818742, 0, 0]), U256([9273532287161656598, 9223373561101671327, 0, 0]), U256([14389155779922968915, 9223372798978192080, 0, 0]), U256([11944517547488873984, 9223372417916476072, 0, 0]), U256([7160493593002695032, 9223372227385623972, 0, 0]), U256([3877399239423769021, 9223372132120199398, 0, 0]), U256([2012999447705866115, 9223372084487487480, 0, 0]), U256([5636762163936731773, 9223372060671131613, 0, 0]), U256([17811004930353718290, 9223372048762953702, 0, 0]), U256([10347815440944783144, 9223372042808864753, 0, 0]), U256([5534485967275032552, 9223372039831820280, 0, 0]), U256([551544536482065066, 9223372038343298044, 0, 0]), U256([18168591730206419159, 9223372037599036925, 0, 0]), U256([18169186749027531366, 9223372037226906366, 0, 0]), U256([9049973086281850581, 9223372037040841087, 0, 0]), U256([13739703507937809624, 9223372036947808447, 0, 0]), U256([16091060022809218664, 9223372036901292127, 0, 0]), U256([17268361106255771015, 9223372036878033967, 0, 0]), U256([17857417354481757955, 9223372036866404887, 0, 0]), U256([18152046905220428966, 9223372036860590347, 0, 0]), U256([18299387037246183839, 9223372036857683077, 0, 0]), U256([18373063442423166115, 9223372036856229442, 0, 0]), U256([9186531192947907654, 9223372036855502625, 0, 0]), U256([13816637501262810784, 9223372036855139216, 0, 0]), U256([6908318717614925679, 9223372036854957512, 0, 0]), U256([3454159350553342912, 9223372036854866660, 0, 0]), U256([1727079673213141474, 9223372036854821234, 0, 0]), U256([863539836090688241, 9223372036854798521, 0, 0]), U256([9655141954771149305, 9223372036854787164, 0, 0]), U256([4827570977353331996, 9223372036854781486, 0, 0]), U256([2413785488668605334, 9223372036854778647, 0, 0]), U256([10430264781187063309, 9223372036854777227, 0, 0]), U256([14438504427447803671, 9223372036854776517, 0, 0]), U256([16442624250578551696, 9223372036854776162, 0, 0]), U256([8221312125289244361, 9223372036854775985, 0, 0]), U256([13334028099499390117, 9223372036854775896, 0, 0]), U256([6667014049749693090, 9223372036854775852, 0, 0]), U256([3333507024874846053, 9223372036854775830, 0, 0]), U256([1666753512437422904, 9223372036854775819, 0, 0]), U256([10056748793073487229, 9223372036854775813, 0, 0]), U256([14251746433391519415, 9223372036854775810, 0, 0]), U256([7125873216695759705, 9223372036854775809, 0, 0]), U256([12786308645202655660, 9223372036854775808, 0, 0]), U256([6393154322601327830, 9223372036854775808, 0, 0])];
+
 const TWO_MINUS_TWO_MINUS_I : [U256; 20] = [U256([13043817825332782212, 0, 0, 0]), U256([15511800964685064948, 0, 0, 0]), U256([16915738899553466670, 0, 0, 0]), U256([17664662643191237676, 0, 0, 0]), U256([18051468387014017850, 0, 0, 0]), U256([18248035989933441396, 0, 0, 0]), U256([18347121020861646923, 0, 0, 0]), U256([18396865112328554661, 0, 0, 0]), U256([18421787711448657617, 0, 0, 0]), U256([18434261669329232139, 0, 0, 0]), U256([18440501815349552981, 0, 0, 0]), U256([18443622680442407997, 0, 0, 0]), U256([18445183311048607332, 0, 0, 0]), U256([18445963675871538003, 0, 0, 0]), U256([18446353870663572145, 0, 0, 0]), U256([18446548971154807802, 0, 0, 0]), U256([18446646522174239825, 0, 0, 0]), U256([18446695297877410579, 0, 0, 0]), U256([18446719685777359790, 0, 0, 0]), U256([18446731879739425374, 0, 0, 0])];
 
 const EXP_POS_LOOKUP        : [U256; 8]  = [U256([13249961062380153450, 2, 0, 0]), U256([7176818287289529100, 7, 0, 0]), U256([11033920579092664090, 54, 0, 0]), U256([17671741784691597056, 2980, 0, 0]), U256([9601675514881374392, 8886110, 0, 0]), U256([12823456651613180037, 78962960182680, 0, 0]), U256([17127243763087097131, 17696838799656736180, 338008108, 0]), U256([13460297379963274417, 4294423684612430841, 17671928477841822154, 114249481722274167])];
@@ -23,35 +34,77 @@ const INV_EXP_POS_LOOKUP    : [U256; 8]  = [U256([6786177901268885274, 0, 0, 0])
 
 const INV_EXP_NEG_LOOKUP    : [U256; 20] = [U256([11188515852577165299, 0, 0, 0]), U256([14366338729722795843, 0, 0, 0]), U256([16279194507819420732, 0, 0, 0]), U256([17329112349219823218, 0, 0, 0]), U256([17879197424118840458, 0, 0, 0]), U256([18160753814917686419, 0, 0, 0]), U256([18303190372430456779, 0, 0, 0]), U256([18374827034086858296, 0, 0, 0]), U256([18410750438167364677, 0, 0, 0]), U256([18428738468430479223, 0, 0, 0]), U256([18437739073120195921, 0, 0, 0]), U256([18442241023793258495, 0, 0, 0]), U256([18444492411329227605, 0, 0, 0]), U256([18445618208161748319, 0, 0, 0]), U256([18446181132345977515, 0, 0, 0]), U256([18446462600880313685, 0, 0, 0]), U256([18446603336758065834, 0, 0, 0]), U256([18446673705099591509, 0, 0, 0]), U256([18446708889371017194, 0, 0, 0]), U256([18446726481531895805, 0, 0, 0])];
 
-// TODO currently these functions return Result<>. would it be better to simply panic?
+/// Error returned by the fixed point math functions. The exponent bounds are the largest supported integer part of the
+/// (x64) argument.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FixedPointError {
+    /// The result (or an intermediate value) does not fit in the x64 representation (includes negative results)
+    Overflow,
+    DivisionByZero,
+    LogOfZero,
+    /// The result does not fit in the x64 representation for exponents larger than 'max_exponent'
+    ExponentTooLarge { max_exponent: u64 },
+    /// Not enough significant bits are left in the result for exponents larger than 'max_exponent'
+    PrecisionLoss { max_exponent: u64 },
+}
+
+impl fmt::Display for FixedPointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FixedPointError::Overflow                           => write!(f, "fixed point overflow"),
+            FixedPointError::DivisionByZero                     => write!(f, "fixed point division by zero"),
+            FixedPointError::LogOfZero                          => write!(f, "logarithm of zero"),
+            FixedPointError::ExponentTooLarge { max_exponent }  => write!(f, "exponent too large (max {})", max_exponent),
+            FixedPointError::PrecisionLoss { max_exponent }     => write!(f, "precision loss, exponent too large (max {})", max_exponent),
+        }
+    }
+}
+
+impl std::error::Error for FixedPointError {}
+
+/// Number of fractional bits computed by 'log2_x64_with_precision' and 'pow2_x64_with_precision'. Every extra bit costs
+/// one more iteration (i.e. compute units), hence pools may choose a higher precision only where it matters (e.g. large
+/// swaps).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precision {
+    Bits32,
+    Bits48,
+    Bits64,
+}
+
+impl Precision {
+    pub const fn fractional_bits(self) -> usize {
+        match self {
+            Precision::Bits32 => 32,
+            Precision::Bits48 => 48,
+            Precision::Bits64 => 64,
+        }
+    }
+}
 
-pub fn mul_x64(a: U256, b: U256) -> Result<U256, ()> {
-    let (r0, _) = a.overflowing_mul(b);
-    let mut r1 = mulmod(a, b, U256_MAX);
 
-    r1 = r1.overflowing_sub(r0).0 - U256::from((r1 < r0) as u64);
+pub fn mul_x64(a: U256, b: U256) -> Result<U256, FixedPointError> {
+    let r = full_mul(a, b);
 
     // !!CRITICAL!! Check for second order overflow.
-    // We know r1 expand with shift(r1, HighBase-pXX)
-    // r1 · 2^(256-64) = r1 · 2^192 < 2^256
-    // r1 < 2^64 (or r1 <= 2^64 - 1, that is P_XX_MAX)
-    if r1 > P_XX_MAX { return Err(()); }
+    // (a·b) >> 64 must fit in 256 bits, i.e. the product must be < 2^320 (the top 3 limbs must be zero)
+    if (r[5] | r[6] | r[7]) != 0 { return Err(FixedPointError::Overflow); }
 
-    Ok((r1 << (256-P_XX)) + (r0 >> P_XX))
+    Ok(U256([r[1], r[2], r[3], r[4]]))
 }
 
 
-pub fn div_x64(a: U256, b: U256) -> Result<U256, ()> {
-    if b.is_zero() { return Err(()) };
+pub fn div_x64(a: U256, b: U256) -> Result<U256, FixedPointError> {
+    if b.is_zero() { return Err(FixedPointError::DivisionByZero) };
 
     let m = P_XX_MAX % b; // 2**p-1 % b
     let r = P_XX_MAX / b; // 2**p-1 / b
 
     // Ok(r * a + (m + P_XX_ONE) * a / b)
-    r.checked_mul(a).ok_or(())?
+    r.checked_mul(a).ok_or(FixedPointError::Overflow)?
         .checked_add(
-            (m.checked_add(P_XX_ONE).ok_or(())?).checked_mul(a / b).ok_or(())?
-        ).ok_or(())
+            (m.checked_add(P_XX_ONE).ok_or(FixedPointError::Overflow)?).checked_mul(a / b).ok_or(FixedPointError::Overflow)?
+        ).ok_or(FixedPointError::Overflow)
 
     //TODO fix overflow of (m + P_XX_ONE) * a
     // let (partial, overflowed) = (m + P_XX_ONE).overflowing_mul(a);
@@ -62,8 +115,8 @@ pub fn div_x64(a: U256, b: U256) -> Result<U256, ()> {
 }
 
 
-pub fn log2_x64(x: U256) -> Result<U256, ()> {
-    if x.is_zero() { return Err(()) }
+pub fn log2_x64(x: U256) -> Result<U256, FixedPointError> {
+    if x.is_zero() { return Err(FixedPointError::LogOfZero) }
 
     let mut x_i = x.clone();
     let mut log2_intermediate = ZERO_X64;
@@ -100,7 +153,7 @@ pub fn log2_x64(x: U256) -> Result<U256, ()> {
         // x_i = x_i >> 1u32;
         log2_intermediate += U256([1, 0, 0, 0]);
     }
-    log2_intermediate = log2_intermediate.checked_sub(U256([P_XX, 0, 0, 0])).ok_or(())?;
+    log2_intermediate = log2_intermediate.checked_sub(U256([P_XX, 0, 0, 0])).ok_or(FixedPointError::Overflow)?;  // x < 1 (negative log)
     // TODO: Is it cheaper to get the major and then run this on the major
     // instead of running it on the whole and then removing 64.
 
@@ -119,14 +172,39 @@ pub fn log2_x64(x: U256) -> Result<U256, ()> {
 }
 
 
-pub fn ln_x64(x: U256) -> Result<U256, ()> {
+/// log2 computed to the given number of fractional bits. Unlike 'log2_x64', the intermediate squarings are computed with
+/// 127 fractional bits, so that the result is accurate up to the last computed bit.
+pub fn log2_x64_with_precision(x: U256, precision: Precision) -> Result<U256, FixedPointError> {
+    if x.is_zero() { return Err(FixedPointError::LogOfZero) }
+
+    // Integer part of the log (+64, as x is x64)
+    let msb = x.bits() - 1;
+    if msb < (P_XX as usize) { return Err(FixedPointError::Overflow) }  // x < 1 (negative log)
+
+    // Normalise x to [1, 2) (x127)
+    let mut x_i = if msb > P_HP { x >> (msb - P_HP) } else { x << (P_HP - msb) };
+    let mut log2_intermediate = U256::from(msb - (P_XX as usize)) << P_XX;
+
+    for i in 1..=precision.fractional_bits() {
+        x_i = (x_i * x_i) >> P_HP;      // x_i < 2**128 => x_i · x_i < 2**256. No overflow
+        if x_i >= TWO_HP {
+            log2_intermediate |= P_XX_ONE << ((P_XX as usize) - i);
+            x_i >>= 1u64;
+        }
+    }
+
+    Ok(log2_intermediate)
+}
+
+
+pub fn ln_x64(x: U256) -> Result<U256, FixedPointError> {
     Ok(mul_x64(log2_x64(x)?, LN2_X64)?)
 }
 
 
-pub fn pow2_x64(x: U256) -> Result<U256, ()> {
+pub fn pow2_x64(x: U256) -> Result<U256, FixedPointError> {
     let major_x = x >> P_XX;
-    if major_x >= U256([192, 0, 0, 0]) { return Err(()) };
+    if major_x >= U256([192, 0, 0, 0]) { return Err(FixedPointError::ExponentTooLarge { max_exponent: 191 }) };
 
     let mut intermediate = ONE_X64; // 2**64
     for i in 1..20 {
@@ -143,11 +221,41 @@ pub fn pow2_x64(x: U256) -> Result<U256, ()> {
 }
 
 
-pub fn inv_pow2_x64(x: U256) -> Result<U256, ()> {
+/// pow2 computed using the given number of fractional bits of the exponent (rounded down). The lookup table is x127, so
+/// that the accumulated rounding errors do not affect the x64 result.
+pub fn pow2_x64_with_precision(x: U256, precision: Precision) -> Result<U256, FixedPointError> {
+    let major_x = x >> P_XX;
+    if major_x >= U256([192, 0, 0, 0]) { return Err(FixedPointError::ExponentTooLarge { max_exponent: 191 }) };
+
+    let intermediate = pow2_fraction_x127(x, precision);
+
+    // Convert to x64 while applying the major part. intermediate < 2**128 => intermediate << (191 - 63) < 2**256
+    let major_x = major_x.as_usize();
+    if major_x < P_HP - (P_XX as usize) {
+        return Ok(intermediate >> (P_HP - (P_XX as usize) - major_x))
+    }
+    Ok(intermediate << (major_x + (P_XX as usize) - P_HP))
+}
+
+
+/// 2 to the power of the fractional part of x (x64), computed using the given number of fractional bits. The result is
+/// within [1, 2) and is returned x127.
+fn pow2_fraction_x127(x: U256, precision: Precision) -> U256 {
+    let mut intermediate = ONE_HP;
+    for i in 1..=precision.fractional_bits() {
+        if x.bit((P_XX as usize) - i) {
+            intermediate = (intermediate * TWO_TWO_MINUS_I_X127[i-1]) >> P_HP;    // Both factors < 2**128. No overflow
+        }
+    }
+    intermediate
+}
+
+
+pub fn inv_pow2_x64(x: U256) -> Result<U256, FixedPointError> {
     let major_x = x >> P_XX;
 
     // dev: Major larger than fixed points. Reserve a few (64-41=23) bits for accuracy
-    if major_x >=  U256([41, 0, 0, 0]) { return Err(()) };
+    if major_x >=  U256([41, 0, 0, 0]) { return Err(FixedPointError::PrecisionLoss { max_exponent: 40 }) };
 
     let mut intermediate = ONE_X64; // 2**64
     for i in 1..(20-1) {
@@ -162,21 +270,52 @@ pub fn inv_pow2_x64(x: U256) -> Result<U256, ()> {
     // calculation. We prefer to do it later than sooner to not waste decimals.
     Ok(intermediate >> major_x)
 }
+
+
+/// inv_pow2 computed using the given number of fractional bits of the exponent (rounded down), see
+/// 'pow2_x64_with_precision'.
+pub fn inv_pow2_x64_with_precision(x: U256, precision: Precision) -> Result<U256, FixedPointError> {
+    let major_x = x >> P_XX;
+
+    // dev: Major larger than fixed points. Reserve a few (64-41=23) bits for accuracy
+    if major_x >=  U256([41, 0, 0, 0]) { return Err(FixedPointError::PrecisionLoss { max_exponent: 40 }) };
+
+    // 2^(-fraction) = 2^(127+64) / 2^fraction (x127), which is x64. 2^fraction >= 1, hence the result is <= 1
+    let intermediate = (ONE_HP << P_XX) / pow2_fraction_x127(x, precision);
+
+    Ok(intermediate >> major_x)
+}
     
-pub fn pow_x64(x: U256, p: U256) -> Result<U256, ()> {
+pub fn pow_x64(x: U256, p: U256) -> Result<U256, FixedPointError> {
     Ok(pow2_x64(
       mul_x64(p, log2_x64(x)?)? 
     )?)
 }
     
-pub fn inv_pow_x64(x: U256, p: U256) -> Result<U256, ()> {
+pub fn inv_pow_x64(x: U256, p: U256) -> Result<U256, FixedPointError> {
     Ok(inv_pow2_x64(
       mul_x64(log2_x64(x)?, p)? 
     )?)
 }
+
+/// pow computed with 'log2_x64_with_precision' and 'pow2_x64_with_precision'.
+pub fn pow_x64_with_precision(x: U256, p: U256, precision: Precision) -> Result<U256, FixedPointError> {
+    pow2_x64_with_precision(
+        mul_x64(p, log2_x64_with_precision(x, precision)?)?,
+        precision
+    )
+}
+
+/// inv_pow computed with 'log2_x64_with_precision' and 'inv_pow2_x64_with_precision'.
+pub fn inv_pow_x64_with_precision(x: U256, p: U256, precision: Precision) -> Result<U256, FixedPointError> {
+    inv_pow2_x64_with_precision(
+        mul_x64(log2_x64_with_precision(x, precision)?, p)?,
+        precision
+    )
+}
     
-pub fn exp_x64(x: U256) -> Result<U256, ()> {
-    if (x >> U256([64, 0, 0, 0])) > U256([134, 0, 0, 0]) { return Err(()) };
+pub fn exp_x64(x: U256) -> Result<U256, FixedPointError> {
+    if (x >> U256([64, 0, 0, 0])) > U256([134, 0, 0, 0]) { return Err(FixedPointError::ExponentTooLarge { max_exponent: 134 }) };
 
     let mut exp_intermediate = ONE_X64; // 2**64
     for i in 0..8 {
@@ -196,8 +335,8 @@ pub fn exp_x64(x: U256) -> Result<U256, ()> {
     Ok(exp_intermediate)
 }
     
-pub fn inv_exp_x64(x: U256) -> Result<U256, ()> {
-    if x > (P_XX_ONE << ((P_XX as usize) + 4)) { return Err(()) };
+pub fn inv_exp_x64(x: U256) -> Result<U256, FixedPointError> {
+    if x > (P_XX_ONE << ((P_XX as usize) + 4)) { return Err(FixedPointError::PrecisionLoss { max_exponent: 16 }) };
 
     let mut exp_intermediate = ONE_X64; // 2**64
     for i in 0..8 {
@@ -217,7 +356,7 @@ pub fn inv_exp_x64(x: U256) -> Result<U256, ()> {
     Ok(exp_intermediate)
 }
     
-pub fn safe_pow_x64(a: U256, b: U256, p: U256) -> Result<U256, ()> {
+pub fn safe_pow_x64(a: U256, b: U256, p: U256) -> Result<U256, FixedPointError> {
     if a < b {
         return Ok(inv_pow_x64(div_x64(b, a)?, p)?);
     }
@@ -225,37 +364,21 @@ pub fn safe_pow_x64(a: U256, b: U256, p: U256) -> Result<U256, ()> {
 }
 
 
-// https://stackoverflow.com/questions/12168348/ways-to-do-modulo-multiplication-with-primitive-types
-fn mulmod(a: U256, b: U256, m: U256) -> U256 {
-    let mut res = ZERO_X64;
-    let mut a   = a.clone();
-    let mut b   = b.clone();
-
-    /* Only needed if b >= m */
-    if b >= m {
-        if m > (U256::max_value() >> P_XX_ONE) { b -= m }
-        else { b %= m };
-    }
+/// Full 256x256 -> 512 bit multiplication (schoolbook over the 64 bit limbs). Returns the limbs of the product, least
+/// significant first. Runs a fixed number of limb multiplications, independent of the values of 'a' and 'b'.
+pub(crate) fn full_mul(a: U256, b: U256) -> [u64; 8] {
+    let mut r = [0u64; 8];
 
-    while !a.is_zero() {
-        if !(a & P_XX_ONE).is_zero() {
-            /* Add b to res, modulo m, without overflow */
-            if b >= m - res {
-                res = res.overflowing_sub(m).0;
-            }
-            res = res.overflowing_add(b).0;
+    for i in 0..4 {
+        let mut carry: u64 = 0;
+        for j in 0..4 {
+            // a_i·b_j + r_ij + carry <= (2^64-1)^2 + 2·(2^64-1) = 2^128-1. No overflow
+            let t = (a.0[i] as u128) * (b.0[j] as u128) + (r[i + j] as u128) + (carry as u128);
+            r[i + j] = t as u64;
+            carry    = (t >> 64) as u64;
         }
-        a >>= P_XX_ONE;
-
-        /* Double b, modulo m */
-        let mut temp_b = b.clone();
-        if b >= m - b { /* Equiv to if (2 * b >= m), without overflow */
-            temp_b = temp_b.overflowing_sub(m).0
-        }
-        b = b.overflowing_add(temp_b).0;
+        r[i + 4] = carry;
     }
 
-    res
+    r
 }
-
-
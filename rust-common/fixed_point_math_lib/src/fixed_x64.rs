@@ -0,0 +1,167 @@
+use std::fmt;
+use std::str::FromStr;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use crate::u256::U256;
+
+use u512::U512;
+
+mod u512 {
+    // The lints are raised by the code generated by the macro
+    #![allow(clippy::manual_div_ceil, clippy::assign_op_pattern)]
+
+    use uint::construct_uint;
+
+    construct_uint! {
+        pub struct U512(8);
+    }
+}
+
+const FRACTION_BITS      : usize = 64;
+const FRACTION_MASK      : U256  = U256([0xFFFFFFFFFFFFFFFFu64, 0, 0, 0]);
+const FRACTION_DIGITS    : usize = 64;      // 2^-64 = 5^64 / 10^64, hence any x64 fraction has at most 64 decimal digits
+const FIVE_POW_64        : U256  = U256([0x6E38ED64BF6A1F01u64, 0xE93FF9F4DAA797EDu64, 0x184F03u64, 0]);  // 5^64
+
+/// Unsigned x64 fixed point number (64 fractional bits). Displays and parses as a decimal number (e.g. '1.5'), or as
+/// the raw hexadecimal value when using the hex formatters/the '0x' prefix. Serialises as the [u64; 4] limbs.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, BorshSerialize, BorshDeserialize)]
+pub struct FixedX64(pub U256);
+
+impl FixedX64 {
+    pub const ZERO : FixedX64 = FixedX64(U256([0, 0, 0, 0]));
+    pub const ONE  : FixedX64 = FixedX64(U256([0, 1, 0, 0]));
+
+    pub fn integer_part(&self) -> U256 {
+        self.0 >> FRACTION_BITS
+    }
+
+    /// Fractional part of the number (x64).
+    pub fn fractional_part(&self) -> U256 {
+        self.0 & FRACTION_MASK
+    }
+
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        self.0.to_be_bytes()
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> FixedX64 {
+        FixedX64(U256::from_be_bytes(bytes))
+    }
+}
+
+impl From<U256> for FixedX64 {
+    fn from(value: U256) -> Self {
+        FixedX64(value)
+    }
+}
+
+impl From<FixedX64> for U256 {
+    fn from(value: FixedX64) -> Self {
+        value.0
+    }
+}
+
+/// Renders the exact decimal value. A precision (e.g. '{:.6}') truncates (does not round) the fractional digits.
+impl fmt::Display for FixedX64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // fraction / 2^64 = fraction · 5^64 / 10^64. No overflow, as fraction · 5^64 < 10^64
+        // NOTE: U256's Display implementation ignores the formatter's width, hence the explicit padding
+        let fraction_digits = (self.fractional_part() * FIVE_POW_64).to_string();
+        let fraction_digits = "0".repeat(FRACTION_DIGITS - fraction_digits.len()) + &fraction_digits;
+
+        let fraction_digits = match f.precision() {
+            Some(precision) if precision <= FRACTION_DIGITS => fraction_digits[..precision].to_string(),
+            Some(precision) => format!("{:0<width$}", fraction_digits, width = precision),
+            None            => fraction_digits.trim_end_matches('0').to_string()
+        };
+
+        if fraction_digits.is_empty() {
+            return write!(f, "{}", self.integer_part())
+        }
+
+        write!(f, "{}.{}", self.integer_part(), fraction_digits)
+    }
+}
+
+impl fmt::LowerHex for FixedX64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl fmt::UpperHex for FixedX64 {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if f.alternate() {
+            write!(f, "0x")?;
+        }
+        write!(f, "{}", format!("{:x}", self.0).to_uppercase())
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParseFixedX64Error {
+    Empty,
+    InvalidDigit,
+    Overflow,
+}
+
+impl fmt::Display for ParseFixedX64Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseFixedX64Error::Empty        => write!(f, "cannot parse a fixed point number from an empty string"),
+            ParseFixedX64Error::InvalidDigit => write!(f, "invalid digit found in string"),
+            ParseFixedX64Error::Overflow     => write!(f, "number too large to fit in the x64 representation"),
+        }
+    }
+}
+
+impl std::error::Error for ParseFixedX64Error {}
+
+/// Parses a decimal number (e.g. '1.5', rounded down to the closest x64 value), or a raw hexadecimal value prefixed
+/// with '0x'.
+impl FromStr for FixedX64 {
+    type Err = ParseFixedX64Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(hex_digits) = s.strip_prefix("0x") {
+            return parse_digits(hex_digits, 16).map(FixedX64)
+        }
+
+        let (integer_digits, fraction_digits) = s.split_once('.').unwrap_or((s, ""));
+
+        if integer_digits.is_empty() && fraction_digits.is_empty() {
+            return Err(ParseFixedX64Error::Empty)
+        }
+
+        let integer = if integer_digits.is_empty() { U256::zero() } else { parse_digits(integer_digits, 10)? };
+        if integer.bits() > 256 - FRACTION_BITS {
+            return Err(ParseFixedX64Error::Overflow)
+        }
+
+        // Digits past the 64th do not affect the (rounded down) result, as any x64 fraction has at most 64 digits
+        if !fraction_digits.chars().all(|c| c.is_ascii_digit()) {
+            return Err(ParseFixedX64Error::InvalidDigit)
+        }
+        let fraction_digits = &fraction_digits[..fraction_digits.len().min(FRACTION_DIGITS)];
+
+        // fraction = digits · 2^64 / 10^digit_count. No overflow, as digits · 2^64 < 10^64 · 2^64 < 2^512
+        let fraction = if fraction_digits.is_empty() { U256::zero() } else {
+            let digits = U512::from_dec_str(fraction_digits).map_err(|_| ParseFixedX64Error::InvalidDigit)?;
+            let fraction = (digits << FRACTION_BITS) / U512::from(10u64).pow(U512::from(fraction_digits.len()));
+            U256([fraction.0[0], 0, 0, 0])   // fraction < 2^64
+        };
+
+        Ok(FixedX64((integer << FRACTION_BITS) | fraction))
+    }
+}
+
+fn parse_digits(digits: &str, radix: u32) -> Result<U256, ParseFixedX64Error> {
+    if digits.is_empty() {
+        return Err(ParseFixedX64Error::Empty)
+    }
+    if !digits.chars().all(|c| c.is_digit(radix)) {
+        return Err(ParseFixedX64Error::InvalidDigit)
+    }
+
+    U256::from_str_radix(digits, radix).map_err(|_| ParseFixedX64Error::Overflow)
+}
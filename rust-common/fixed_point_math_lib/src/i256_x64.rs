@@ -0,0 +1,162 @@
+use std::cmp::Ordering;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+
+use crate::u256::U256;
+use crate::fixed_point_math_x64::{mul_x64, div_x64, FixedPointError};
+
+const SIGN_BIT      : usize = 255;
+const MIN_MAGNITUDE : U256  = U256([0, 0, 0, 0x8000000000000000u64]);   // 2**255
+
+/// Signed x64 fixed point number (64 fractional bits), stored as the two's complement of a 256 bit integer. Serialises
+/// as the [u64; 4] limbs (least significant first), i.e. with the same layout as the unsigned x64 values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, BorshSerialize, BorshDeserialize)]
+pub struct I256X64(pub [u64; 4]);
+
+impl I256X64 {
+    pub const ZERO : I256X64 = I256X64([0, 0, 0, 0]);
+    pub const ONE  : I256X64 = I256X64([0, 1, 0, 0]);
+    pub const MAX  : I256X64 = I256X64([0xFFFFFFFFFFFFFFFFu64, 0xFFFFFFFFFFFFFFFFu64, 0xFFFFFFFFFFFFFFFFu64, 0x7FFFFFFFFFFFFFFFu64]);
+    pub const MIN  : I256X64 = I256X64([0, 0, 0, 0x8000000000000000u64]);
+
+    /// Builds the number from its sign and (x64) magnitude.
+    pub fn from_magnitude(negative: bool, magnitude: U256) -> Result<I256X64, FixedPointError> {
+        if negative {
+            // The magnitude of the minimum value (2**255) has no positive counterpart
+            if magnitude > MIN_MAGNITUDE { return Err(FixedPointError::Overflow) }
+
+            return Ok(I256X64((!magnitude).overflowing_add(U256::one()).0.0))
+        }
+
+        if magnitude >= MIN_MAGNITUDE { return Err(FixedPointError::Overflow) }
+
+        Ok(I256X64(magnitude.0))
+    }
+
+    pub fn is_negative(&self) -> bool {
+        self.raw().bit(SIGN_BIT)
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.raw().is_zero()
+    }
+
+    /// Absolute value of the number (x64). Cannot overflow, as the magnitude of the minimum value is 2**255.
+    pub fn magnitude(&self) -> U256 {
+        if self.is_negative() {
+            return (!self.raw()).overflowing_add(U256::one()).0
+        }
+        self.raw()
+    }
+
+    pub fn checked_neg(self) -> Result<I256X64, FixedPointError> {
+        I256X64::from_magnitude(!self.is_negative(), self.magnitude())
+    }
+
+    pub fn checked_add(self, other: I256X64) -> Result<I256X64, FixedPointError> {
+        let result = I256X64(self.raw().overflowing_add(other.raw()).0.0);
+
+        // Overflow if both operands have the same sign, and the result a different one
+        if self.is_negative() == other.is_negative() && result.is_negative() != self.is_negative() {
+            return Err(FixedPointError::Overflow)
+        }
+
+        Ok(result)
+    }
+
+    pub fn checked_sub(self, other: I256X64) -> Result<I256X64, FixedPointError> {
+        let result = I256X64(self.raw().overflowing_sub(other.raw()).0.0);
+
+        // Overflow if the operands have different signs, and the result a different sign than the minuend
+        if self.is_negative() != other.is_negative() && result.is_negative() != self.is_negative() {
+            return Err(FixedPointError::Overflow)
+        }
+
+        Ok(result)
+    }
+
+    /// Fixed point (x64) multiplication.
+    pub fn checked_mul(self, other: I256X64) -> Result<I256X64, FixedPointError> {
+        I256X64::from_magnitude(
+            self.is_negative() != other.is_negative(),
+            mul_x64(self.magnitude(), other.magnitude())?
+        )
+    }
+
+    /// Fixed point (x64) division.
+    pub fn checked_div(self, other: I256X64) -> Result<I256X64, FixedPointError> {
+        I256X64::from_magnitude(
+            self.is_negative() != other.is_negative(),
+            div_x64(self.magnitude(), other.magnitude())?
+        )
+    }
+
+    /// Adds an unsigned x64 value.
+    pub fn checked_add_u256(self, other: U256) -> Result<I256X64, FixedPointError> {
+        self.checked_add(I256X64::try_from(other)?)
+    }
+
+    /// Subtracts an unsigned x64 value.
+    pub fn checked_sub_u256(self, other: U256) -> Result<I256X64, FixedPointError> {
+        self.checked_sub(I256X64::try_from(other)?)
+    }
+
+    fn raw(&self) -> U256 {
+        U256(self.0)
+    }
+}
+
+impl Ord for I256X64 {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.is_negative(), other.is_negative()) {
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            _             => self.raw().cmp(&other.raw())     // Two's complement preserves the order within the same sign
+        }
+    }
+}
+
+impl PartialOrd for I256X64 {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl TryFrom<U256> for I256X64 {
+    type Error = FixedPointError;
+
+    fn try_from(value: U256) -> Result<Self, Self::Error> {
+        I256X64::from_magnitude(false, value)
+    }
+}
+
+impl TryFrom<I256X64> for U256 {
+    type Error = FixedPointError;
+
+    fn try_from(value: I256X64) -> Result<Self, Self::Error> {
+        if value.is_negative() { return Err(FixedPointError::Overflow) }
+        Ok(value.raw())
+    }
+}
+
+/// Interprets the i128 as a (x64) fixed point number.
+impl From<i128> for I256X64 {
+    fn from(value: i128) -> Self {
+        let limbs = value as u128;
+        let extension = if value < 0 { 0xFFFFFFFFFFFFFFFFu64 } else { 0 };
+
+        I256X64([limbs as u64, (limbs >> 64) as u64, extension, extension])
+    }
+}
+
+impl TryFrom<I256X64> for i128 {
+    type Error = FixedPointError;
+
+    fn try_from(value: I256X64) -> Result<Self, Self::Error> {
+        if value < I256X64::from(i128::MIN) || value > I256X64::from(i128::MAX) {
+            return Err(FixedPointError::Overflow)
+        }
+
+        Ok(((value.0[0] as u128) | ((value.0[1] as u128) << 64)) as i128)
+    }
+}
@@ -1,4 +1,6 @@
 pub mod fixed_point_math_x64;
+pub mod fixed_x64;
+pub mod i256_x64;
 pub mod u256;
 
 #[cfg(test)]
@@ -10,6 +12,10 @@ pub mod test {
     pub mod test_log;
 
     pub mod test_mul;
+    pub mod test_mul_legacy;
     pub mod test_div;
     pub mod test_pow;
+
+    pub mod test_i256_x64;
+    pub mod test_fixed_x64;
 }
\ No newline at end of file
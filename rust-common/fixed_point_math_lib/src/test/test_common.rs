@@ -3,6 +3,7 @@
 pub mod test_common {
     use std::ops::Shr;
     use crate::u256::U256;
+    use crate::fixed_point_math_x64::FixedPointError;
 
     use std::{ops::{Shl, Sub}, f64::{INFINITY, NEG_INFINITY}, fmt};
 
@@ -293,7 +294,7 @@ pub mod test_common {
         pub max_abs_error   : f64,
         pub avg_abs_error   : f64,
         pub eval_points     : Vec<T>,
-        pub calc_points     : Vec<Result<U256, ()>>,
+        pub calc_points     : Vec<Result<U256, FixedPointError>>,
         pub target_points   : Vec<Result<U256, ()>>,
         pub relative_errors : Vec<Result<EvalRelError, EvalError>>,
         pub valid_count     : u64,
@@ -323,12 +324,12 @@ pub mod test_common {
     }
 
     pub fn evaluate_impl<T> (
-        impl_fn: fn(&T) -> Result<U256, ()>,
+        impl_fn: fn(&T) -> Result<U256, FixedPointError>,
         target_fn: fn(&T) -> Result<U256, ()>,
         eval_points: Vec<T>
     ) -> EvaluateImplResult<T> {
 
-        let calc_points   : Vec<Result<U256, ()>> = eval_points.iter().map(|p_x64| impl_fn(p_x64)).collect();
+        let calc_points   : Vec<Result<U256, FixedPointError>> = eval_points.iter().map(|p_x64| impl_fn(p_x64)).collect();
         let target_points : Vec<Result<U256, ()>> = eval_points.iter().map(|p_x64| target_fn(p_x64)).collect();
 
         let mut high_error: f64 = NEG_INFINITY;
@@ -343,7 +344,7 @@ pub mod test_common {
 
         let relative_errors: Vec<Result<EvalRelError, EvalError>> = calc_points.iter().zip(&target_points).map(|(eval, target)| -> Result<EvalRelError, EvalError> {
             match (eval, target) {
-                (Err(()), Err(())) => {
+                (Err(_), Err(())) => {
                     expected_none_count += 1;
                     Ok(None)
                 },
@@ -367,7 +368,7 @@ pub mod test_common {
                     invalid_count_expected_none += 1;
                     Err(EvalError::CalcForInvalidTarget)
                 },
-                (Err(()), Ok(_)) => {
+                (Err(_), Ok(_)) => {
                     expected_some_count += 1;
                     invalid_count_expected_some += 1;
                     Err(EvalError::NoCalcForValidTarget)
@@ -0,0 +1,98 @@
+#[cfg(test)]
+mod test_fixed_x64 {
+
+    use std::str::FromStr;
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    use crate::u256::U256;
+    use crate::fixed_x64::{FixedX64, ParseFixedX64Error};
+    use crate::fixed_point_math_x64::*;
+
+
+
+    // Test serialisation *******************************************************************************************************
+
+    #[test]
+    fn test_borsh_serialisation() {
+        let value = U256([1, 2, 3, 4]);
+
+        let serialised = value.try_to_vec().unwrap();
+        assert_eq!(serialised, value.0.try_to_vec().unwrap());    // Same layout as the [u64; 4] limbs
+        assert_eq!(U256::try_from_slice(&serialised).unwrap(), value);
+
+        let fixed = FixedX64(value);
+        assert_eq!(fixed.try_to_vec().unwrap(), serialised);
+        assert_eq!(FixedX64::try_from_slice(&serialised).unwrap(), fixed);
+    }
+
+    #[test]
+    fn test_be_bytes() {
+        let value = U256([1, 2, 3, 0x0400000000000000u64]);
+
+        let bytes = value.to_be_bytes();
+        assert_eq!(bytes[0], 4);
+        assert_eq!(bytes[31], 1);
+        assert_eq!(bytes[23], 2);
+        assert_eq!(U256::from_be_bytes(bytes), value);
+        assert_eq!(FixedX64::from_be_bytes(bytes), FixedX64(value));
+    }
+
+
+
+    // Test string conversions **************************************************************************************************
+
+    #[test]
+    fn test_display() {
+        assert_eq!(FixedX64::ZERO.to_string(), "0");
+        assert_eq!(FixedX64::ONE.to_string(), "1");
+        assert_eq!(FixedX64(ONE_X64 * 3 / 2).to_string(), "1.5");
+        assert_eq!(FixedX64(ONE_X64 * 1000 + ONE_X64 / 8).to_string(), "1000.125");
+
+        // Exact representation of 2^-64
+        assert_eq!(FixedX64(U256::one()).to_string(), "0.0000000000000000000542101086242752217003726400434970855712890625");
+
+        // Precision truncates the fractional digits
+        assert_eq!(format!("{:.3}", FixedX64(ONE_X64 * 2 / 3)), "0.666");
+        assert_eq!(format!("{:.0}", FixedX64(ONE_X64 * 5 / 2)), "2");
+        assert_eq!(format!("{:.4}", FixedX64(ONE_X64 / 2)), "0.5000");
+    }
+
+    #[test]
+    fn test_hex_format() {
+        assert_eq!(format!("{:x}", FixedX64(ONE_X64 * 3 / 2)), "18000000000000000");
+        assert_eq!(format!("{:#X}", FixedX64(ONE_X64 * 3 / 2 + 10)), "0x1800000000000000A");
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert_eq!(FixedX64::from_str("1.5"), Ok(FixedX64(ONE_X64 * 3 / 2)));
+        assert_eq!(FixedX64::from_str("42"), Ok(FixedX64(ONE_X64 * 42)));
+        assert_eq!(FixedX64::from_str(".25"), Ok(FixedX64(ONE_X64 / 4)));
+        assert_eq!(FixedX64::from_str("0x18000000000000000"), Ok(FixedX64(ONE_X64 * 3 / 2)));
+
+        // Rounds down
+        assert_eq!(FixedX64::from_str("0.1"), Ok(FixedX64(ONE_X64 / 10)));
+        assert_eq!(FixedX64::from_str("0.00000000000000000005"), Ok(FixedX64::ZERO));
+
+        assert_eq!(FixedX64::from_str(""), Err(ParseFixedX64Error::Empty));
+        assert_eq!(FixedX64::from_str("."), Err(ParseFixedX64Error::Empty));
+        assert_eq!(FixedX64::from_str("0x"), Err(ParseFixedX64Error::Empty));
+        assert_eq!(FixedX64::from_str("1.5a"), Err(ParseFixedX64Error::InvalidDigit));
+        assert_eq!(FixedX64::from_str("-1"), Err(ParseFixedX64Error::InvalidDigit));
+        assert_eq!(FixedX64::from_str("1.2.3"), Err(ParseFixedX64Error::InvalidDigit));
+        assert_eq!(FixedX64::from_str(&(U256::one() << 192).to_string()), Err(ParseFixedX64Error::Overflow));
+    }
+
+    #[test]
+    fn test_string_round_trip() {
+        let values = [U256::zero(), U256::one(), ONE_X64 / 3, ONE_X64 * 12345 + 6789, U256_MAX];
+
+        for value in values {
+            let fixed = FixedX64(value);
+            assert_eq!(FixedX64::from_str(&fixed.to_string()), Ok(fixed));
+            assert_eq!(FixedX64::from_str(&format!("{:#x}", fixed)), Ok(fixed));
+        }
+    }
+
+}
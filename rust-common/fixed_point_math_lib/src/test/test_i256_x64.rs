@@ -0,0 +1,101 @@
+#[cfg(test)]
+mod test_i256_x64 {
+
+    use borsh::{BorshDeserialize, BorshSerialize};
+
+    use crate::u256::U256;
+    use crate::i256_x64::I256X64;
+    use crate::fixed_point_math_x64::*;
+
+    fn signed_x64(negative: bool, magnitude: U256) -> I256X64 {
+        I256X64::from_magnitude(negative, magnitude).unwrap()
+    }
+
+
+
+    // Test conversions *********************************************************************************************************
+
+    #[test]
+    fn test_u256_conversions() {
+        let value = ONE_X64 * 3 / 2;
+
+        assert_eq!(U256::try_from(I256X64::try_from(value).unwrap()), Ok(value));
+        assert_eq!(I256X64::try_from(U256_MAX), Err(FixedPointError::Overflow));
+        assert_eq!(I256X64::try_from(U256::one() << 255), Err(FixedPointError::Overflow));
+        assert_eq!(U256::try_from(signed_x64(true, ONE_X64)), Err(FixedPointError::Overflow));
+    }
+
+    #[test]
+    fn test_i128_conversions() {
+        for value in [0_i128, 1, -1, 1 << 64, -(1 << 64), i128::MAX, i128::MIN] {
+            assert_eq!(i128::try_from(I256X64::from(value)), Ok(value));
+        }
+
+        assert_eq!(I256X64::from(-(1_i128 << 64)), signed_x64(true, ONE_X64));
+        assert_eq!(i128::try_from(I256X64::MAX), Err(FixedPointError::Overflow));
+        assert_eq!(i128::try_from(I256X64::MIN), Err(FixedPointError::Overflow));
+    }
+
+    #[test]
+    fn test_magnitude() {
+        assert_eq!(I256X64::MIN.magnitude(), U256::one() << 255);
+        assert_eq!(I256X64::MAX.magnitude(), (U256::one() << 255) - 1);
+        assert_eq!(signed_x64(true, ONE_X64).magnitude(), ONE_X64);
+        assert!(signed_x64(true, ONE_X64).is_negative());
+        assert!(!signed_x64(true, ZERO_X64).is_negative());
+        assert!(signed_x64(true, ZERO_X64).is_zero());
+    }
+
+    #[test]
+    fn test_borsh_serialisation() {
+        let value = signed_x64(true, ONE_X64 * 5 / 4);
+
+        let serialised = value.try_to_vec().unwrap();
+        assert_eq!(serialised.len(), 32);
+        assert_eq!(I256X64::try_from_slice(&serialised).unwrap(), value);
+
+        // Same layout as the unsigned [u64; 4] values
+        assert_eq!(I256X64::ONE.try_to_vec().unwrap(), ONE_X64.0.try_to_vec().unwrap());
+    }
+
+
+
+    // Test arithmetic **********************************************************************************************************
+
+    #[test]
+    fn test_ordering() {
+        let mut values = vec![I256X64::MAX, I256X64::ONE, signed_x64(true, ONE_X64), I256X64::MIN, I256X64::ZERO];
+        values.sort();
+
+        assert_eq!(values, vec![I256X64::MIN, signed_x64(true, ONE_X64), I256X64::ZERO, I256X64::ONE, I256X64::MAX]);
+    }
+
+    #[test]
+    fn test_add_sub() {
+        let two   = signed_x64(false, ONE_X64 * 2);
+        let three = signed_x64(false, ONE_X64 * 3);
+
+        assert_eq!(two.checked_sub(three), Ok(signed_x64(true, ONE_X64)));
+        assert_eq!(two.checked_sub(three).unwrap().checked_add(three), Ok(two));
+        assert_eq!(I256X64::ZERO.checked_sub_u256(ONE_X64).unwrap().checked_add_u256(ONE_X64 * 3), Ok(two));
+
+        assert_eq!(I256X64::MAX.checked_add(I256X64::ONE), Err(FixedPointError::Overflow));
+        assert_eq!(I256X64::MIN.checked_sub(I256X64::ONE), Err(FixedPointError::Overflow));
+        assert_eq!(I256X64::MIN.checked_add(I256X64::MAX), Ok(signed_x64(true, U256::one())));
+        assert_eq!(I256X64::MIN.checked_neg(), Err(FixedPointError::Overflow));
+        assert_eq!(I256X64::MAX.checked_neg().unwrap().checked_sub(I256X64::MIN), Ok(signed_x64(false, U256::one())));
+    }
+
+    #[test]
+    fn test_mul_div() {
+        let minus_half = signed_x64(true, ONE_X64 / 2);
+        let three      = signed_x64(false, ONE_X64 * 3);
+
+        assert_eq!(minus_half.checked_mul(three), Ok(signed_x64(true, ONE_X64 * 3 / 2)));
+        assert_eq!(minus_half.checked_mul(minus_half), Ok(signed_x64(false, ONE_X64 / 4)));
+        assert_eq!(three.checked_div(minus_half), Ok(signed_x64(true, ONE_X64 * 6)));
+        assert_eq!(three.checked_div(I256X64::ZERO), Err(FixedPointError::DivisionByZero));
+        assert_eq!(I256X64::MAX.checked_mul(three), Err(FixedPointError::Overflow));
+    }
+
+}
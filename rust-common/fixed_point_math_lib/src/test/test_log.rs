@@ -64,6 +64,14 @@ mod test_log {
         Ok(())  
     }
 
+    /// Test the errors returned by log2_x64
+    #[test]
+    fn test_log2_errors() {
+        assert_eq!(log2_x64(ZERO_X64), Err(FixedPointError::LogOfZero));
+        assert_eq!(log2_x64(ONE_X64 - 1), Err(FixedPointError::Overflow));     // Negative output
+        assert_eq!(log2_x64(ONE_X64), Ok(ZERO_X64));
+    }
+
     /// Test log2_x64 for a random set of numbers (all expected to generate valid outputs)
     #[test]
     fn test_log2_randrange_all_valid() -> Result<(), ()> {
@@ -207,6 +215,149 @@ mod test_log {
 
 
 
+    // Test log2_x64_with_precision *****************************************************************************************************
+
+    const PRECISIONS: [Precision; 3] = [Precision::Bits32, Precision::Bits48, Precision::Bits64];
+
+    /// Max relative error bound for outputs larger than 'min_output' (the absolute error is bounded by the last computed bit)
+    fn log2_with_precision_max_error_bound(precision: Precision, min_output: f64) -> f64 {
+        // Relative error of the f64 conversions used by get_rel_error (~2**-52)
+        1e-15_f64.max(2_f64.powi(-(precision.fractional_bits() as i32)) / min_output) * 2.
+    }
+
+    fn log2_with_precision_impl(precision: Precision) -> fn(&U256) -> Result<U256, FixedPointError> {
+        match precision {
+            Precision::Bits32 => |a: &U256| log2_x64_with_precision(*a, Precision::Bits32),
+            Precision::Bits48 => |a: &U256| log2_x64_with_precision(*a, Precision::Bits48),
+            Precision::Bits64 => |a: &U256| log2_x64_with_precision(*a, Precision::Bits64),
+        }
+    }
+
+    /// Test log2_x64_with_precision for a set of interest points
+    #[test]
+    fn test_log2_with_precision_poi() -> Result<(), ()> {
+
+        let mut points_of_interest_x64 = vec![
+            ZERO_X64,       // Must fail
+            U256::one(),    // Must fail
+            ONE_X64 - 1,    // Must fail
+            ONE_X64,
+            U256_MAX,
+        ];
+        points_of_interest_x64.append(&mut get_powers_of_2_x64(-64_i64, 0, 1));             // Must fail
+        points_of_interest_x64.append(&mut get_powers_of_2_x64(0, 256-64, 1));
+        points_of_interest_x64.append(&mut get_powers_of_2_minus_1_x64(-64_i64, 1, 1));     // Must fail
+        points_of_interest_x64.append(&mut get_powers_of_2_minus_1_x64(1, 256-64+1, 1));
+
+        let points_of_interest_x64 = remove_duplicates_and_sort(points_of_interest_x64);
+
+        for precision in PRECISIONS {
+            let result = evaluate_impl(
+                log2_with_precision_impl(precision),
+                |a: &U256| target_log2_x64(*a).map_err(|_err| ()),
+                points_of_interest_x64.clone()
+            );
+
+            println!("\nlog2_x64_with_precision ({:?}) - Points of interest\n{}", precision, result);
+
+            // The smallest non-zero output is log2(2 - 2^-64) ~ 1
+            assert!(result.max_abs_error <= log2_with_precision_max_error_bound(precision, 0.5));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+        }
+
+        Ok(())
+    }
+
+    /// Test log2_x64_with_precision for a random set of numbers (all expected to generate valid outputs)
+    #[test]
+    fn test_log2_with_precision_randrange() -> Result<(), ()> {
+
+        let rand_sample_x64 = sample_space(
+            ONE_X64 + U256::from(2_u64.pow(54_u32)),
+            U256_MAX,
+            200000_usize
+        );
+
+        for precision in PRECISIONS {
+            let result = evaluate_impl(
+                log2_with_precision_impl(precision),
+                |a: &U256| target_log2_x64(*a).map_err(|_err| ()),
+                rand_sample_x64.clone()
+            );
+
+            println!("\nlog2_x64_with_precision ({:?}) - Randrange [ 1_x64 + 2^(-10)_x64, MAX_x64 )\n{}", precision, result);
+
+            // log2(1 + 2^-10) > 2^-10
+            assert!(result.max_abs_error <= log2_with_precision_max_error_bound(precision, 2_f64.powi(-10)));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+
+            assert!(result.expected_none_count == 0);
+        }
+
+        Ok(())
+    }
+
+    /// Test log2_x64_with_precision for a random set of numbers close to 1_x64 (where log2_x64 is inaccurate)
+    #[test]
+    fn test_log2_with_precision_randrange_close_to_1() -> Result<(), ()> {
+
+        let rand_sample_x64 = sample_space(
+            ONE_X64 + U256::from(2_u64.pow(37_u32)),
+            ONE_X64 + U256::from(2_u64.pow(54_u32)),
+            200000_usize
+        );
+
+        for precision in PRECISIONS {
+            let result = evaluate_impl(
+                log2_with_precision_impl(precision),
+                |a: &U256| target_log2_x64(*a).map_err(|_err| ()),
+                rand_sample_x64.clone()
+            );
+
+            println!("\nlog2_x64_with_precision ({:?}) - Randrange [ 1_x64 + 2^(-27)_x64, 1_x64 + 2^(-10)_x64 )\n{}", precision, result);
+
+            // log2(1 + 2^-27) > 2^-27
+            assert!(result.max_abs_error <= log2_with_precision_max_error_bound(precision, 2_f64.powi(-27)));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+
+            assert!(result.expected_none_count == 0);
+        }
+
+        Ok(())
+    }
+
+    /// Test that lower precisions compute the same leading fractional bits as higher ones
+    #[test]
+    fn test_log2_with_precision_truncation() {
+
+        let rand_sample_x64 = sample_space(ONE_X64, U256_MAX, 10000_usize);
+
+        for a in rand_sample_x64 {
+            let result = log2_x64_with_precision(a, Precision::Bits64).unwrap();
+
+            for precision in PRECISIONS {
+                let truncation_mask = !((U256::one() << (64 - precision.fractional_bits())) - 1);
+                assert_eq!(log2_x64_with_precision(a, precision), Ok(result & truncation_mask));
+            }
+        }
+    }
+
+    /// Test the errors returned by log2_x64_with_precision
+    #[test]
+    fn test_log2_with_precision_errors() {
+        for precision in PRECISIONS {
+            assert_eq!(log2_x64_with_precision(ZERO_X64, precision), Err(FixedPointError::LogOfZero));
+            assert_eq!(log2_x64_with_precision(ONE_X64 - 1, precision), Err(FixedPointError::Overflow));     // Negative output
+            assert_eq!(log2_x64_with_precision(ONE_X64, precision), Ok(ZERO_X64));
+            assert_eq!(log2_x64_with_precision(ONE_X64 << 191, precision), Ok(ONE_X64 * 191));
+        }
+    }
+
+
+
     // Test ln_x64 **************************************************************************************************************
 
     // Set test bounds
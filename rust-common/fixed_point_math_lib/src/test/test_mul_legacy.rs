@@ -0,0 +1,119 @@
+#[cfg(test)]
+mod test_mul_legacy {
+    use rand::Rng;
+
+    use crate::u256::U256;
+    use crate::fixed_point_math_x64::*;
+
+
+
+    // Legacy implementation ***************************************************************************************************
+
+    // Copy of the former (mulmod based) implementation of mul_x64, as kept in the TestProgramFixedPointMath program
+    // (solana/programs/TestProgramFixedPointMath/src/legacy.rs)
+
+    const P_XX      : u64  = 64;
+    const P_XX_MAX  : U256 = U256([0xFFFFFFFFFFFFFFFFu64, 0, 0, 0]);
+    const P_XX_ONE  : U256 = U256([1, 0, 0, 0]);
+
+    /// Full 256x256 bit multiplication of the legacy implementation. Returns the (low, high) 256 bits of the product.
+    fn legacy_full_mul(a: U256, b: U256) -> (U256, U256) {
+        let (r0, _) = a.overflowing_mul(b);
+        let r1 = mulmod(a, b, U256::max_value());
+
+        (r0, r1.overflowing_sub(r0).0 - U256::from((r1 < r0) as u64))
+    }
+
+    fn legacy_mul_x64(a: U256, b: U256) -> Option<U256> {
+        let (r0, r1) = legacy_full_mul(a, b);
+
+        if r1 > P_XX_MAX { return None; }
+
+        Some((r1 << (256-P_XX)) + (r0 >> P_XX))
+    }
+
+    fn mulmod(a: U256, b: U256, m: U256) -> U256 {
+        let mut res = U256::zero();
+        let mut a   = a;
+        let mut b   = b;
+
+        if b >= m {
+            if m > (U256::max_value() >> P_XX_ONE) { b -= m }
+            else { b %= m };
+        }
+
+        while !a.is_zero() {
+            if !(a & P_XX_ONE).is_zero() {
+                if b >= m - res {
+                    res = res.overflowing_sub(m).0;
+                }
+                res = res.overflowing_add(b).0;
+            }
+            a >>= P_XX_ONE;
+
+            let mut temp_b = b;
+            if b >= m - b {
+                temp_b = temp_b.overflowing_sub(m).0
+            }
+            b = b.overflowing_add(temp_b).0;
+        }
+
+        res
+    }
+
+
+
+    // Test against the legacy implementation **********************************************************************************
+
+    const SAMPLE_COUNT: usize = 20000;
+
+    /// Random number with a random bit length, so that both small and large magnitudes (and thus both valid and
+    /// overflowing products) are sampled.
+    fn rand_u256(rng: &mut impl Rng) -> U256 {
+        let value = U256([rng.gen(), rng.gen(), rng.gen(), rng.gen()]);
+        let bits  = rng.gen_range(0..=256usize);
+
+        if bits == 0 { return U256::zero() }
+
+        value >> (256 - bits)
+    }
+
+    /// Compare full_mul with the legacy implementation for random numbers
+    #[test]
+    fn test_full_mul_legacy_randrange() {
+        let mut rng = rand::thread_rng();
+
+        for _ in 0..SAMPLE_COUNT {
+            let a = rand_u256(&mut rng);
+            let b = rand_u256(&mut rng);
+
+            let r = full_mul(a, b);
+            let (legacy_low, legacy_high) = legacy_full_mul(a, b);
+
+            assert_eq!(U256([r[0], r[1], r[2], r[3]]), legacy_low, "full_mul low bits mismatch for a: {}, b: {}", a, b);
+            assert_eq!(U256([r[4], r[5], r[6], r[7]]), legacy_high, "full_mul high bits mismatch for a: {}, b: {}", a, b);
+        }
+    }
+
+    /// Compare mul_x64 with the legacy implementation for random numbers (both valid and overflowing products)
+    #[test]
+    fn test_mul_x64_legacy_randrange() {
+        let mut rng = rand::thread_rng();
+
+        let mut overflow_count = 0;
+        for _ in 0..SAMPLE_COUNT {
+            let a = rand_u256(&mut rng);
+            let b = rand_u256(&mut rng);
+
+            let result = mul_x64(a, b).ok();
+            assert_eq!(result, legacy_mul_x64(a, b), "mul_x64 mismatch for a: {}, b: {}", a, b);
+
+            if result.is_none() { overflow_count += 1 }
+        }
+
+        // Make sure both outcomes have been sampled
+        assert!(overflow_count > 0);
+        assert!(overflow_count < SAMPLE_COUNT);
+    }
+
+}
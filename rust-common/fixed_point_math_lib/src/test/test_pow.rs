@@ -819,6 +819,75 @@ mod test_pow {
 
         Ok(())  
     }
-    
 
-}
\ No newline at end of file
+
+
+    // Test pow_x64_with_precision and inv_pow_x64_with_precision ***************************************************************
+
+    const PRECISIONS: [Precision; 3] = [Precision::Bits32, Precision::Bits48, Precision::Bits64];
+
+    /// Max relative error bound for p < 2: the log2 truncation error (scaled by p) and the pow2 truncation error each
+    /// contribute a relative error < 2·ln(2)·2^-bits
+    fn pow_with_precision_max_error_bound(precision: Precision) -> f64 {
+        // Relative error of the f64 conversions used by get_rel_error (~2**-52)
+        1e-15_f64.max(2_f64.powi(-(precision.fractional_bits() as i32))) * 4.
+    }
+
+    fn pow_with_precision_impl(precision: Precision) -> fn(&(U256, U256)) -> Result<U256, FixedPointError> {
+        match precision {
+            Precision::Bits32 => |(a, b): &(U256, U256)| pow_x64_with_precision(*a, *b, Precision::Bits32),
+            Precision::Bits48 => |(a, b): &(U256, U256)| pow_x64_with_precision(*a, *b, Precision::Bits48),
+            Precision::Bits64 => |(a, b): &(U256, U256)| pow_x64_with_precision(*a, *b, Precision::Bits64),
+        }
+    }
+
+    fn inv_pow_with_precision_impl(precision: Precision) -> fn(&(U256, U256)) -> Result<U256, FixedPointError> {
+        match precision {
+            Precision::Bits32 => |(a, b): &(U256, U256)| inv_pow_x64_with_precision(*a, *b, Precision::Bits32),
+            Precision::Bits48 => |(a, b): &(U256, U256)| inv_pow_x64_with_precision(*a, *b, Precision::Bits48),
+            Precision::Bits64 => |(a, b): &(U256, U256)| inv_pow_x64_with_precision(*a, *b, Precision::Bits64),
+        }
+    }
+
+    /// Test pow_x64_with_precision and inv_pow_x64_with_precision for a random set of numbers - small range of x and y
+    #[test]
+    fn test_pow_with_precision_randrange() -> Result<(), ()> {
+
+        let points_of_interest_x64: Vec<(U256, U256)> = get_pow_randrange_only_valid(
+            200000_usize,
+            ONE_X64,
+            U256::from(8) * ONE_X64,
+            U256::zero(),
+            U256::from(2) * ONE_X64,
+        );
+
+        for precision in PRECISIONS {
+            let result = evaluate_impl(
+                pow_with_precision_impl(precision),
+                |(a, b): &(U256, U256)| target_pow_x64(*a, *b).map_err(|_err| ()),
+                points_of_interest_x64.clone()
+            );
+
+            println!("\npow_x64_with_precision ({:?}) - Randrange x: [1_x64, 8_x64), p: [0, 2_x64)\n{}", precision, result);
+
+            assert!(result.max_abs_error <= pow_with_precision_max_error_bound(precision));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+
+            let result = evaluate_impl(
+                inv_pow_with_precision_impl(precision),
+                |(a, b): &(U256, U256)| target_inv_pow_x64(*a, *b).map_err(|_err| ()),
+                points_of_interest_x64.clone()
+            );
+
+            println!("\ninv_pow_x64_with_precision ({:?}) - Randrange x: [1_x64, 8_x64), p: [0, 2_x64)\n{}", precision, result);
+
+            assert!(result.max_abs_error <= pow_with_precision_max_error_bound(precision));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+        }
+
+        Ok(())
+    }
+
+}
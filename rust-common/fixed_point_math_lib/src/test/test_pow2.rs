@@ -68,6 +68,16 @@ mod test_pow2 {
         Ok(())  
     }
 
+    /// Test the errors returned by pow2_x64 and inv_pow2_x64
+    #[test]
+    fn test_pow2_errors() {
+        assert_eq!(pow2_x64(ONE_X64 * 192), Err(FixedPointError::ExponentTooLarge { max_exponent: 191 }));
+        assert!(pow2_x64(ONE_X64 * 191).is_ok());
+
+        assert_eq!(inv_pow2_x64(ONE_X64 * 41), Err(FixedPointError::PrecisionLoss { max_exponent: 40 }));
+        assert!(inv_pow2_x64(ONE_X64 * 40).is_ok());
+    }
+
     /// Test pow2_x64 for a random set of numbers
     #[test]
     fn test_pow2_randrange() -> Result<(), ()> {
@@ -123,6 +133,98 @@ mod test_pow2 {
 
 
 
+    // Test pow2_x64_with_precision *****************************************************************************************************
+
+    const PRECISIONS: [Precision; 3] = [Precision::Bits32, Precision::Bits48, Precision::Bits64];
+
+    /// Max relative error bound: the ignored fractional bits of the exponent contribute a relative error < ln(2)·2^-bits
+    fn pow2_with_precision_max_error_bound(precision: Precision) -> f64 {
+        // Relative error of the f64 conversions used by get_rel_error (~2**-52)
+        1e-15_f64.max(2_f64.powi(-(precision.fractional_bits() as i32))) * 2.
+    }
+
+    fn pow2_with_precision_impl(precision: Precision) -> fn(&U256) -> Result<U256, FixedPointError> {
+        match precision {
+            Precision::Bits32 => |a: &U256| pow2_x64_with_precision(*a, Precision::Bits32),
+            Precision::Bits48 => |a: &U256| pow2_x64_with_precision(*a, Precision::Bits48),
+            Precision::Bits64 => |a: &U256| pow2_x64_with_precision(*a, Precision::Bits64),
+        }
+    }
+
+    /// Test pow2_x64_with_precision for a set of interest points
+    #[test]
+    fn test_pow2_with_precision_poi() -> Result<(), ()> {
+
+        let max_theoretical_input = (U256::from(256 - 64) * ONE_X64) - 1;
+
+        let mut points_of_interest_x64 = vec![
+            ZERO_X64,
+            ONE_X64,
+            max_theoretical_input,      // Should work
+            max_theoretical_input + 1,  // Should fail
+            U256_MAX
+        ];
+        points_of_interest_x64.append(&mut get_powers_of_2_x64(-64_i64, 10, 1));
+        points_of_interest_x64.append(&mut get_powers_of_2_minus_1_x64(-64_i64, 10, 1));
+
+        let points_of_interest_x64 = remove_duplicates_and_sort(points_of_interest_x64);
+
+        for precision in PRECISIONS {
+            let result = evaluate_impl(
+                pow2_with_precision_impl(precision),
+                |a: &U256| target_pow2_x64(*a).map_err(|_err| ()),
+                points_of_interest_x64.clone()
+            );
+
+            println!("\npow2_x64_with_precision ({:?}) - Points of interest\n{}", precision, result);
+
+            assert!(result.max_abs_error <= pow2_with_precision_max_error_bound(precision));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+        }
+
+        Ok(())
+    }
+
+    /// Test pow2_x64_with_precision for a random set of numbers
+    #[test]
+    fn test_pow2_with_precision_randrange() -> Result<(), ()> {
+
+        let rand_sample_x64 = sample_space(
+            ZERO_X64,
+            ONE_X64.mul(U256::from(256)),   // Note values from 256-64 to 256 should fail
+            200000_usize
+        );
+
+        for precision in PRECISIONS {
+            let result = evaluate_impl(
+                pow2_with_precision_impl(precision),
+                |a: &U256| target_pow2_x64(*a).map_err(|_err| ()),
+                rand_sample_x64.clone()
+            );
+
+            println!("\npow2_x64_with_precision ({:?}) - Randrange [ 0, 256_x64 )\n{}", precision, result);
+
+            assert!(result.max_abs_error <= pow2_with_precision_max_error_bound(precision));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+        }
+
+        Ok(())
+    }
+
+    /// Test the errors returned by pow2_x64_with_precision
+    #[test]
+    fn test_pow2_with_precision_errors() {
+        for precision in PRECISIONS {
+            assert_eq!(pow2_x64_with_precision(ONE_X64 * 192, precision), Err(FixedPointError::ExponentTooLarge { max_exponent: 191 }));
+            assert_eq!(pow2_x64_with_precision(ONE_X64 * 191, precision), Ok(ONE_X64 << 191));
+            assert_eq!(pow2_x64_with_precision(ZERO_X64, precision), Ok(ONE_X64));
+        }
+    }
+
+
+
     // Test inv_pow2_x64 ********************************************************************************************************
 
     // Set test bounds
@@ -228,4 +330,92 @@ mod test_pow2 {
         Ok(())
     }
 
-}
\ No newline at end of file
+
+
+    // Test inv_pow2_x64_with_precision *****************************************************************************************
+
+    /// Max relative error bound: on top of the pow2 bound, the x64 output of 2^-40 only keeps 24 significant bits
+    fn inv_pow2_with_precision_max_error_bound(precision: Precision) -> f64 {
+        pow2_with_precision_max_error_bound(precision).max(2_f64.powi(-23))
+    }
+
+    fn inv_pow2_with_precision_impl(precision: Precision) -> fn(&U256) -> Result<U256, FixedPointError> {
+        match precision {
+            Precision::Bits32 => |a: &U256| inv_pow2_x64_with_precision(*a, Precision::Bits32),
+            Precision::Bits48 => |a: &U256| inv_pow2_x64_with_precision(*a, Precision::Bits48),
+            Precision::Bits64 => |a: &U256| inv_pow2_x64_with_precision(*a, Precision::Bits64),
+        }
+    }
+
+    /// Test inv_pow2_x64_with_precision for a set of interest points
+    #[test]
+    fn test_inv_pow2_with_precision_poi() -> Result<(), ()> {
+
+        let max_input = U256::from(41).mul(ONE_X64).sub(U256::one());   // 41*2**64 - 1
+
+        let mut points_of_interest_x64 = vec![
+            ZERO_X64,
+            ONE_X64,
+            max_input,
+            max_input + 1   // Should fail
+        ];
+        points_of_interest_x64.append(&mut get_powers_of_2_x64(-64_i64, 10, 1));
+        points_of_interest_x64.append(&mut get_powers_of_2_minus_1_x64(-64_i64, 10, 1));
+
+        let points_of_interest_x64 = remove_duplicates_and_sort(points_of_interest_x64);
+
+        for precision in PRECISIONS {
+            let result = evaluate_impl(
+                inv_pow2_with_precision_impl(precision),
+                |a: &U256| target_inv_pow2_x64(*a).map_err(|_err| ()),
+                points_of_interest_x64.clone()
+            );
+
+            println!("\ninv_pow2_x64_with_precision ({:?}) - Points of interest\n{}", precision, result);
+
+            assert!(result.max_abs_error <= inv_pow2_with_precision_max_error_bound(precision));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+        }
+
+        Ok(())
+    }
+
+    /// Test inv_pow2_x64_with_precision for a random set of numbers
+    #[test]
+    fn test_inv_pow2_with_precision_randrange() -> Result<(), ()> {
+
+        let rand_sample_x64 = sample_space(
+            ZERO_X64,
+            ONE_X64.mul(U256::from(55)),   // Note values larger than 41_x64 should fail
+            200000_usize
+        );
+
+        for precision in PRECISIONS {
+            let result = evaluate_impl(
+                inv_pow2_with_precision_impl(precision),
+                |a: &U256| target_inv_pow2_x64(*a).map_err(|_err| ()),
+                rand_sample_x64.clone()
+            );
+
+            println!("\ninv_pow2_x64_with_precision ({:?}) - Randrange [ 0, 55_x64 )\n{}", precision, result);
+
+            assert!(result.max_abs_error <= inv_pow2_with_precision_max_error_bound(precision));
+            assert!(result.invalid_count_expected_none == 0);
+            assert!(result.invalid_count_expected_some == 0);
+        }
+
+        Ok(())
+    }
+
+    /// Test the errors returned by inv_pow2_x64_with_precision
+    #[test]
+    fn test_inv_pow2_with_precision_errors() {
+        for precision in PRECISIONS {
+            assert_eq!(inv_pow2_x64_with_precision(ONE_X64 * 41, precision), Err(FixedPointError::PrecisionLoss { max_exponent: 40 }));
+            assert_eq!(inv_pow2_x64_with_precision(ONE_X64 * 40, precision), Ok(ONE_X64 >> 40));
+            assert_eq!(inv_pow2_x64_with_precision(ZERO_X64, precision), Ok(ONE_X64));
+        }
+    }
+
+}
@@ -1,5 +1,45 @@
+use std::io;
+use std::ops::BitOrAssign;
+
+use borsh::{BorshDeserialize, BorshSerialize};
 use uint::construct_uint;
 
 construct_uint! {
     pub struct U256(4);
-}
\ No newline at end of file
+}
+
+impl U256 {
+    /// Big-endian (i.e. EVM) byte representation of the number.
+    pub fn to_be_bytes(&self) -> [u8; 32] {
+        let mut bytes = [0u8; 32];
+        self.to_big_endian(&mut bytes);
+        bytes
+    }
+
+    pub fn from_be_bytes(bytes: [u8; 32]) -> U256 {
+        U256::from_big_endian(&bytes)
+    }
+}
+
+// Not provided by the uint crate
+impl BitOrAssign for U256 {
+    fn bitor_assign(&mut self, other: U256) {
+        *self = *self | other;
+    }
+}
+
+// Serialised as the [u64; 4] limbs (least significant first), so that U256 values share the layout of the raw limb
+// arrays used in instruction arguments and accounts. The programs' instruction arguments and accounts keep the raw
+// [u64; 4] arrays nonetheless (converting with U256(..) and .0), as the Anchor IDL only describes the types defined
+// within the program crates themselves.
+impl BorshSerialize for U256 {
+    fn serialize<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        self.0.serialize(writer)
+    }
+}
+
+impl BorshDeserialize for U256 {
+    fn deserialize(buf: &mut &[u8]) -> io::Result<Self> {
+        Ok(U256(<[u64; 4]>::deserialize(buf)?))
+    }
+}
@@ -0,0 +1,349 @@
+use anchor_lang::prelude::*;
+use shared_lib::u256::U256;
+
+// Catalyst payload structure ***************************************************************************************************
+// NOTE: The payload format matches the one of the EVM implementation (evm/src/CatalystPayload.sol), so that Solana pools
+// can interact with the EVM vaults.
+//
+// Addresses have 65 bytes reserved: the first byte contains the address length, and the address is right-aligned within the
+// remaining 64 bytes. Remote addresses are represented on Solana as Pubkeys, with addresses shorter than 32 bytes (i.e. EVM
+// addresses) being left-padded with zeros.
+//
+// Common Payload (beginning)
+//    CONTEXT               0   (1 byte)
+//    + FROM_VAULT_LENGTH   1   (1 byte)
+//    + FROM_VAULT          2   (64 bytes)
+//    + TO_VAULT_LENGTH     66  (1 byte)
+//    + TO_VAULT            67  (64 bytes)
+//    + TO_ACCOUNT_LENGTH   131 (1 byte)
+//    + TO_ACCOUNT          132 (64 bytes)
+//    + UNITS               196 (32 bytes)
+//
+// Context-depending Payload
+//    CTX0 - 0x00 - Asset Swap Payload
+//       + TO_ASSET_INDEX   228 (1 byte)
+//       + MIN_OUT          229 (32 bytes)
+//       + FROM_AMOUNT      261 (32 bytes)
+//       + FROM_ASSET_LEN   293 (1 byte)
+//       + FROM_ASSET       294 (64 bytes)
+//       + BLOCK_NUMBER     358 (4 bytes)
+//      (Underwrite Logic)
+//       + UW_INCENTIVE     362 (2 bytes)
+//
+//    CTX1 - 0x01 - Liquidity Swap Payload
+//       + MIN_OUT          228 (32 bytes)
+//       + MIN_REFERENCE    260 (32 bytes)
+//       + FROM_AMOUNT      292 (32 bytes)
+//       + BLOCK_NUMBER     324 (4 bytes)
+//
+// Common Payload (end)
+//    + DATA_LENGTH         LENGTH-N-2 (2 bytes)
+//    + DATA                LENGTH-N   (N bytes)
+//
+// All integers are big-endian encoded.
+
+
+
+// Contexts *********************************************************************************************************************
+
+pub const CTX0_ASSET_SWAP     : u8 = 0x00;
+pub const CTX1_LIQUIDITY_SWAP : u8 = 0x01;
+
+
+
+// Common Payload ***************************************************************************************************************
+
+pub const CONTEXT_POS           : usize = 0;
+
+pub const FROM_VAULT_LENGTH_POS : usize = 1;
+pub const FROM_VAULT_END        : usize = 66;
+
+pub const TO_VAULT_LENGTH_POS   : usize = 66;
+pub const TO_VAULT_END          : usize = 131;
+
+pub const TO_ACCOUNT_LENGTH_POS : usize = 131;
+pub const TO_ACCOUNT_END        : usize = 196;
+
+pub const UNITS_START           : usize = 196;
+pub const UNITS_END             : usize = 228;
+
+pub const ADDRESS_ENCODED_LENGTH : usize = 65;      // Includes the length byte
+
+const SOLANA_ADDRESS_LENGTH     : u8 = 32;
+const EVM_ADDRESS_LENGTH        : u8 = 20;
+
+
+
+// CTX0 Asset Swap Payload ******************************************************************************************************
+
+pub const CTX0_TO_ASSET_INDEX_POS    : usize = 228;
+
+pub const CTX0_MIN_OUT_START         : usize = 229;
+pub const CTX0_MIN_OUT_END           : usize = 261;
+
+pub const CTX0_FROM_AMOUNT_START     : usize = 261;
+pub const CTX0_FROM_AMOUNT_END       : usize = 293;
+
+pub const CTX0_FROM_ASSET_LENGTH_POS : usize = 293;
+pub const CTX0_FROM_ASSET_END        : usize = 358;
+
+pub const CTX0_BLOCK_NUMBER_START    : usize = 358;
+pub const CTX0_BLOCK_NUMBER_END      : usize = 362;
+
+pub const CTX0_UW_INCENTIVE_START    : usize = 362;
+pub const CTX0_UW_INCENTIVE_END      : usize = 364;
+
+pub const CTX0_DATA_LENGTH_START     : usize = 364;
+pub const CTX0_DATA_LENGTH_END       : usize = 366;
+
+pub const CTX0_DATA_START            : usize = 366;
+
+
+
+// CTX1 Liquidity Swap Payload **************************************************************************************************
+
+pub const CTX1_MIN_POOL_TOKEN_START  : usize = 228;
+pub const CTX1_MIN_POOL_TOKEN_END    : usize = 260;
+
+pub const CTX1_MIN_REFERENCE_START   : usize = 260;
+pub const CTX1_MIN_REFERENCE_END     : usize = 292;
+
+pub const CTX1_FROM_AMOUNT_START     : usize = 292;
+pub const CTX1_FROM_AMOUNT_END       : usize = 324;
+
+pub const CTX1_BLOCK_NUMBER_START    : usize = 324;
+pub const CTX1_BLOCK_NUMBER_END      : usize = 328;
+
+pub const CTX1_DATA_LENGTH_START     : usize = 328;
+pub const CTX1_DATA_LENGTH_END       : usize = 330;
+
+pub const CTX1_DATA_START            : usize = 330;
+
+
+
+// Payloads *********************************************************************************************************************
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct AssetSwapPayload {
+    pub from_vault: Pubkey,
+    pub to_vault: Pubkey,
+    pub to_account: Pubkey,
+    pub units_x64: [u64; 4],
+    pub to_asset_index: u8,
+    pub min_out: [u64; 4],
+    pub from_amount: [u64; 4],
+    pub from_asset: Pubkey,
+    pub block_number: u32,
+    pub underwrite_incentive_x16: u16,
+    pub data: Vec<u8>
+}
+
+impl AssetSwapPayload {
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+
+        let mut payload = vec![0u8; CTX0_DATA_START + self.data.len()];
+
+        payload[CONTEXT_POS] = CTX0_ASSET_SWAP;
+
+        encode_common_payload(&mut payload, &self.from_vault, &self.to_vault, &self.to_account, &self.units_x64);
+
+        payload[CTX0_TO_ASSET_INDEX_POS] = self.to_asset_index;
+        payload[CTX0_MIN_OUT_START..CTX0_MIN_OUT_END].copy_from_slice(&encode_u256(&self.min_out));
+        payload[CTX0_FROM_AMOUNT_START..CTX0_FROM_AMOUNT_END].copy_from_slice(&encode_u256(&self.from_amount));
+        encode_address(&self.from_asset, &mut payload[CTX0_FROM_ASSET_LENGTH_POS..CTX0_FROM_ASSET_END]);
+        payload[CTX0_BLOCK_NUMBER_START..CTX0_BLOCK_NUMBER_END].copy_from_slice(&self.block_number.to_be_bytes());
+        payload[CTX0_UW_INCENTIVE_START..CTX0_UW_INCENTIVE_END].copy_from_slice(&self.underwrite_incentive_x16.to_be_bytes());
+
+        encode_data(&mut payload, CTX0_DATA_LENGTH_START, &self.data)?;
+
+        Ok(payload)
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self> {
+
+        if payload.len() < CTX0_DATA_START {
+            return Err(error!(PayloadErrorCode::InvalidPayloadLength));
+        }
+
+        if payload[CONTEXT_POS] != CTX0_ASSET_SWAP {
+            return Err(error!(PayloadErrorCode::InvalidPayloadContext));
+        }
+
+        Ok(AssetSwapPayload {
+            from_vault: decode_address(&payload[FROM_VAULT_LENGTH_POS..FROM_VAULT_END])?,
+            to_vault: decode_address(&payload[TO_VAULT_LENGTH_POS..TO_VAULT_END])?,
+            to_account: decode_address(&payload[TO_ACCOUNT_LENGTH_POS..TO_ACCOUNT_END])?,
+            units_x64: decode_u256(&payload[UNITS_START..UNITS_END]),
+            to_asset_index: payload[CTX0_TO_ASSET_INDEX_POS],
+            min_out: decode_u256(&payload[CTX0_MIN_OUT_START..CTX0_MIN_OUT_END]),
+            from_amount: decode_u256(&payload[CTX0_FROM_AMOUNT_START..CTX0_FROM_AMOUNT_END]),
+            from_asset: decode_address(&payload[CTX0_FROM_ASSET_LENGTH_POS..CTX0_FROM_ASSET_END])?,
+            block_number: u32::from_be_bytes(payload[CTX0_BLOCK_NUMBER_START..CTX0_BLOCK_NUMBER_END].try_into().unwrap()),
+            underwrite_incentive_x16: u16::from_be_bytes(payload[CTX0_UW_INCENTIVE_START..CTX0_UW_INCENTIVE_END].try_into().unwrap()),
+            data: decode_data(payload, CTX0_DATA_LENGTH_START)?
+        })
+    }
+}
+
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct LiquiditySwapPayload {
+    pub from_vault: Pubkey,
+    pub to_vault: Pubkey,
+    pub to_account: Pubkey,
+    pub units_x64: [u64; 4],
+    pub min_pool_tokens: [u64; 4],
+    pub min_reference_asset: [u64; 4],
+    pub from_amount: [u64; 4],
+    pub block_number: u32,
+    pub data: Vec<u8>
+}
+
+impl LiquiditySwapPayload {
+
+    pub fn encode(&self) -> Result<Vec<u8>> {
+
+        let mut payload = vec![0u8; CTX1_DATA_START + self.data.len()];
+
+        payload[CONTEXT_POS] = CTX1_LIQUIDITY_SWAP;
+
+        encode_common_payload(&mut payload, &self.from_vault, &self.to_vault, &self.to_account, &self.units_x64);
+
+        payload[CTX1_MIN_POOL_TOKEN_START..CTX1_MIN_POOL_TOKEN_END].copy_from_slice(&encode_u256(&self.min_pool_tokens));
+        payload[CTX1_MIN_REFERENCE_START..CTX1_MIN_REFERENCE_END].copy_from_slice(&encode_u256(&self.min_reference_asset));
+        payload[CTX1_FROM_AMOUNT_START..CTX1_FROM_AMOUNT_END].copy_from_slice(&encode_u256(&self.from_amount));
+        payload[CTX1_BLOCK_NUMBER_START..CTX1_BLOCK_NUMBER_END].copy_from_slice(&self.block_number.to_be_bytes());
+
+        encode_data(&mut payload, CTX1_DATA_LENGTH_START, &self.data)?;
+
+        Ok(payload)
+    }
+
+    pub fn decode(payload: &[u8]) -> Result<Self> {
+
+        if payload.len() < CTX1_DATA_START {
+            return Err(error!(PayloadErrorCode::InvalidPayloadLength));
+        }
+
+        if payload[CONTEXT_POS] != CTX1_LIQUIDITY_SWAP {
+            return Err(error!(PayloadErrorCode::InvalidPayloadContext));
+        }
+
+        Ok(LiquiditySwapPayload {
+            from_vault: decode_address(&payload[FROM_VAULT_LENGTH_POS..FROM_VAULT_END])?,
+            to_vault: decode_address(&payload[TO_VAULT_LENGTH_POS..TO_VAULT_END])?,
+            to_account: decode_address(&payload[TO_ACCOUNT_LENGTH_POS..TO_ACCOUNT_END])?,
+            units_x64: decode_u256(&payload[UNITS_START..UNITS_END]),
+            min_pool_tokens: decode_u256(&payload[CTX1_MIN_POOL_TOKEN_START..CTX1_MIN_POOL_TOKEN_END]),
+            min_reference_asset: decode_u256(&payload[CTX1_MIN_REFERENCE_START..CTX1_MIN_REFERENCE_END]),
+            from_amount: decode_u256(&payload[CTX1_FROM_AMOUNT_START..CTX1_FROM_AMOUNT_END]),
+            block_number: u32::from_be_bytes(payload[CTX1_BLOCK_NUMBER_START..CTX1_BLOCK_NUMBER_END].try_into().unwrap()),
+            data: decode_data(payload, CTX1_DATA_LENGTH_START)?
+        })
+    }
+}
+
+
+
+// Helpers **********************************************************************************************************************
+
+/// Returns the context of the payload (without validating the rest of it).
+pub fn decode_context(payload: &[u8]) -> Result<u8> {
+    match payload.get(CONTEXT_POS) {
+        Some(context) => Ok(*context),
+        None => Err(error!(PayloadErrorCode::InvalidPayloadLength))
+    }
+}
+
+/// Returns the 32 bytes which hold the (left-padded) address of an encoded address field, without validating its encoding.
+/// Meant for account constraints, where the payload has not been decoded yet.
+pub fn address_bytes(payload: &[u8], address_end: usize) -> Result<&[u8]> {
+    payload.get(address_end-32..address_end).ok_or_else(|| error!(PayloadErrorCode::InvalidPayloadLength))
+}
+
+fn encode_common_payload(
+    payload: &mut [u8],
+    from_vault: &Pubkey,
+    to_vault: &Pubkey,
+    to_account: &Pubkey,
+    units_x64: &[u64; 4]
+) {
+    encode_address(from_vault, &mut payload[FROM_VAULT_LENGTH_POS..FROM_VAULT_END]);
+    encode_address(to_vault, &mut payload[TO_VAULT_LENGTH_POS..TO_VAULT_END]);
+    encode_address(to_account, &mut payload[TO_ACCOUNT_LENGTH_POS..TO_ACCOUNT_END]);
+    payload[UNITS_START..UNITS_END].copy_from_slice(&encode_u256(units_x64));
+}
+
+/// Pubkeys whose first 12 bytes are zero are encoded as EVM (20 byte) addresses.
+fn encode_address(address: &Pubkey, buffer: &mut [u8]) {
+    let address_bytes = address.to_bytes();
+
+    buffer[0] = match address_bytes[..12].iter().all(|byte| *byte == 0) {
+        true  => EVM_ADDRESS_LENGTH,
+        false => SOLANA_ADDRESS_LENGTH
+    };
+    buffer[1..ADDRESS_ENCODED_LENGTH-32].fill(0);
+    buffer[ADDRESS_ENCODED_LENGTH-32..ADDRESS_ENCODED_LENGTH].copy_from_slice(&address_bytes);
+}
+
+fn decode_address(buffer: &[u8]) -> Result<Pubkey> {
+    let address_length = buffer[0] as usize;
+
+    // Only addresses of up to 32 bytes can be represented as Pubkeys
+    if address_length == 0 || address_length > 32 {
+        return Err(error!(PayloadErrorCode::InvalidAddressEncoding));
+    }
+
+    // Make sure the bytes not used by the address are zero
+    if !buffer[1..ADDRESS_ENCODED_LENGTH-address_length].iter().all(|byte| *byte == 0) {
+        return Err(error!(PayloadErrorCode::InvalidAddressEncoding));
+    }
+
+    Ok(Pubkey::new(&buffer[ADDRESS_ENCODED_LENGTH-32..ADDRESS_ENCODED_LENGTH]))
+}
+
+/// Encodes a u256 given as 4 u64 little-endian limbs (i.e. the U256 representation) as a 32 byte big-endian integer.
+pub fn encode_u256(value: &[u64; 4]) -> [u8; 32] {
+    U256(*value).to_be_bytes()
+}
+
+/// Decodes a 32 byte big-endian integer into 4 u64 little-endian limbs (i.e. the U256 representation).
+pub fn decode_u256(buffer: &[u8]) -> [u64; 4] {
+    U256::from_be_bytes(buffer.try_into().unwrap()).0
+}
+
+fn encode_data(payload: &mut [u8], data_length_start: usize, data: &[u8]) -> Result<()> {
+    let data_length: u16 = data.len().try_into().map_err(|_| error!(PayloadErrorCode::DataTooLong))?;
+
+    payload[data_length_start..data_length_start+2].copy_from_slice(&data_length.to_be_bytes());
+    payload[data_length_start+2..].copy_from_slice(data);
+
+    Ok(())
+}
+
+fn decode_data(payload: &[u8], data_length_start: usize) -> Result<Vec<u8>> {
+    let data_length = u16::from_be_bytes(payload[data_length_start..data_length_start+2].try_into().unwrap()) as usize;
+
+    if payload.len() != data_length_start + 2 + data_length {
+        return Err(error!(PayloadErrorCode::InvalidPayloadLength));
+    }
+
+    Ok(payload[data_length_start+2..].to_vec())
+}
+
+
+
+// Errors ***********************************************************************************************************************
+
+#[error_code(offset = 7000)]
+pub enum PayloadErrorCode {
+    #[msg("The payload length does not match the one expected for its context.")]
+    InvalidPayloadLength,
+    #[msg("The payload context does not match the expected one.")]
+    InvalidPayloadContext,
+    #[msg("The payload contains an invalid or unsupported address encoding.")]
+    InvalidAddressEncoding,
+    #[msg("The payload data exceeds the maximum length.")]
+    DataTooLong,
+}
@@ -2,11 +2,21 @@ use anchor_lang::prelude::*;
 use anchor_lang::prelude::borsh;
 use anchor_lang::solana_program::entrypoint::ProgramResult;
 use anchor_lang::solana_program::{instruction::Instruction, program::invoke_signed};
+use anchor_lang::solana_program::hash::hashv;
+use anchor_lang::system_program;
 use borsh::BorshSerialize;
 use std::convert::TryInto;
 
 use spl_token::ID as TOKEN_PROGRAM_ID;
 
+pub mod catalyst_payload;
+use catalyst_payload::*;
+
+#[cfg(test)]
+pub mod test {
+    pub mod test_catalyst_payload;
+}
+
 use polymerase_register::cpi::accounts::RegisterPort;
 use polymerase_register::{program::PolymeraseRegister};
 use polymerase_sender::cpi::accounts::SendIbcPacket;
@@ -17,16 +27,31 @@ declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLCC");
 
 
 // CPI sighashs
-const SIGHASH_IN_SWAP           : [u8; 8] = [78, 13, 161, 247, 110, 249, 148, 65];
-const SIGHASH_IN_LIQUIDITY_SWAP : [u8; 8] = [148, 187, 47, 168, 127, 65, 48, 95];
-const SIGHASH_OUT_SWAP_ACK      : [u8; 8] = [25, 202, 103, 138, 82, 99, 175, 108];
-const SIGHASH_OUT_SWAP_TIMEOUT  : [u8; 8] = [221, 78, 207, 141, 128, 224, 34, 43];
+const SIGHASH_IN_SWAP                    : [u8; 8] = [78, 13, 161, 247, 110, 249, 148, 65];
+const SIGHASH_IN_LIQUIDITY_SWAP          : [u8; 8] = [148, 187, 47, 168, 127, 65, 48, 95];
+const SIGHASH_OUT_SWAP_ACK               : [u8; 8] = [25, 202, 103, 138, 82, 99, 175, 108];
+const SIGHASH_OUT_SWAP_TIMEOUT           : [u8; 8] = [221, 78, 207, 141, 128, 224, 34, 43];
+const SIGHASH_FILL_UNDERWRITE            : [u8; 8] = [56, 205, 152, 2, 209, 247, 236, 139];
+const SIGHASH_OUT_LIQUIDITY_SWAP_ACK     : [u8; 8] = [84, 16, 127, 31, 153, 106, 53, 181];
+const SIGHASH_OUT_LIQUIDITY_SWAP_TIMEOUT : [u8; 8] = [109, 225, 107, 164, 105, 148, 41, 7];
+
+// Polymerase seeds
+const POLYMERASE_AUTHORITY_SEED : &[u8] = b"polymeraseAuthority";   // NOTE: must match the seed used by the Polymerase endpoint
+
+// SwapPool seeds
+const POOL_UNDERWRITE_SEED      : &[u8] = b"poolUnderwrite";    // NOTE: must match the seed used by the SwapPool programs
+
+// Interface seeds
+pub const RECEIPT_SEED          : &[u8] = b"receipt";
+pub const CHANNEL_SEED          : &[u8] = b"channel";
+
+// Maximum time (in seconds) during which an incoming packet may be delivered
+pub const MAX_PACKET_VALIDITY           : u64 = 60 * 60 * 24 * 7;
 
-// Define swap contexts
-const CTX_ASSET_SWAP            : u8 = 0x00;
-const CTX_ASSET_APPROX_SWAP     : u8 = 0x01;
-const CTX_LIQUIDITY_SWAP        : u8 = 0x02;
-const CTX_LIQUIDITY_APPROX_SWAP : u8 = 0x03;
+// Time (in seconds) after which the rent of a swap receipt may be reclaimed. Once a receipt is reclaimed, the swap it belongs
+// to could be executed again: the delay must hence be (by a wide margin) longer than the maximum packet validity.
+pub const MIN_RECEIPT_RECLAIM_DELAY     : u64 = 2 * MAX_PACKET_VALIDITY;
+pub const DEFAULT_RECEIPT_RECLAIM_DELAY : u64 = 60 * 60 * 24 * 30;
 
 #[program]
 pub mod ibc_interface {
@@ -43,6 +68,11 @@ pub mod ibc_interface {
         ctx.accounts.interface_state_account.swap_pool = swap_pool_state;
         ctx.accounts.interface_state_account.swap_pool_authority = swap_pool_authority;
         ctx.accounts.interface_state_account.polymerase_endpoint_program = polymerase_endpoint_program;
+        ctx.accounts.interface_state_account.polymerase_authority = Pubkey::find_program_address(
+            &[POLYMERASE_AUTHORITY_SEED],
+            &polymerase_endpoint_program
+        ).0;
+        ctx.accounts.interface_state_account.receipt_reclaim_delay = DEFAULT_RECEIPT_RECLAIM_DELAY;
         ctx.accounts.interface_state_account.interface_state_account_bump = ctx.bumps.get("interface_state_account").unwrap().to_owned();
         Ok(())
     }
@@ -52,6 +82,24 @@ pub mod ibc_interface {
         Ok(())
     }
 
+    // NOTE: While paused, incoming packets are refused (and hence time out and get refunded on the source chain), and
+    // outgoing swaps cannot be sent. Acks and timeouts are still processed, so that the swap escrows can be released.
+    pub fn set_paused(ctx: Context<SetConfigurator>, paused: bool) -> Result<()> {
+        ctx.accounts.interface_state_account.paused = paused;
+        Ok(())
+    }
+
+    // NOTE: Once a receipt is reclaimed, the swap it belongs to could be executed again. The delay must hence be (by a wide
+    // margin) longer than the time during which a packet can be delivered (see MIN_RECEIPT_RECLAIM_DELAY).
+    pub fn set_receipt_reclaim_delay(ctx: Context<SetConfigurator>, receipt_reclaim_delay: u64) -> Result<()> {
+        if receipt_reclaim_delay < MIN_RECEIPT_RECLAIM_DELAY {
+            return Err(error!(ErrorCode::ReceiptReclaimDelayTooShort));
+        }
+
+        ctx.accounts.interface_state_account.receipt_reclaim_delay = receipt_reclaim_delay;
+        Ok(())
+    }
+
     // ! TODO must be required before completing setup
     pub fn register_receiver(
         ctx: Context<RegisterReceiver>
@@ -107,6 +155,17 @@ pub mod ibc_interface {
         // Ok(())
     }
 
+    /// Links a (local) IBC channel to the chain on its other end. Used to derive the source chain of incoming packets.
+    pub fn create_channel(
+        ctx: Context<CreateChannel>,
+        channel_id: String,             // Used in the CreateChannel context
+        chain: u64
+    ) -> Result<()> {
+        ctx.accounts.channel_state_account.remote_chain               = chain;
+        ctx.accounts.channel_state_account.channel_state_account_bump = ctx.bumps.get("channel_state_account").unwrap().to_owned();
+        Ok(())
+    }
+
     pub fn create_connection(
         ctx: Context<CreateConnection>,
         chain: u64,                     // Used in the CreateConnection context
@@ -114,75 +173,94 @@ pub mod ibc_interface {
         interface_program: Pubkey       // target crosschain program
     ) -> Result<()> {
         ctx.accounts.connection_state_account.connected_interface_program   = interface_program;
+        ctx.accounts.connection_state_account.remote_chain                  = chain;
+        ctx.accounts.connection_state_account.remote_pool                   = pool;
+        ctx.accounts.connection_state_account.enabled                       = true;
         ctx.accounts.connection_state_account.connection_state_account_bump = ctx.bumps.get("connection_state_account").unwrap().to_owned();
         Ok(())
     }
 
+    // NOTE: Disabled connections can neither send nor receive swaps. Acks and timeouts of already sent swaps are still
+    // processed, so that the swap escrows can be released.
+    pub fn set_connection(
+        ctx: Context<SetConnection>,
+        chain: u64,                     // Used in the SetConnection context
+        pool: Pubkey,                   // Used in the SetConnection context
+        enabled: bool
+    ) -> Result<()> {
+        ctx.accounts.connection_state_account.enabled = enabled;
+        Ok(())
+    }
+
+    /// Closes the interface together with the connections passed as remaining accounts, refunding their rent to the
+    /// rent_receiver. Used by the swap pool when its setup is cancelled.
+    pub fn close_interface<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CloseInterface<'info>>
+    ) -> Result<()> {
+
+        let interface_state_account_key = ctx.accounts.interface_state_account.key();
+
+        for connection_state_account_info in ctx.remaining_accounts {
+
+            let connection_state_account: Account<ConnectionState> = Account::try_from(connection_state_account_info)?;
+
+            // Make sure the connection belongs to this interface
+            let expected_connection_state_account = Pubkey::create_program_address(
+                &[
+                    &interface_state_account_key.to_bytes(),
+                    connection_state_account.remote_chain.to_le_bytes().as_ref(),
+                    &connection_state_account.remote_pool.to_bytes(),
+                    &[connection_state_account.connection_state_account_bump]
+                ],
+                &crate::ID
+            ).map_err(|_| error!(ErrorCode::InvalidConnectionStateAccount))?;
+
+            if connection_state_account.key().ne(&expected_connection_state_account) {
+                return Err(error!(ErrorCode::InvalidConnectionStateAccount));
+            }
+
+            connection_state_account.close(ctx.accounts.rent_receiver.to_account_info())?;
+        }
+
+        // NOTE: The interface_state_account is closed by the 'close' constraint of CloseInterface
+        Ok(())
+    }
+
     pub fn cross_chain_swap(
         ctx: Context<CrossChainSwap>,
         chain: u64,
-        approx: bool,
         source_pool: Pubkey,    // Used to derive the ibc_interface_state account
         target_pool: Pubkey,
         target_asset_index: u8,
         destination: Pubkey,
         transferred_units_x64: [u64; 4],
         min_output: [u64; 4],
-        source_swap_id: u32
+        from_amount: u64,
+        from_asset: Pubkey,
+        block_number: u32,
+        underwrite_incentive_x16: u16
     ) -> Result<()> {
-        /*
-            Message Format
-
-            0       context            : u8
-            1-32    source_pool        : Pubkey
-            33-64   target_pool        : Pubkey
-            65-96   destination        : Pubkey
-            97-128  units              : u256 (as 4 u64)
-            129     target_asset_index : u8
-            130-161 min_output         : u256 (as 4 u64)
-            162-165 source_swap_id     : u32
-        */
 
         // ! Swap pool authority verification in CrossChainSwap context
         // !    ==> Only allow the authorized swap pool authority holder to execute this function
 
         // ! The target pool connection state (chain + pool) is checked in the context
 
-        let mut message = vec![0; 166];
-
-        match approx {
-            false => message[0] = CTX_ASSET_SWAP,
-            true =>  message[0] = CTX_ASSET_APPROX_SWAP
-        }
-        
-        message[1..33]      // Context
-            .copy_from_slice(&ctx.accounts.interface_state_account.key().to_bytes());
-        
-        message[33..65]     // Source pool
-            .copy_from_slice(&target_pool.to_bytes());
-        
-        message[65..97]     // Target pool
-            .copy_from_slice(&destination.to_bytes());
-        
-        message[97..129]    // Destination
-            .copy_from_slice(&transferred_units_x64
-                .iter()
-                .flat_map(|el| {el.to_be_bytes()})
-                .collect::<Vec<u8>>()[0..32]
-            ); //TODO! verify + efficiency + use big_endian?
-
-        message[129..130]   // Units
-            .copy_from_slice(&[target_asset_index]);
-
-        message[130..162]   // Minimum output
-            .copy_from_slice(&min_output
-                .iter()
-                .flat_map(|el| {el.to_be_bytes()})
-                .collect::<Vec<u8>>()[0..32]
-            ); //TODO! verify + efficiency + use big_endian?
-        
-        message[162..166]   // Source swap id
-            .copy_from_slice(&source_swap_id.to_be_bytes());
+        // NOTE: The escrow of the swap is derived from the payload contents (see compute_asset_escrow_id), so that it can
+        // be released on the ack/timeout of the swap.
+        let message = AssetSwapPayload {
+            from_vault: ctx.accounts.interface_state_account.key(),
+            to_vault: target_pool,
+            to_account: destination,
+            units_x64: transferred_units_x64,
+            to_asset_index: target_asset_index,
+            min_out: min_output,
+            from_amount: [from_amount, 0, 0, 0],
+            from_asset,
+            block_number,
+            underwrite_incentive_x16,
+            data: vec![]
+        }.encode()?;
 
         // Invoke call_multichain
         //TODO create external function for the following code
@@ -213,50 +291,32 @@ pub mod ibc_interface {
     pub fn cross_chain_liquidity_swap(
         ctx: Context<CrossChainSwap>,
         chain: u64,
-        approx: bool,
         source_pool: Pubkey,    // Used to derive the ibc_interface_state account
         target_pool: Pubkey,
         destination: Pubkey,
-        transferred_liquidity_units_x64: [u64; 4]
+        transferred_liquidity_units_x64: [u64; 4],
+        min_pool_tokens: [u64; 4],
+        min_reference_asset: [u64; 4],
+        from_amount: u64,
+        block_number: u32
     ) -> Result<()> {
-        /*
-            Message Format
-
-            0       context            : u8
-            1-32    source_pool        : Pubkey
-            33-64   target_pool        : Pubkey
-            65-96   destination        : Pubkey
-            97-128  units              : u256 (as 4 u64)
-            129-XX  TBD
-        */
 
         // ! Swap pool authority verification in CrossChainSwap context
         // !    ==> Only allow the authorized swap pool authority holder to execute this function
 
         // ! The target pool connection state (chain + pool) is checked in the context
 
-        assert!(!approx, "Cross chain liquidity swap does not support approx mode.");
-
-        let mut message = vec![0; 130];
-
-        message[0] = CTX_LIQUIDITY_SWAP;     // Context
-
-        message[1..33]                  // Source pool
-            .copy_from_slice(&ctx.accounts.interface_state_account.key().to_bytes());
-
-        message[33..65]                 // Target pool
-            .copy_from_slice(&target_pool.to_bytes());
-
-        message[65..97]                 // Destination
-            .copy_from_slice(&destination.to_bytes());
-
-        message[97..129]                // Units
-            .copy_from_slice(&transferred_liquidity_units_x64
-            .iter()
-            .flat_map(|el| {el.to_be_bytes()})
-            .collect::<Vec<u8>>()[0..32]
-        ); //TODO! verify + efficiency + use big_endian?
-
+        let message = LiquiditySwapPayload {
+            from_vault: ctx.accounts.interface_state_account.key(),
+            to_vault: target_pool,
+            to_account: destination,
+            units_x64: transferred_liquidity_units_x64,
+            min_pool_tokens,
+            min_reference_asset,
+            from_amount: [from_amount, 0, 0, 0],
+            block_number,
+            data: vec![]
+        }.encode()?;
 
         // Invoke call_multichain
         //TODO create external function for the following code
@@ -286,18 +346,15 @@ pub mod ibc_interface {
 
     pub fn on_recv_packet<'a, 'b, 'c, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, OnIBCInvocation<'info>>,
+        channel_id: String,     // Used in the OnIBCInvocation context to derive the source chain
         message: Vec<u8>
     ) -> Result<()> {
 
-        // ! TODO:
-        // ! PolymeraseInterface authority verification in OnRecvPacket context
+        // ! PolymeraseInterface authority verification in the OnIBCInvocation context
         // !    ==> Only allow the authorized PolymeraseInterface authority holder to execute this function
 
-        // let chain //TODO
-
-        let context: u8 = u8::from_be_bytes(message[..1].try_into().unwrap());
-        let source_pool: Pubkey = Pubkey::new(&message[1..33]);
-        let target_pool: Pubkey = Pubkey::new(&message[33..65]);
+        // NOTE: The source chain is derived from the channel through which the packet has been received (see the
+        // channel_state_account of the OnIBCInvocation context)
 
         //TODO move to context?
         // Check the provided SwapPool program is the expected one
@@ -305,57 +362,93 @@ pub mod ibc_interface {
             return Err(error!(ErrorCode::InvalidSwapPoolProgram));
         }
 
-        //TODO!
-        // //TODO move to context?
-        // // Check the source chain + pool is valid, i.e. it is a connected and enabled pool connection
-        // if !ctx.accounts.connection_state_account.state {
-        //     return Err(error!(ErrorCode::SourcePoolNotConnected));
-        // }
+        // NOTE: The source pool connection is verified to be enabled in the OnIBCInvocation context
 
-        match context {
+        match decode_context(&message)? {
 
-            CTX_ASSET_SWAP | CTX_ASSET_APPROX_SWAP => {
+            CTX0_ASSET_SWAP => {
                 // Invoke in_swap of the target pool
-                /*
-                    Message Format
-                    0       context            : u8
-                    1-32    source_pool        : Pubkey
-                    33-64   target_pool        : Pubkey
-                    65-96   destination        : Pubkey
-                    97-128  units              : u256 (as 4 u64)
-                    129     target_asset_index : u8
-                    130-161 min_output         : u256 (as 4 u64)
-                    162-165 source_swap_id     : u32
-                */
-    
-                let units_x64: [u64; 4] = [                                         // TODO create helper function
-                    u64::from_be_bytes(message[97..105].try_into().unwrap()),
-                    u64::from_be_bytes(message[105..113].try_into().unwrap()),
-                    u64::from_be_bytes(message[113..121].try_into().unwrap()),
-                    u64::from_be_bytes(message[121..129].try_into().unwrap())
-                ];
-
+                let payload = AssetSwapPayload::decode(&message)?;
 
                 // Min output: for Solana, the maximum value allowed is u64
-                let min_output: u64 = u64::from_be_bytes(message[130..138].try_into().unwrap());
+                let min_output: u64 = payload.min_out[0];
 
                 // Make sure that the rest of the 'min_output' value is zero
-                if !message[138..162].iter().all(|slice| slice == &0) {
+                if payload.min_out[1..].iter().any(|limb| *limb != 0) {
                     return Err(error!(ErrorCode::MinimumOutputExceedsMaxAllowed));
                 }
 
 
+                // The underwrite escrow (index 4) must always be provided
+                check_remaining_accounts(ctx.remaining_accounts, 5)?;
+
                 // ! VERY IMPORTANT verify provided output_asset_wallet matches the one specified in the message
-                if Pubkey::new(&message[65..97]).ne(&ctx.remaining_accounts[1].key()) {
+                if payload.to_account.ne(&ctx.remaining_accounts[1].key()) {
                     return Err(error!(ErrorCode::InvalidAssetWalletAccount));
                 }
 
+                // Create the swap receipt. Fails if the swap has already been executed (i.e. replayed packet)
+                ctx.accounts.create_receipt(
+                    &payload.from_vault,
+                    compute_send_asset_hash(
+                        &payload.to_account,
+                        &payload.units_x64,
+                        &payload.from_amount,
+                        &payload.from_asset,
+                        payload.block_number
+                    )
+                )?;
+
+                // Check whether the swap has been underwritten. The underwrite escrow account must ALWAYS be provided (even
+                // if it has not been created), otherwise an underwritten swap could be paid out a second time.
+                let identifier = compute_underwrite_identifier(
+                    ctx.accounts.connection_state_account.remote_chain,
+                    &payload.from_vault,
+                    payload.block_number,
+                    &ctx.accounts.swap_pool.key(),
+                    payload.to_asset_index,
+                    &payload.units_x64,
+                    min_output,
+                    &ctx.remaining_accounts[1].key(),
+                    payload.underwrite_incentive_x16
+                );
+
+                let (expected_underwrite_escrow, _) = Pubkey::find_program_address(
+                    &[
+                        &ctx.accounts.swap_pool.key().to_bytes(),
+                        &identifier,
+                        POOL_UNDERWRITE_SEED
+                    ],
+                    &ctx.accounts.swap_pool_program.key()
+                );
+
+                let underwrite_escrow = &ctx.remaining_accounts[4];
+                if underwrite_escrow.key().ne(&expected_underwrite_escrow) {
+                    return Err(error!(ErrorCode::InvalidUnderwriteEscrowAccount));
+                }
+
+                if underwrite_escrow.owner.eq(&ctx.accounts.swap_pool_program.key()) && !underwrite_escrow.data_is_empty() {
+                    // Repay the underwriter instead of executing the swap
+                    check_remaining_accounts(ctx.remaining_accounts, 7)?;
+                    ctx.accounts.invoke_fill_underwrite(
+                        identifier,
+                        ctx.remaining_accounts[0].to_account_info(),
+                        ctx.remaining_accounts[2].to_account_info(),
+                        ctx.remaining_accounts[5].to_account_info(),
+                        ctx.remaining_accounts[3].to_account_info(),
+                        underwrite_escrow.to_account_info(),
+                        ctx.remaining_accounts[6].to_account_info(),
+                    ).unwrap();
+
+                    return Ok(())
+                }
+
 
                 ctx.accounts.invoke_in_swap(
-                    message[129],
-                    units_x64,
+                    payload.to_asset_index,
+                    payload.units_x64,
                     min_output,
-                    context == CTX_ASSET_APPROX_SWAP,
+                    false,                  // NOTE: The payload format does not support approximate swaps
                     ctx.remaining_accounts[0].to_account_info(),
                     ctx.remaining_accounts[1].to_account_info(),
                     ctx.remaining_accounts[2].to_account_info(),
@@ -366,32 +459,41 @@ pub mod ibc_interface {
 
             },
 
-            CTX_LIQUIDITY_SWAP => {
+            CTX1_LIQUIDITY_SWAP => {
                 // Invoke in_liquidity_swap of the target pool
-                /*
-                    Message Format
-                    0       context            : u8
-                    1-32    source_pool        : Pubkey
-                    33-64   target_pool        : Pubkey
-                    65-96   destination        : Pubkey
-                    97-128  units              : u256 (as 4 u64)
-                */
-    
-                // CPI - Arguments 
-                let liquidity_units_x64: [u64; 4] = [
-                    u64::from_be_bytes(message[97..105].try_into().unwrap()),
-                    u64::from_be_bytes(message[105..113].try_into().unwrap()),
-                    u64::from_be_bytes(message[113..121].try_into().unwrap()),
-                    u64::from_be_bytes(message[121..129].try_into().unwrap())
-                ];
+                let payload = LiquiditySwapPayload::decode(&message)?;
+
+                // Min outputs: for Solana, the maximum value allowed is u64
+                let min_pool_tokens: u64 = payload.min_pool_tokens[0];
+                let min_reference_asset: u64 = payload.min_reference_asset[0];
+
+                // Make sure that the rest of the minimum output values are zero
+                if payload.min_pool_tokens[1..].iter().chain(payload.min_reference_asset[1..].iter()).any(|limb| *limb != 0) {
+                    return Err(error!(ErrorCode::MinimumOutputExceedsMaxAllowed));
+                }
+
+                check_remaining_accounts(ctx.remaining_accounts, 3)?;
 
                 // ! VERY IMPORTANT verify provided destination_pool_token_wallet matches the one specified in the message
-                if Pubkey::new(&message[65..97]).ne(&ctx.remaining_accounts[1].key()) {
+                if payload.to_account.ne(&ctx.remaining_accounts[1].key()) {
                     return Err(error!(ErrorCode::InvalidPoolTokenWalletAccount));
                 }
 
+                // Create the swap receipt. Fails if the swap has already been executed (i.e. replayed packet)
+                ctx.accounts.create_receipt(
+                    &payload.from_vault,
+                    compute_send_liquidity_hash(
+                        &payload.to_account,
+                        &payload.units_x64,
+                        &payload.from_amount,
+                        payload.block_number
+                    )
+                )?;
+
                 ctx.accounts.invoke_liquidity_in_swap(
-                    liquidity_units_x64,
+                    payload.units_x64,
+                    min_pool_tokens,
+                    min_reference_asset,
                     ctx.remaining_accounts[0].to_account_info(),
                     ctx.remaining_accounts[1].to_account_info(),
                     ctx.remaining_accounts[2].to_account_info()
@@ -410,37 +512,30 @@ pub mod ibc_interface {
         message: Vec<u8>
     ) -> Result<()> {
 
-        // ! TODO:
-        // ! PolymeraseInterface authority verification in OnRecvPacket context
+        // ! PolymeraseInterface authority verification in the OnIBCResult context
         // !    ==> Only allow the authorized PolymeraseInterface authority holder to execute this function
 
-        let context: u8 = u8::from_be_bytes(message[..1].try_into().unwrap());
-        let source_swap_id: u32 = u32::from_be_bytes(message[162..166].try_into().unwrap());
-
         //TODO move to context?
         // Check the provided SwapPool program is the expected one
         if !ctx.accounts.swap_pool_program.key.eq(&ctx.accounts.swap_pool.owner) {
             return Err(error!(ErrorCode::InvalidSwapPoolProgram));
         }
 
-        match context {
+        match decode_context(&message)? {
 
-            CTX_ASSET_SWAP | CTX_ASSET_APPROX_SWAP => {
-                // Invoke in_swap of the target pool
-                /*
-                    Message Format
-                    0       context            : u8
-                    1-32    source_pool        : Pubkey
-                    33-64   target_pool        : Pubkey
-                    65-96   destination        : Pubkey
-                    97-128  units              : u256 (as 4 u64)
-                    129     target_asset_index : u8
-                    130-161 min_output         : u256 (as 4 u64)
-                    162-165 source_swap_id     : u32
-                */
+            CTX0_ASSET_SWAP => {
+                // Release the escrow of the swap
+                let payload = AssetSwapPayload::decode(&message)?;
+                check_remaining_accounts(ctx.remaining_accounts, 5)?;
 
                 ctx.accounts.invoke_out_swap_ack(
-                    source_swap_id,
+                    compute_asset_escrow_id(
+                        &payload.to_account,
+                        &payload.units_x64,
+                        payload.from_amount[0],
+                        &payload.from_asset,
+                        payload.block_number
+                    ),
                     ctx.remaining_accounts[3].to_owned(),
                     ctx.remaining_accounts[4].to_owned(),
                 ).unwrap();
@@ -448,18 +543,23 @@ pub mod ibc_interface {
                 Ok(())
             },
 
-            CTX_LIQUIDITY_SWAP => {
-                // Invoke in_liquidity_swap of the target pool
-                /*
-                    Message Format
-                    0       context            : u8
-                    1-32    source_pool        : Pubkey
-                    33-64   target_pool        : Pubkey
-                    65-96   destination        : Pubkey
-                    97-128  units              : u256 (as 4 u64)
-                */
-
-                Ok(())  //TODO do something?
+            CTX1_LIQUIDITY_SWAP => {
+                // Release the escrow of the swap
+                let payload = LiquiditySwapPayload::decode(&message)?;
+                check_remaining_accounts(ctx.remaining_accounts, 4)?;
+
+                ctx.accounts.invoke_out_liquidity_swap_ack(
+                    compute_liquidity_escrow_id(
+                        &payload.to_account,
+                        &payload.units_x64,
+                        payload.from_amount[0],
+                        payload.block_number
+                    ),
+                    ctx.remaining_accounts[2].to_owned(),
+                    ctx.remaining_accounts[3].to_owned(),
+                ).unwrap();
+
+                Ok(())
             },
             _ => Err(error!(ErrorCode::InvalidContext))
         }
@@ -472,37 +572,30 @@ pub mod ibc_interface {
         message: Vec<u8>
     ) -> Result<()> {
 
-        // ! TODO:
-        // ! PolymeraseInterface authority verification in OnRecvPacket context
+        // ! PolymeraseInterface authority verification in the OnIBCResult context
         // !    ==> Only allow the authorized PolymeraseInterface authority holder to execute this function
 
-        let context: u8 = u8::from_be_bytes(message[..1].try_into().unwrap());
-        let source_swap_id: u32 = u32::from_be_bytes(message[162..166].try_into().unwrap());
-
         //TODO move to context?
         // Check the provided SwapPool program is the expected one
         if !ctx.accounts.swap_pool_program.key.eq(&ctx.accounts.swap_pool.owner) {
             return Err(error!(ErrorCode::InvalidSwapPoolProgram));
         }
 
-        match context {
+        match decode_context(&message)? {
 
-            CTX_ASSET_SWAP | CTX_ASSET_APPROX_SWAP => {
-                // Invoke in_swap of the target pool
-                /*
-                    Message Format
-                    0       context            : u8
-                    1-32    source_pool        : Pubkey
-                    33-64   target_pool        : Pubkey
-                    65-96   destination        : Pubkey
-                    97-128  units              : u256 (as 4 u64)
-                    129     target_asset_index : u8
-                    130-161 min_output         : u256 (as 4 u64)
-                    162-165 source_swap_id     : u32
-                */
+            CTX0_ASSET_SWAP => {
+                // Return the escrowed assets to the fallback wallet
+                let payload = AssetSwapPayload::decode(&message)?;
+                check_remaining_accounts(ctx.remaining_accounts, 5)?;
 
                 ctx.accounts.invoke_out_swap_timeout(
-                    source_swap_id,
+                    compute_asset_escrow_id(
+                        &payload.to_account,
+                        &payload.units_x64,
+                        payload.from_amount[0],
+                        &payload.from_asset,
+                        payload.block_number
+                    ),
                     ctx.remaining_accounts[0].to_owned(),
                     ctx.remaining_accounts[1].to_owned(),
                     ctx.remaining_accounts[2].to_owned(),
@@ -513,24 +606,50 @@ pub mod ibc_interface {
                 Ok(())
             },
 
-            CTX_LIQUIDITY_SWAP => {
-                // Invoke in_liquidity_swap of the target pool
-                /*
-                    Message Format
-                    0       context            : u8
-                    1-32    source_pool        : Pubkey
-                    33-64   target_pool        : Pubkey
-                    65-96   destination        : Pubkey
-                    97-128  units              : u256 (as 4 u64)
-                */
-
-                Ok(())  //TODO do something?
+            CTX1_LIQUIDITY_SWAP => {
+                // Return the escrowed pool tokens to the fallback wallet
+                let payload = LiquiditySwapPayload::decode(&message)?;
+                check_remaining_accounts(ctx.remaining_accounts, 4)?;
+
+                ctx.accounts.invoke_out_liquidity_swap_timeout(
+                    compute_liquidity_escrow_id(
+                        &payload.to_account,
+                        &payload.units_x64,
+                        payload.from_amount[0],
+                        payload.block_number
+                    ),
+                    ctx.remaining_accounts[0].to_owned(),
+                    ctx.remaining_accounts[1].to_owned(),
+                    ctx.remaining_accounts[2].to_owned(),
+                    ctx.remaining_accounts[3].to_owned(),
+                ).unwrap();
+
+                Ok(())
             },
             _ => Err(error!(ErrorCode::InvalidContext))
         }
 
     }
 
+
+    pub fn reclaim_receipt(ctx: Context<ReclaimReceipt>) -> Result<()> {
+
+        // NOTE: The minimum delay is also enforced here, as a safeguard for interface accounts configured with a shorter delay
+        let receipt_reclaim_delay = ctx.accounts.interface_state_account.receipt_reclaim_delay.max(MIN_RECEIPT_RECLAIM_DELAY);
+
+        let current_timestamp: i64 = Clock::get()?.unix_timestamp;
+        let reclaimable_timestamp: i64 = ctx.accounts.receipt.created_at.checked_add(
+            receipt_reclaim_delay.try_into().map_err(|_| error!(ErrorCode::ReceiptNotReclaimable))?
+        ).ok_or_else(|| error!(ErrorCode::ReceiptNotReclaimable))?;
+
+        if current_timestamp < reclaimable_timestamp {
+            return Err(error!(ErrorCode::ReceiptNotReclaimable));
+        }
+
+        // NOTE: The receipt account is closed (and its rent returned to the rent payer) by the ReclaimReceipt context
+        Ok(())
+    }
+
 }
 
 
@@ -582,6 +701,28 @@ pub struct RegisterReceiver<'info> {
 }
 
 
+#[derive(Accounts)]
+#[instruction(channel_id: String)]
+pub struct CreateChannel<'info> {
+    #[account(mut)]
+    pub configurator: Signer<'info>,
+    #[account(has_one = configurator @ ErrorCode::InvalidConfigurator)]
+    pub interface_state_account: Account<'info, CrossChainSwapInterfaceState>,
+    #[account(
+        init,
+        payer = configurator,
+        space = ChannelState::LEN,  // NOTE: includes discriminator
+        seeds = [
+            &interface_state_account.key().to_bytes(),
+            channel_id.as_bytes(),
+            CHANNEL_SEED
+        ],
+        bump
+    )]
+    pub channel_state_account: Account<'info, ChannelState>,
+    pub system_program: Program<'info, System>
+}
+
 #[derive(Accounts)]
 #[instruction(chain: u64, pool: Pubkey)]
 pub struct CreateConnection<'info> {
@@ -606,17 +747,52 @@ pub struct CreateConnection<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(chain: u64, approx: bool, source_pool: Pubkey, target_pool: Pubkey)]
+#[instruction(chain: u64, pool: Pubkey)]
+pub struct SetConnection<'info> {
+    pub configurator: Signer<'info>,
+    #[account(has_one = configurator @ ErrorCode::InvalidConfigurator)]
+    pub interface_state_account: Account<'info, CrossChainSwapInterfaceState>,
+    #[account(
+        mut,
+        seeds = [
+            &interface_state_account.key().to_bytes(),
+            chain.to_le_bytes().as_ref(),
+            &pool.to_bytes()
+        ],
+        bump = connection_state_account.connection_state_account_bump
+    )]
+    pub connection_state_account: Account<'info, ConnectionState>,
+}
+
+#[derive(Accounts)]
+pub struct CloseInterface<'info> {
+    pub swap_pool_authority: Signer<'info>,
+    #[account(
+        mut,
+        has_one = swap_pool_authority @ ErrorCode::InvalidSwapPoolAuthority,
+        close = rent_receiver
+    )]
+    pub interface_state_account: Account<'info, CrossChainSwapInterfaceState>,
+    #[account(mut)]
+    /// CHECK: Safe, only receives the rent of the closed accounts
+    pub rent_receiver: UncheckedAccount<'info>
+
+    // Remaining accounts: the connection_state_accounts to close (mut)
+}
+
+#[derive(Accounts)]
+#[instruction(chain: u64, source_pool: Pubkey, target_pool: Pubkey)]
 pub struct CrossChainSwap<'info> {
     pub swap_pool_authority: Signer<'info>,
     #[account(
         has_one    = swap_pool_authority @ ErrorCode::InvalidSwapPoolAuthority,  // The invocation must come from the swap_pool_authority
-        constraint = interface_state_account.swap_pool == source_pool @ ErrorCode::InvalidSwapPoolAccount      // The source_pool must match the one saved
+        constraint = interface_state_account.swap_pool == source_pool @ ErrorCode::InvalidSwapPoolAccount,     // The source_pool must match the one saved
+        constraint = !interface_state_account.paused @ ErrorCode::InterfacePaused
         // has_one = polymerase_endpoint_program,  // The provided polymerase_endpoint_program must match the saved one // ! TODO verify polymerase sender
     )]
     pub interface_state_account: Account<'info, CrossChainSwapInterfaceState>,    //TODO! must verify that it has been created using swap_pool
     #[account(
-        constraint = !(connection_state_account.connected_interface_program.eq(&Pubkey::default())) @ ErrorCode::PoolNotConnected,  // The target pool must be connected
+        constraint = connection_state_account.enabled @ ErrorCode::PoolNotConnected,  // The target pool must be connected (and the connection enabled)
         seeds = [
             &interface_state_account.key().to_bytes(),
             chain.to_le_bytes().as_ref(),
@@ -637,44 +813,170 @@ pub struct CrossChainSwap<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(message: Vec<u8>)]
+#[instruction(channel_id: String, message: Vec<u8>)]
 pub struct OnIBCInvocation<'info> {
     /// CHECK: unused
     pub port_registration: UncheckedAccount<'info>,
-    #[account()]    // ! TODO add seeds => polymerase receiver set to pda with no seeds?
     pub polymerase_authority: Signer<'info>,
     #[account(
-        constraint = interface_state_account.key().eq(&Pubkey::new(&message[33..65])) @ ErrorCode::InvalidInterfaceAccount, // ! The interface_state_account, derived from the provided swap_pool account, must match the target cross chain pool id present in the payload (i.e. verify the provided swap pool) 
-        constraint = interface_state_account.swap_pool == swap_pool.key() @ ErrorCode::InvalidSwapPoolAccount               // ! The swap_pool must match the one saved
+        has_one    = polymerase_authority @ ErrorCode::InvalidPolymeraseAuthority,                                          // ! Only the Polymerase endpoint may deliver packets
+        constraint = interface_state_account.key().eq(&Pubkey::new(address_bytes(&message, TO_VAULT_END)?)) @ ErrorCode::InvalidInterfaceAccount, // ! The interface_state_account, derived from the provided swap_pool account, must match the target cross chain pool id present in the payload (i.e. verify the provided swap pool) 
+        constraint = interface_state_account.swap_pool == swap_pool.key() @ ErrorCode::InvalidSwapPoolAccount,              // ! The swap_pool must match the one saved
+        constraint = !interface_state_account.paused @ ErrorCode::InterfacePaused                                           // ! Refuse incoming packets while paused (they will time out)
     )]
-    pub interface_state_account: Box<Account<'info, CrossChainSwapInterfaceState>>,    //TODO! must verify that it has been created using swap_pool
+    pub interface_state_account: Box<Account<'info, CrossChainSwapInterfaceState>>,
     #[account(mut)]
-    /// CHECK: //TODO
+    /// CHECK: Verified against the swap pool saved in the interface_state_account
     pub swap_pool: AccountInfo<'info>,
-    /// CHECK: //TODO
+    /// CHECK: Verified to be the owner of the swap_pool
     #[account(
         constraint = swap_pool_program.key().eq(swap_pool.owner) @ ErrorCode::InvalidSwapPoolProgram    // ! Make sure the provided swap pool program matches the expected one
     )]
     pub swap_pool_program: AccountInfo<'info>,
-    /// CHECK: Safe, as we are not reading from the account.
-    #[account()]    //TODO account attribute required?
+    /// CHECK: Safe, as we are passing it directly to the SwapPool program
     pub token_program: UncheckedAccount<'info>,
 
     // Changing accounts
     #[account(
-        constraint=!connection_state_account.connected_interface_program.eq(&Pubkey::default()), //TODO delete account to remove connection ? => avoid check
-        // seeds = [        // ! TODO - Disabled until source chain and sender is known
-        //     &interface_state_account.key().to_bytes(),
-        //     &header[0..8],  // ! The source chain
-        //     &header[8..40]  // ! The source pool id (transaction signer)
-        // ],
-        // bump
+        seeds = [
+            &interface_state_account.key().to_bytes(),
+            channel_id.as_bytes(),                                          // ! The channel through which the packet has been received
+            CHANNEL_SEED
+        ],
+        bump = channel_state_account.channel_state_account_bump
     )]
-    pub connection_state_account: Account<'info, ConnectionState>
+    pub channel_state_account: Account<'info, ChannelState>,
+    #[account(
+        constraint = connection_state_account.enabled @ ErrorCode::SourcePoolNotConnected,    // The source pool must be connected (and the connection enabled)
+        seeds = [
+            &interface_state_account.key().to_bytes(),
+            channel_state_account.remote_chain.to_le_bytes().as_ref(),      // ! The source chain, derived from the channel
+            address_bytes(&message, FROM_VAULT_END)?                        // ! The source pool id present in the payload
+        ],
+        bump = connection_state_account.connection_state_account_bump
+    )]
+    pub connection_state_account: Account<'info, ConnectionState>,
+
+    // Replay protection
+    #[account(mut)]
+    /// CHECK: Verified and created by the on_recv_packet handler (the seeds depend on the decoded payload)
+    pub receipt: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub receipt_rent_payer: Signer<'info>,
+    pub system_program: Program<'info, System>
+
+    // Remaining accounts used for dynamic accounts (asset swaps)
+    // 0 -     - output_asset_mint
+    // 1 - mut - output_asset_wallet
+    // 2 - mut - swap_pool_asset_wallet
+    // 3 -     - swap_pool_authority
+    // 4 - mut - underwrite_escrow (must always be provided)
+    // 5 - mut - underwriter_wallet (only if the swap has been underwritten)
+    // 6 - mut - underwrite_escrow_rent_payer (only if the swap has been underwritten)
 }
 
 impl<'info> OnIBCInvocation<'info> {
 
+    /// Creates the receipt of an incoming swap. The receipt address is derived from the source chain, the source pool and
+    /// the swap hash, so that the creation fails if the same swap is received twice.
+    pub fn create_receipt(
+        &self,
+        source_pool: &Pubkey,
+        swap_hash: [u8; 32]
+    ) -> Result<()> {
+
+        let interface_state_account_key = self.interface_state_account.key();
+        let remote_chain_bytes = self.connection_state_account.remote_chain.to_le_bytes();
+        let seeds: &[&[u8]] = &[
+            interface_state_account_key.as_ref(),
+            remote_chain_bytes.as_ref(),
+            source_pool.as_ref(),
+            &swap_hash,
+            RECEIPT_SEED
+        ];
+
+        let (expected_receipt, receipt_bump) = Pubkey::find_program_address(seeds, &crate::ID);
+        if self.receipt.key().ne(&expected_receipt) {
+            return Err(error!(ErrorCode::InvalidReceiptAccount));
+        }
+
+        if !self.receipt.data_is_empty() {
+            return Err(error!(ErrorCode::SwapAlreadyExecuted));
+        }
+
+        let receipt_seeds: &[&[u8]] = &[
+            interface_state_account_key.as_ref(),
+            remote_chain_bytes.as_ref(),
+            source_pool.as_ref(),
+            &swap_hash,
+            RECEIPT_SEED,
+            &[receipt_bump]
+        ];
+
+        // NOTE: The receipt account may have been funded beforehand (to prevent the receipt creation). Hence the lamports
+        // are topped up instead of using 'create_account' in that case.
+        let rent_lamports = Rent::get()?.minimum_balance(ReceiptState::LEN);
+        let current_lamports = self.receipt.lamports();
+
+        if current_lamports == 0 {
+            system_program::create_account(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::CreateAccount {
+                        from: self.receipt_rent_payer.to_account_info(),
+                        to: self.receipt.to_account_info()
+                    },
+                    &[receipt_seeds]
+                ),
+                rent_lamports,
+                ReceiptState::LEN as u64,
+                &crate::ID
+            )?;
+        }
+        else {
+            let required_lamports = rent_lamports.saturating_sub(current_lamports);
+            if required_lamports > 0 {
+                system_program::transfer(
+                    CpiContext::new(
+                        self.system_program.to_account_info(),
+                        system_program::Transfer {
+                            from: self.receipt_rent_payer.to_account_info(),
+                            to: self.receipt.to_account_info()
+                        }
+                    ),
+                    required_lamports
+                )?;
+            }
+
+            system_program::allocate(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::Allocate { account_to_allocate: self.receipt.to_account_info() },
+                    &[receipt_seeds]
+                ),
+                ReceiptState::LEN as u64
+            )?;
+
+            system_program::assign(
+                CpiContext::new_with_signer(
+                    self.system_program.to_account_info(),
+                    system_program::Assign { account_to_assign: self.receipt.to_account_info() },
+                    &[receipt_seeds]
+                ),
+                &crate::ID
+            )?;
+        }
+
+        let receipt = ReceiptState {
+            interface: interface_state_account_key,
+            rent_payer: self.receipt_rent_payer.key(),
+            created_at: Clock::get()?.unix_timestamp
+        };
+        receipt.try_serialize(&mut &mut self.receipt.try_borrow_mut_data()?[..])?;
+
+        Ok(())
+    }
+
     pub fn invoke_in_swap(
         &self,
         output_asset_index: u8,
@@ -758,9 +1060,102 @@ impl<'info> OnIBCInvocation<'info> {
     }
 
 
+    pub fn invoke_fill_underwrite(
+        &self,
+        identifier: [u8; 32],
+        asset_mint: AccountInfo<'info>,
+        swap_pool_asset_wallet: AccountInfo<'info>,
+        underwriter_wallet: AccountInfo<'info>,
+        swap_authority: AccountInfo<'info>,
+        underwrite_escrow: AccountInfo<'info>,
+        underwrite_escrow_rent_payer: AccountInfo<'info>
+    ) -> ProgramResult {
+
+        // Build CPI data (sighash + arguments)
+        let mut data_vec = SIGHASH_FILL_UNDERWRITE.try_to_vec().unwrap();
+        data_vec.append(&mut FillUnderwriteArgs {
+            identifier
+        }.try_to_vec().unwrap()); //TODO beter way to do this?
+
+        // Build instruction
+        let instruction = Instruction {
+            data: data_vec,
+            program_id: self.swap_pool_program.key(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: self.swap_pool.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: asset_mint.key(),                   // Underwritten asset mint
+                    is_signer: false,
+                    is_writable: false,
+                },
+                AccountMeta {
+                    pubkey: swap_pool_asset_wallet.key(),       // Swap pool underwritten asset wallet
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: underwriter_wallet.key(),           // Underwriter wallet (verified by the SwapPool program)
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: swap_authority.key(),               // Swap pool authority
+                    is_signer: false,
+                    is_writable: false,
+                },
+                AccountMeta {
+                    pubkey: underwrite_escrow.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: underwrite_escrow_rent_payer.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: self.interface_state_account.key(),
+                    is_signer: true,
+                    is_writable: false,
+                },
+                AccountMeta {
+                    pubkey: self.token_program.key(),
+                    is_signer: false,
+                    is_writable: false,
+                }
+            ]
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.swap_pool.to_account_info(),
+                asset_mint,
+                swap_pool_asset_wallet,
+                underwriter_wallet,
+                swap_authority,
+                underwrite_escrow,
+                underwrite_escrow_rent_payer,
+                self.interface_state_account.to_account_info(),
+                self.token_program.to_account_info()
+            ],
+            &[&[
+                &self.swap_pool.key().to_bytes(),
+                &[self.interface_state_account.interface_state_account_bump]
+            ]]
+        )
+    }
+
+
     pub fn invoke_liquidity_in_swap(
         &self,
         liquidity_units_x64: [u64; 4],
+        min_pool_tokens: u64,
+        min_reference_asset: u64,
         swap_pool_token_mint: AccountInfo<'info>,
         destination_pool_token_wallet: AccountInfo<'info>,
         swap_authority: AccountInfo<'info>
@@ -769,7 +1164,9 @@ impl<'info> OnIBCInvocation<'info> {
         // Build CPI data (sighash + arguments)
         let mut data_vec = SIGHASH_IN_LIQUIDITY_SWAP.try_to_vec().unwrap();
         data_vec.append(&mut InLiquiditySwapArgs {
-            liquidity_units_x64
+            liquidity_units_x64,
+            min_pool_tokens,
+            min_reference_asset
         }.try_to_vec().unwrap()); //TODO beter way to do this?
 
         // Build instruction
@@ -835,19 +1232,20 @@ impl<'info> OnIBCInvocation<'info> {
 pub struct OnIBCResult<'info> {
     /// CHECK: unused
     pub port_registration: UncheckedAccount<'info>,
-    // #[account()]    // ! TODO add seeds => polymerase receiver set to pda with no seeds?
-    // pub polymerase_authority: Signer<'info>, // TODO disabled for now (until Polymer contract is implemented)
+    pub polymerase_authority: Signer<'info>,
 
     #[account(
-        constraint = interface_state_account.key().eq(&Pubkey::new(&message[1..33])) @ ErrorCode::InvalidInterfaceAccount, // ! The interface_state_account, derived from the provided swap_pool account, must match the source cross chain pool id present in the payload (i.e. verify the provided swap pool) 
+        has_one    = polymerase_authority @ ErrorCode::InvalidPolymeraseAuthority,                                         // ! Only the Polymerase endpoint may deliver acks/timeouts
+        constraint = interface_state_account.key().eq(&Pubkey::new(address_bytes(&message, FROM_VAULT_END)?)) @ ErrorCode::InvalidInterfaceAccount, // ! The interface_state_account, derived from the provided swap_pool account, must match the source cross chain pool id present in the payload (i.e. verify the provided swap pool) 
         constraint = interface_state_account.swap_pool == swap_pool.key() @ ErrorCode::InvalidSwapPoolAccount              // ! The swap_pool must match the one saved
+        // NOTE: Acks/timeouts are processed even while paused, so that the swap escrows can always be released
     )]
-    pub interface_state_account: Box<Account<'info, CrossChainSwapInterfaceState>>,    //TODO! must verify that it has been created using swap_pool
+    pub interface_state_account: Box<Account<'info, CrossChainSwapInterfaceState>>,
     
     #[account(mut)]
-    /// CHECK: //TODO
+    /// CHECK: Verified against the swap pool saved in the interface_state_account
     pub swap_pool: AccountInfo<'info>,
-    /// CHECK: //TODO
+    /// CHECK: Verified to be the owner of the swap_pool
     #[account(
         constraint = swap_pool_program.key().eq(swap_pool.owner) @ ErrorCode::InvalidSwapPoolProgram                       // ! Make sure the provided swap pool program matches the expected one
     )]
@@ -862,12 +1260,18 @@ pub struct OnIBCResult<'info> {
     /// CHECK: safe, as we are passing it directly to the SwapPool program
     pub system_program: UncheckedAccount<'info>,
 
-    // Remaining accounts used for dynamic accounts
+    // Remaining accounts used for dynamic accounts (asset swaps)
     // 0 -     - asset_mint
     // 1 - mut - fallback_wallet
     // 2 - mut - swap_pool_asset_wallet
     // 3 - mut - swap_escrow
     // 4 - mut - swap_escrow_rent_payer
+
+    // Remaining accounts used for dynamic accounts (liquidity swaps)
+    // 0 - mut - swap_pool_token_mint
+    // 1 - mut - fallback_wallet
+    // 2 - mut - liquidity_escrow
+    // 3 - mut - liquidity_escrow_rent_payer
 }
 
 
@@ -875,14 +1279,14 @@ impl<'info> OnIBCResult<'info> {
 
     pub fn invoke_out_swap_ack(
         &self,
-        source_swap_id: u32,
+        escrow_id: [u8; 32],
         swap_escrow: AccountInfo<'info>,
         swap_escrow_rent_payer: AccountInfo<'info>
     ) -> ProgramResult {
 
         // Build CPI data (sighash + arguments)
         let mut data_vec = SIGHASH_OUT_SWAP_ACK.try_to_vec().unwrap();
-        data_vec.append(&mut OutSwapAckArgs {escrow_nonce: source_swap_id}.try_to_vec().unwrap()); //TODO beter way to do this?
+        data_vec.append(&mut OutSwapAckArgs {escrow_id}.try_to_vec().unwrap()); //TODO beter way to do this?
 
         // Build instruction
         let instruction = Instruction {
@@ -937,7 +1341,7 @@ impl<'info> OnIBCResult<'info> {
 
     pub fn invoke_out_swap_timeout(
         &self,
-        source_swap_id: u32,
+        escrow_id: [u8; 32],
         asset_mint: AccountInfo<'info>,
         fallback_wallet: AccountInfo<'info>,
         swap_pool_asset_wallet: AccountInfo<'info>,
@@ -948,7 +1352,7 @@ impl<'info> OnIBCResult<'info> {
         // Build CPI data (sighash + arguments)
         let mut data_vec = SIGHASH_OUT_SWAP_TIMEOUT.try_to_vec().unwrap();
         data_vec.append(&mut OutSwapTimeoutArgs {
-            escrow_nonce: source_swap_id
+            escrow_id
         }.try_to_vec().unwrap()); //TODO beter way to do this?
 
         // Build instruction
@@ -1030,6 +1434,173 @@ impl<'info> OnIBCResult<'info> {
         )
 
     }
+
+
+    pub fn invoke_out_liquidity_swap_ack(
+        &self,
+        escrow_id: [u8; 32],
+        liquidity_escrow: AccountInfo<'info>,
+        liquidity_escrow_rent_payer: AccountInfo<'info>
+    ) -> ProgramResult {
+
+        // Build CPI data (sighash + arguments)
+        let mut data_vec = SIGHASH_OUT_LIQUIDITY_SWAP_ACK.try_to_vec().unwrap();
+        data_vec.append(&mut OutLiquiditySwapAckArgs {escrow_id}.try_to_vec().unwrap()); //TODO beter way to do this?
+
+        // Build instruction
+        let instruction = Instruction {
+            data: data_vec,
+            program_id: self.swap_pool_program.key(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: self.swap_pool.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: liquidity_escrow.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: liquidity_escrow_rent_payer.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: self.interface_state_account.key(),
+                    is_signer: true,
+                    is_writable: false,
+                },
+                AccountMeta {
+                    pubkey: self.system_program.key(),
+                    is_signer: false,
+                    is_writable: false,
+                }
+            ]
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.swap_pool.to_account_info(),
+                liquidity_escrow,
+                liquidity_escrow_rent_payer,
+                self.interface_state_account.to_account_info(),
+                self.system_program.to_account_info()
+            ],
+            &[&[
+                &self.swap_pool.key().to_bytes(),
+                &[self.interface_state_account.interface_state_account_bump]
+            ]]
+        )
+
+    }
+
+
+    pub fn invoke_out_liquidity_swap_timeout(
+        &self,
+        escrow_id: [u8; 32],
+        swap_pool_token_mint: AccountInfo<'info>,
+        fallback_wallet: AccountInfo<'info>,
+        liquidity_escrow: AccountInfo<'info>,
+        liquidity_escrow_rent_payer: AccountInfo<'info>
+    ) -> ProgramResult {
+
+        // Build CPI data (sighash + arguments)
+        let mut data_vec = SIGHASH_OUT_LIQUIDITY_SWAP_TIMEOUT.try_to_vec().unwrap();
+        data_vec.append(&mut OutLiquiditySwapTimeoutArgs {
+            escrow_id
+        }.try_to_vec().unwrap()); //TODO beter way to do this?
+
+        // Build instruction
+        let instruction = Instruction {
+            data: data_vec,
+            program_id: self.swap_pool_program.key(),
+            accounts: vec![
+                AccountMeta {
+                    pubkey: self.swap_pool.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: swap_pool_token_mint.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: fallback_wallet.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: self.swap_pool_authority.key(),
+                    is_signer: false,
+                    is_writable: false,
+                },
+                AccountMeta {
+                    pubkey: self.token_program.key(),
+                    is_signer: false,
+                    is_writable: false,
+                },
+                AccountMeta {
+                    pubkey: liquidity_escrow.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: liquidity_escrow_rent_payer.key(),
+                    is_signer: false,
+                    is_writable: true,
+                },
+                AccountMeta {
+                    pubkey: self.interface_state_account.key(),
+                    is_signer: true,
+                    is_writable: false,
+                },
+                AccountMeta {
+                    pubkey: self.system_program.key(),
+                    is_signer: false,
+                    is_writable: false,
+                }
+            ]
+        };
+
+        invoke_signed(
+            &instruction,
+            &[
+                self.swap_pool.to_account_info(),
+                swap_pool_token_mint,
+                fallback_wallet,
+                self.swap_pool_authority.to_account_info(),
+                self.token_program.to_account_info(),
+                liquidity_escrow,
+                liquidity_escrow_rent_payer,
+                self.interface_state_account.to_account_info(),
+                self.system_program.to_account_info(),
+            ],
+            &[&[
+                &self.swap_pool.key().to_bytes(),
+                &[self.interface_state_account.interface_state_account_bump]
+            ]]
+        )
+
+    }
+}
+
+
+#[derive(Accounts)]
+pub struct ReclaimReceipt<'info> {
+    pub interface_state_account: Account<'info, CrossChainSwapInterfaceState>,
+    #[account(
+        mut,
+        has_one = rent_payer @ ErrorCode::InvalidReceiptRentPayer,
+        constraint = receipt.interface == interface_state_account.key() @ ErrorCode::InvalidInterfaceAccount,
+        close = rent_payer
+    )]
+    pub receipt: Account<'info, ReceiptState>,
+    #[account(mut)]
+    pub rent_payer: Signer<'info>
 }
 
 
@@ -1042,22 +1613,163 @@ pub struct CrossChainSwapInterfaceState {
     pub swap_pool: Pubkey,
     pub swap_pool_authority: Pubkey,
     pub polymerase_endpoint_program: Pubkey,    //TODO Hardcode?
+    pub polymerase_authority: Pubkey,           // Derived from polymerase_endpoint_program, signs incoming packets, acks and timeouts
+    pub paused: bool,
+    pub receipt_reclaim_delay: u64,             // Seconds after which the rent of the swap receipts may be reclaimed
     pub interface_state_account_bump: u8
 }
 
 impl CrossChainSwapInterfaceState {
-    pub const LEN: usize = 8 + 32*4 + 1;  // NOTE: includes discriminator
+    pub const LEN: usize = 8 + 32*5 + 1 + 8 + 1;  // NOTE: includes discriminator
 }
 
 
 #[account]
 pub struct ConnectionState {
     pub connected_interface_program: Pubkey,
+    pub remote_chain: u64,
+    pub remote_pool: Pubkey,
+    pub enabled: bool,
     pub connection_state_account_bump: u8
 }
 
 impl ConnectionState {
-    pub const LEN: usize = 8 + 32*1 + 1;  // NOTE: includes discriminator
+    pub const LEN: usize = 8 + 32*2 + 8 + 1 + 1;  // NOTE: includes discriminator
+}
+
+
+/// Links a (local) IBC channel to the chain on its other end.
+#[account]
+pub struct ChannelState {
+    pub remote_chain: u64,
+    pub channel_state_account_bump: u8
+}
+
+impl ChannelState {
+    pub const LEN: usize = 8 + 8 + 1;  // NOTE: includes discriminator
+}
+
+
+/// Created for every executed incoming swap, to prevent the same swap from being executed more than once.
+#[account]
+pub struct ReceiptState {
+    pub interface: Pubkey,
+    pub rent_payer: Pubkey,
+    pub created_at: i64
+}
+
+impl ReceiptState {
+    pub const LEN: usize = 8 + 32*2 + 8;  // NOTE: includes discriminator
+}
+
+
+
+// Helpers **********************************************************************************************************************
+
+/// Computes the identifier of an incoming asset swap, which is used to match an underwrite with the swap once it arrives.
+/// The source chain, the source pool and the block number of the swap (used as the swap nonce) are included, so that
+/// identical swaps coming from different sources cannot be matched with the same underwrite.
+pub fn compute_underwrite_identifier(
+    source_chain: u64,
+    source_pool: &Pubkey,
+    nonce: u32,
+    target_pool: &Pubkey,
+    target_asset_index: u8,
+    units_x64: &[u64; 4],
+    min_output: u64,
+    destination: &Pubkey,
+    underwrite_incentive_x16: u16
+) -> [u8; 32] {
+    hashv(&[
+        &source_chain.to_be_bytes(),
+        &source_pool.to_bytes(),
+        &nonce.to_be_bytes(),
+        &target_pool.to_bytes(),
+        &[target_asset_index],
+        &units_x64.iter().flat_map(|el| {el.to_be_bytes()}).collect::<Vec<u8>>(),
+        &min_output.to_be_bytes(),
+        &destination.to_bytes(),
+        &underwrite_incentive_x16.to_be_bytes()
+    ]).to_bytes()
+}
+
+/// Verifies that at least 'count' remaining accounts have been provided.
+pub fn check_remaining_accounts(remaining_accounts: &[AccountInfo], count: usize) -> Result<()> {
+    if remaining_accounts.len() < count {
+        return Err(error!(ErrorCode::MissingRemainingAccounts));
+    }
+    Ok(())
+}
+
+/// Computes the hash of an asset swap, which identifies the swap on both the source and the destination chains.
+/// NOTE: Includes the same fields as the EVM implementation (_computeSendAssetHash).
+pub fn compute_send_asset_hash(
+    to_account: &Pubkey,
+    units_x64: &[u64; 4],
+    from_amount: &[u64; 4],
+    from_asset: &Pubkey,
+    block_number: u32
+) -> [u8; 32] {
+    hashv(&[
+        &to_account.to_bytes(),
+        &encode_u256(units_x64),
+        &encode_u256(from_amount),
+        &from_asset.to_bytes(),
+        &block_number.to_be_bytes()
+    ]).to_bytes()
+}
+
+/// Computes the hash of a liquidity swap, which identifies the swap on both the source and the destination chains.
+/// NOTE: Includes the same fields as the EVM implementation (_computeSendLiquidityHash).
+pub fn compute_send_liquidity_hash(
+    to_account: &Pubkey,
+    units_x64: &[u64; 4],
+    from_amount: &[u64; 4],
+    block_number: u32
+) -> [u8; 32] {
+    hashv(&[
+        &to_account.to_bytes(),
+        &encode_u256(units_x64),
+        &encode_u256(from_amount),
+        &block_number.to_be_bytes()
+    ]).to_bytes()
+}
+
+/// Computes the identifier of the escrow of an outgoing asset swap. It is derived from the swap contents carried by the
+/// payload, so that the escrow can be found once the ack/timeout of the swap is received.
+/// NOTE: Computed over the same fields as the swap hash of the EVM implementation, but using sha256 over the 32-byte
+/// Solana public keys (rather than keccak256 over the abi.encodePacked 65-byte address encoding), hence the ids do not
+/// match the EVM ones. The swap units can be computed beforehand with the calc_send_asset instruction of the swap pool.
+pub fn compute_asset_escrow_id(
+    to_account: &Pubkey,
+    units_x64: &[u64; 4],
+    from_amount: u64,
+    from_asset: &Pubkey,
+    block_number: u32
+) -> [u8; 32] {
+    compute_send_asset_hash(
+        to_account,
+        units_x64,
+        &[from_amount, 0, 0, 0],
+        from_asset,
+        block_number
+    )
+}
+
+/// Computes the identifier of the escrow of an outgoing liquidity swap. It is derived from the swap contents carried by
+/// the payload, so that the escrow can be found once the ack/timeout of the swap is received.
+pub fn compute_liquidity_escrow_id(
+    to_account: &Pubkey,
+    units_x64: &[u64; 4],
+    from_amount: u64,
+    block_number: u32
+) -> [u8; 32] {
+    compute_send_liquidity_hash(
+        to_account,
+        units_x64,
+        &[from_amount, 0, 0, 0],
+        block_number
+    )
 }
 
 
@@ -1073,12 +1785,22 @@ pub struct CallMultichainArgs {
 
 #[derive(PartialEq, Debug, Clone, BorshSerialize)]
 pub struct OutSwapAckArgs {
-    escrow_nonce: u32
+    escrow_id: [u8; 32]
 }
 
 #[derive(PartialEq, Debug, Clone, BorshSerialize)]
 pub struct OutSwapTimeoutArgs {
-    escrow_nonce: u32
+    escrow_id: [u8; 32]
+}
+
+#[derive(PartialEq, Debug, Clone, BorshSerialize)]
+pub struct OutLiquiditySwapAckArgs {
+    escrow_id: [u8; 32]
+}
+
+#[derive(PartialEq, Debug, Clone, BorshSerialize)]
+pub struct OutLiquiditySwapTimeoutArgs {
+    escrow_id: [u8; 32]
 }
 
 #[derive(PartialEq, Debug, Clone, BorshSerialize)]
@@ -1089,9 +1811,16 @@ pub struct InSwapArgs {
     approx: bool
 }
 
+#[derive(PartialEq, Debug, Clone, BorshSerialize)]
+pub struct FillUnderwriteArgs {
+    identifier: [u8; 32]
+}
+
 #[derive(PartialEq, Debug, Clone, BorshSerialize)]
 pub struct InLiquiditySwapArgs {
-    liquidity_units_x64: [u64; 4]
+    liquidity_units_x64: [u64; 4],
+    min_pool_tokens: u64,
+    min_reference_asset: u64
 }
 
 
@@ -1127,4 +1856,27 @@ pub enum ErrorCode {
     InvalidPoolTokenWalletAccount,
     #[msg("The specified minimum output exceeds the allowed one by the chain implementation.")]
     MinimumOutputExceedsMaxAllowed,
+    #[msg("The provided underwrite escrow account does not match the one derived from the request payload.")]
+    InvalidUnderwriteEscrowAccount,
+    #[msg("The interface is paused.")]
+    InterfacePaused,
+    #[msg("The provided polymerase authority does not match the expected one.")]
+    InvalidPolymeraseAuthority,
+    #[msg("Not enough remaining accounts have been provided.")]
+    MissingRemainingAccounts,
+    #[msg("The provided connection state account does not belong to the interface.")]
+    InvalidConnectionStateAccount,
+
+
+    // Receipts
+    #[msg("The provided receipt account does not match the one derived from the request payload.")]
+    InvalidReceiptAccount,
+    #[msg("The swap has already been executed.")]
+    SwapAlreadyExecuted,
+    #[msg("The receipt cannot be reclaimed yet.")]
+    ReceiptNotReclaimable,
+    #[msg("The receipt reclaim delay must be longer than the maximum packet validity.")]
+    ReceiptReclaimDelayTooShort,
+    #[msg("The provided rent payer does not match the one of the receipt.")]
+    InvalidReceiptRentPayer,
 }
\ No newline at end of file
@@ -0,0 +1,179 @@
+#[cfg(test)]
+mod test_catalyst_payload {
+
+    use anchor_lang::prelude::*;
+
+    use crate::catalyst_payload::*;
+
+    // Payloads as encoded by the EVM implementation (see the abi.encodePacked calls of evm/src/CatalystChainInterface.sol)
+    const EVM_ASSET_SWAP_PAYLOAD: &str = concat!(
+        "00",    // CONTEXT
+        "1400000000000000000000000000000000000000000000000000000000000000000000000000000000000000001111111111111111111111111111111111111111",    // FROM_VAULT (EVM address)
+        "200000000000000000000000000000000000000000000000000000000000000000aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",    // TO_VAULT (Solana address)
+        "200000000000000000000000000000000000000000000000000000000000000000bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",    // TO_ACCOUNT (Solana address)
+        "0000000000000000000000000000000000000000000000030000000000003039",    // UNITS
+        "02",    // TO_ASSET_INDEX
+        "00000000000000000000000000000000000000000000000000000000000003e8",    // MIN_OUT
+        "00000000000000000000000000000000000000000000000000000000075bcd15",    // FROM_AMOUNT
+        "1400000000000000000000000000000000000000000000000000000000000000000000000000000000000000002222222222222222222222222222222222222222",    // FROM_ASSET (EVM address)
+        "01020304",    // BLOCK_NUMBER
+        "0506",    // UW_INCENTIVE
+        "0002",    // DATA_LENGTH
+        "dead",    // DATA
+    );
+
+    const EVM_LIQUIDITY_SWAP_PAYLOAD: &str = concat!(
+        "01",    // CONTEXT
+        "1400000000000000000000000000000000000000000000000000000000000000000000000000000000000000001111111111111111111111111111111111111111",    // FROM_VAULT (EVM address)
+        "200000000000000000000000000000000000000000000000000000000000000000aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa",    // TO_VAULT (Solana address)
+        "200000000000000000000000000000000000000000000000000000000000000000bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",    // TO_ACCOUNT (Solana address)
+        "0000000000000000000000000000000000000000000000030000000000003039",    // UNITS
+        "00000000000000000000000000000000000000000000000000000000000001f4",    // MIN_OUT
+        "0000000000000000000000000000000000000000000000000000000000000258",    // MIN_REFERENCE
+        "00000000000000000000000000000000000000000000000000000000075bcd15",    // FROM_AMOUNT
+        "01020304",    // BLOCK_NUMBER
+        "0000",    // DATA_LENGTH
+    );
+
+    fn decode_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len()).step_by(2).map(|i| u8::from_str_radix(&hex[i..i+2], 16).unwrap()).collect()
+    }
+
+    fn evm_address(byte: u8) -> Pubkey {
+        let mut address = [0u8; 32];
+        address[12..].fill(byte);
+        Pubkey::new_from_array(address)
+    }
+
+    fn asset_swap_payload() -> AssetSwapPayload {
+        AssetSwapPayload {
+            from_vault: evm_address(0x11),
+            to_vault: Pubkey::new_from_array([0xaa; 32]),
+            to_account: Pubkey::new_from_array([0xbb; 32]),
+            units_x64: [12345, 3, 0, 0],
+            to_asset_index: 2,
+            min_out: [1000, 0, 0, 0],
+            from_amount: [123456789, 0, 0, 0],
+            from_asset: evm_address(0x22),
+            block_number: 0x01020304,
+            underwrite_incentive_x16: 0x0506,
+            data: vec![0xde, 0xad]
+        }
+    }
+
+    fn liquidity_swap_payload() -> LiquiditySwapPayload {
+        LiquiditySwapPayload {
+            from_vault: evm_address(0x11),
+            to_vault: Pubkey::new_from_array([0xaa; 32]),
+            to_account: Pubkey::new_from_array([0xbb; 32]),
+            units_x64: [12345, 3, 0, 0],
+            min_pool_tokens: [500, 0, 0, 0],
+            min_reference_asset: [600, 0, 0, 0],
+            from_amount: [123456789, 0, 0, 0],
+            block_number: 0x01020304,
+            data: vec![]
+        }
+    }
+
+
+
+    // Test round trips *********************************************************************************************************
+
+    #[test]
+    fn test_asset_swap_payload_round_trip() {
+        let payload = asset_swap_payload();
+
+        let encoded_payload = payload.encode().unwrap();
+        assert_eq!(encoded_payload.len(), CTX0_DATA_START + payload.data.len());
+
+        assert_eq!(AssetSwapPayload::decode(&encoded_payload).unwrap(), payload);
+    }
+
+    #[test]
+    fn test_liquidity_swap_payload_round_trip() {
+        let payload = liquidity_swap_payload();
+
+        let encoded_payload = payload.encode().unwrap();
+        assert_eq!(encoded_payload.len(), CTX1_DATA_START);
+
+        assert_eq!(LiquiditySwapPayload::decode(&encoded_payload).unwrap(), payload);
+    }
+
+
+
+    // Test EVM compatibility ***************************************************************************************************
+
+    #[test]
+    fn test_asset_swap_payload_evm_layout() {
+        let evm_payload = decode_hex(EVM_ASSET_SWAP_PAYLOAD);
+
+        assert_eq!(AssetSwapPayload::decode(&evm_payload).unwrap(), asset_swap_payload());
+        assert_eq!(asset_swap_payload().encode().unwrap(), evm_payload);
+    }
+
+    #[test]
+    fn test_liquidity_swap_payload_evm_layout() {
+        let evm_payload = decode_hex(EVM_LIQUIDITY_SWAP_PAYLOAD);
+
+        assert_eq!(LiquiditySwapPayload::decode(&evm_payload).unwrap(), liquidity_swap_payload());
+        assert_eq!(liquidity_swap_payload().encode().unwrap(), evm_payload);
+    }
+
+
+
+    // Test invalid payloads ****************************************************************************************************
+
+    #[test]
+    fn test_invalid_payloads() {
+        let encoded_payload = asset_swap_payload().encode().unwrap();
+
+        // Truncated payload
+        assert_eq!(
+            AssetSwapPayload::decode(&encoded_payload[..CTX0_DATA_START-1]),
+            Err(PayloadErrorCode::InvalidPayloadLength.into())
+        );
+
+        // Data length mismatch
+        assert_eq!(
+            AssetSwapPayload::decode(&encoded_payload[..encoded_payload.len()-1]),
+            Err(PayloadErrorCode::InvalidPayloadLength.into())
+        );
+
+        // Wrong context
+        assert_eq!(
+            LiquiditySwapPayload::decode(&encoded_payload),
+            Err(PayloadErrorCode::InvalidPayloadContext.into())
+        );
+
+        // Address longer than 32 bytes
+        let mut invalid_payload = encoded_payload.clone();
+        invalid_payload[FROM_VAULT_LENGTH_POS] = 33;
+        assert_eq!(
+            AssetSwapPayload::decode(&invalid_payload),
+            Err(PayloadErrorCode::InvalidAddressEncoding.into())
+        );
+
+        // Non-zero padding of an EVM address
+        let mut invalid_payload = encoded_payload;
+        invalid_payload[FROM_VAULT_END-32] = 1;
+        assert_eq!(
+            AssetSwapPayload::decode(&invalid_payload),
+            Err(PayloadErrorCode::InvalidAddressEncoding.into())
+        );
+    }
+
+    #[test]
+    fn test_address_bytes() {
+        let encoded_payload = asset_swap_payload().encode().unwrap();
+
+        assert_eq!(address_bytes(&encoded_payload, TO_VAULT_END).unwrap(), &[0xaa; 32]);
+        assert_eq!(
+            address_bytes(&encoded_payload[..TO_VAULT_END-1], TO_VAULT_END),
+            Err(PayloadErrorCode::InvalidPayloadLength.into())
+        );
+        assert_eq!(
+            address_bytes(&[], FROM_VAULT_END),
+            Err(PayloadErrorCode::InvalidPayloadLength.into())
+        );
+    }
+}
@@ -2,7 +2,8 @@ use std::ops::{Shr, Shl};
 
 use anchor_lang::prelude::*;
 use shared_lib::u256::U256;
-use shared_lib::fixed_point_math_x64::{log2_x64, div_x64, inv_pow_x64, inv_pow2_x64, pow2_x64, LN2_X64, mul_x64};
+use shared_lib::fixed_point_math_x64::{log2_x64, div_x64, inv_pow_x64, inv_pow2_x64, pow2_x64, LN2_X64, mul_x64, FixedPointError, Precision};
+use shared_lib::fixed_point_math_x64::{log2_x64_with_precision, inv_pow_x64_with_precision, inv_pow2_x64_with_precision};
 
 const ONE_X64: U256 = U256([0, 1, 0, 0]);
 
@@ -15,7 +16,7 @@ pub fn calc_asset_amount_for_pool_tokens(pool_token_balance: u64, asset_balance:
         return Ok(pool_token_balance)
     }
 
-    Ok(asset_balance.checked_mul(pool_token_balance).unwrap().checked_div(asset_eq_balance).unwrap())
+    Ok(asset_balance.checked_mul(pool_token_balance).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.checked_div(asset_eq_balance).ok_or(IntegralCalculationErrorCode::ArithmeticError)?)
 }
 
 
@@ -26,7 +27,8 @@ pub fn out_swap_x64(
     input: U256,                    // x
     source_asset_balance: U256,     // At
     source_asset_weight: U256,      // WA
-    approx: bool
+    approx: bool,
+    precision: Option<Precision>    // Fractional bits of the log2/pow2 computations (None for the default ones, ignored if approx)
 ) -> Result<U256> {
     // Computes the integral
     // int_{At}^{At+x} WA/w dw
@@ -34,18 +36,19 @@ pub fn out_swap_x64(
     if approx {
         return Ok(
             div_x64(
-                source_asset_weight.checked_mul(input).unwrap().shl(64),
-                source_asset_balance.checked_add(input).unwrap().checked_mul(LN2_X64).unwrap()
-            ).unwrap()
+                source_asset_weight.checked_mul(input).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.shl(64),
+                source_asset_balance.checked_add(input).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.checked_mul(LN2_X64).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+            ).map_err(fixed_point_error)?
         )
     }
 
     // WA * ln((At + x)/At)
     Ok(source_asset_weight.checked_mul(
-        log2_x64(
-            div_x64(source_asset_balance.checked_add(input).unwrap(), source_asset_balance).unwrap()
-        ).unwrap()
-    ).unwrap())
+        log2_x64_with_optional_precision(
+            div_x64(source_asset_balance.checked_add(input).ok_or(IntegralCalculationErrorCode::ArithmeticError)?, source_asset_balance).map_err(fixed_point_error)?,
+            precision
+        ).map_err(fixed_point_error)?
+    ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?)
 }
 
 
@@ -53,28 +56,29 @@ pub fn in_swap(
     units_x64: U256,                // U
     target_asset_balance: U256,     // Bt
     target_asset_weight: U256,      // WB
-    approx: bool
+    approx: bool,
+    precision: Option<Precision>    // Fractional bits of the log2/pow2 computations (None for the default ones, ignored if approx)
 ) -> Result<U256> {
     // Solves the following integral for 'y'
     // int_{Bt-y}^{Bt} WB/w dW
 
     if approx {
-        let units_times_ln2_x64 = mul_x64(units_x64, LN2_X64).unwrap();
+        let units_times_ln2_x64 = mul_x64(units_x64, LN2_X64).map_err(fixed_point_error)?;
 
         return Ok(
             div_x64(
-                target_asset_balance.checked_mul(units_times_ln2_x64).unwrap(),
-                target_asset_weight.shl(64).checked_add(units_times_ln2_x64).unwrap()
-            ).unwrap()
+                target_asset_balance.checked_mul(units_times_ln2_x64).ok_or(IntegralCalculationErrorCode::ArithmeticError)?,
+                target_asset_weight.shl(64).checked_add(units_times_ln2_x64).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+            ).map_err(fixed_point_error)?
         )
     }
 
     // Bt * (1 - exp(-U/WB))
     Ok(target_asset_balance.checked_mul(
         ONE_X64.checked_sub(
-            inv_pow2_x64(units_x64.checked_div(target_asset_weight).unwrap()).unwrap()
-        ).unwrap()
-    ).unwrap().shr(64u8))
+            inv_pow2_x64_with_optional_precision(units_x64.checked_div(target_asset_weight).ok_or(IntegralCalculationErrorCode::ArithmeticError)?, precision).map_err(fixed_point_error)?
+        ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+    ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.shr(64u8))
 }
 
 pub fn full_swap(
@@ -83,43 +87,96 @@ pub fn full_swap(
     source_asset_weight: U256,
     target_asset_balance: U256,
     target_asset_weight: U256,
-    approx: bool
+    approx: bool,
+    precision: Option<Precision>    // Fractional bits of the log2/pow2 computations (None for the default ones, ignored if approx)
 ) -> Result<U256> {
 
     // Bt * (1 - (At + input) / At) ^ (-WA/WB))       NOTE: (At + input) / At >= 1 as input > 0
 
     if source_asset_weight == target_asset_weight {
-        return Ok(target_asset_balance.checked_mul(input).unwrap().checked_div(source_asset_balance.checked_add(input).unwrap()).unwrap())
+        return Ok(target_asset_balance.checked_mul(input).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.checked_div(source_asset_balance.checked_add(input).ok_or(IntegralCalculationErrorCode::ArithmeticError)?).ok_or(IntegralCalculationErrorCode::ArithmeticError)?)
     }
 
     if approx {
         return Ok(
-            target_asset_balance.checked_mul(source_asset_weight).unwrap().checked_mul(input).unwrap().checked_div(
-                target_asset_weight.checked_mul(source_asset_balance).unwrap().checked_add(
-                    source_asset_weight.checked_add(target_asset_weight).unwrap().checked_mul(input).unwrap()
-                ).unwrap()
-            ).unwrap()
+            target_asset_balance.checked_mul(source_asset_weight).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.checked_mul(input).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.checked_div(
+                target_asset_weight.checked_mul(source_asset_balance).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.checked_add(
+                    source_asset_weight.checked_add(target_asset_weight).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.checked_mul(input).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+                ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+            ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
         )
     }
 
     Ok(target_asset_balance.checked_mul( 
         ONE_X64.checked_sub(
-            inv_pow_x64(
-                div_x64(source_asset_balance.checked_add(input).unwrap(), source_asset_balance).unwrap(),
+            inv_pow_x64_with_optional_precision(
+                div_x64(source_asset_balance.checked_add(input).ok_or(IntegralCalculationErrorCode::ArithmeticError)?, source_asset_balance).map_err(fixed_point_error)?,
                 div_x64(
                     source_asset_weight,
                     target_asset_weight
-                ).unwrap()
-            ).unwrap()
-        ).unwrap()
-    ).unwrap().shr(64u8))
+                ).map_err(fixed_point_error)?,
+                precision
+            ).map_err(fixed_point_error)?
+        ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+    ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.shr(64u8))
+}
+
+
+// The fixed point functions computed with the given precision, or the default (cheaper) implementations if none is given
+fn log2_x64_with_optional_precision(x: U256, precision: Option<Precision>) -> std::result::Result<U256, FixedPointError> {
+    match precision {
+        Some(precision) => log2_x64_with_precision(x, precision),
+        None            => log2_x64(x)
+    }
 }
 
+fn inv_pow2_x64_with_optional_precision(x: U256, precision: Option<Precision>) -> std::result::Result<U256, FixedPointError> {
+    match precision {
+        Some(precision) => inv_pow2_x64_with_precision(x, precision),
+        None            => inv_pow2_x64(x)
+    }
+}
+
+fn inv_pow_x64_with_optional_precision(x: U256, p: U256, precision: Option<Precision>) -> std::result::Result<U256, FixedPointError> {
+    match precision {
+        Some(precision) => inv_pow_x64_with_precision(x, p, precision),
+        None            => inv_pow_x64(x, p)
+    }
+}
 
-#[error_code]
+
+#[error_code(offset = 7000)]
 pub enum IntegralCalculationErrorCode {
     #[msg("Arithmetic Error. Possible overflow/underflow.")]
     ArithmeticError,
+
+    #[msg("Fixed point overflow/underflow.")]
+    FixedPointOverflow,
+
+    #[msg("Fixed point division by zero.")]
+    FixedPointDivisionByZero,
+
+    #[msg("Logarithm of zero.")]
+    LogOfZero,
+
+    #[msg("Exponent too large.")]
+    ExponentTooLarge,
+
+    #[msg("Exponent too large, the result would lose all precision.")]
+    PrecisionLoss,
+}
+
+/// Maps a fixed point math error to the corresponding calculation error (the exceeded bound, if any, is logged).
+pub fn fixed_point_error(error: FixedPointError) -> anchor_lang::error::Error {
+    msg!("Fixed point math error: {}", error);
+
+    match error {
+        FixedPointError::Overflow                => IntegralCalculationErrorCode::FixedPointOverflow.into(),
+        FixedPointError::DivisionByZero          => IntegralCalculationErrorCode::FixedPointDivisionByZero.into(),
+        FixedPointError::LogOfZero               => IntegralCalculationErrorCode::LogOfZero.into(),
+        FixedPointError::ExponentTooLarge { .. } => IntegralCalculationErrorCode::ExponentTooLarge.into(),
+        FixedPointError::PrecisionLoss { .. }    => IntegralCalculationErrorCode::PrecisionLoss.into(),
+    }
 }
 
 
@@ -137,9 +194,9 @@ pub fn calc_out_liquidity_swap_x64(
     // WA * ln((At + x)/At)
     Ok(source_asset_weight.checked_mul(
         log2_x64(
-            div_x64(source_asset_eq_balance, source_asset_eq_balance.checked_sub(input_liquidity).unwrap()).unwrap()
-        ).unwrap()
-    ).unwrap())
+            div_x64(source_asset_eq_balance, source_asset_eq_balance.checked_sub(input_liquidity).ok_or(IntegralCalculationErrorCode::ArithmeticError)?).map_err(fixed_point_error)?
+        ).map_err(fixed_point_error)?
+    ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?)
 }
 
 
@@ -154,7 +211,67 @@ pub fn calc_in_liquidity_swap(
     // Bt * (2^(U/W_SUM)-1)
     Ok(target_asset_eq_balance.checked_mul(
         pow2_x64(
-            liquidity_units_x64.checked_div(target_assets_aggr_weight).unwrap()     // Weight is an integer for this case, can safely >> 64
-        ).unwrap().checked_sub(ONE_X64).unwrap()
-    ).unwrap().shr(64u8))
+            liquidity_units_x64.checked_div(target_assets_aggr_weight).ok_or(IntegralCalculationErrorCode::ArithmeticError)?     // Weight is an integer for this case, can safely >> 64
+        ).map_err(fixed_point_error)?.checked_sub(ONE_X64).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+    ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.shr(64u8))
+}
+
+
+/// Computes the amount of the reference asset owned by 'pool_tokens' once minted, as per the EVM implementation: the
+/// weighted geometric mean of the pool balances, multiplied by the share of the pool tokens. The pool eq balances are used
+/// as the point on the pool invariant (on EVM, the current balances are used).
+pub fn calc_reference_asset_amount(
+    pool_assets_eq_balances: &[u64],    // B0_i
+    pool_assets_weights: &[u64],        // W_i
+    pool_tokens: U256,                  // Pool tokens to be minted
+    pool_tokens_supply: U256            // Current pool tokens supply
+) -> Result<U256> {
+    // (prod_i B0_i^W_i)^(1/W_SUM) = 2^((sum_i log2(B0_i) * W_i) / W_SUM)
+
+    let mut weighted_log2_sum_x64 = U256::zero();
+    let mut aggregate_weight = U256::zero();
+
+    for (eq_balance, weight) in pool_assets_eq_balances.iter().zip(pool_assets_weights.iter()) {
+
+        weighted_log2_sum_x64 = weighted_log2_sum_x64.checked_add(
+            log2_x64(U256::from(*eq_balance).shl(64u8)).map_err(fixed_point_error)?     // No overflow guaranteed, going from u64 to u256
+                .checked_mul(U256::from(*weight)).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+        ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?;
+
+        aggregate_weight = aggregate_weight.checked_add(U256::from(*weight)).ok_or(IntegralCalculationErrorCode::ArithmeticError)?;
+    }
+
+    let reference_asset_balance = pow2_x64(
+        weighted_log2_sum_x64.checked_div(aggregate_weight).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+    ).map_err(fixed_point_error)?.shr(64u8);
+
+    // The pool tokens to be minted are included in the supply
+    Ok(
+        reference_asset_balance
+            .checked_mul(pool_tokens).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+            .checked_div(
+                pool_tokens_supply.checked_add(pool_tokens).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+            ).ok_or(IntegralCalculationErrorCode::ArithmeticError)?
+    )
+}
+
+
+
+// Fees *************************************************************************************************************************
+
+pub fn calc_vault_fee(
+    amount: u64,            // x
+    vault_fee_x64: u64      // Fee as a fraction of the amount (x64, < 1)
+) -> Result<u64> {
+    // x * fee. Cannot exceed u64, as vault_fee_x64 < 1
+    Ok(U256::from(amount).checked_mul(U256::from(vault_fee_x64)).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.shr(64u8).as_u64())
+}
+
+
+pub fn calc_governance_fee(
+    vault_fee: u64,                 // Fee taken from the swap input
+    governance_fee_share_x64: u64   // Governance share of the vault fee (x64, < 1)
+) -> Result<u64> {
+    // vault_fee * share. Cannot exceed vault_fee, as governance_fee_share_x64 < 1
+    Ok(U256::from(vault_fee).checked_mul(U256::from(governance_fee_share_x64)).ok_or(IntegralCalculationErrorCode::ArithmeticError)?.shr(64u8).as_u64())
 }
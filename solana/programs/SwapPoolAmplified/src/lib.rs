@@ -1,6 +1,7 @@
 use std::convert::TryInto;
 
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
 use anchor_spl::token::{Mint, Token, TokenAccount};
 
 use ibc_interface;
@@ -8,10 +9,11 @@ use ibc_interface::cpi::accounts as interface_accounts;
 use ibc_interface::program::IbcInterface;
 
 use shared_lib::u256::U256;
+use shared_lib::i256_x64::I256X64;
 use shared_lib::fixed_point_math_x64::{mul_x64, pow_x64, inv_pow2_x64};
-use token_utils::create_pda_token_account;
+use token_utils::{create_pda_account, create_pda_token_account};
 
-use std::ops::Shl;
+use std::ops::{Shl, Shr};
 
 pub mod calculation_helpers;
 
@@ -19,14 +21,27 @@ const ONE_X64: U256 = U256([0, 1, 0, 0]);
 
 declare_id!("Fg6PaFpoGXkYsidMpWTK6W2BeZ7FEfcYkg476zPFsLnA");
 
-const NUMASSETS: usize = 3;
+const MAX_ASSETS: usize = 8;   // Maximum number of assets per pool (the actual count is set on initialization). NOTE: initializing large pools may require address lookup tables to fit the transaction size limit.
 
 const DECAYRATE: u64 = 60*60*24;
 
-const POOL_ASSET_WALLET_SEED : &[u8] = b"poolAsset";
-const POOL_TOKEN_MINT_SEED   : &[u8] = b"poolMint";
-const POOL_AUTHORITY_SEED    : &[u8] = b"poolAuth";
-const POOL_ESCROW_SEED       : &[u8] = b"poolEscrow";
+const MIN_AMPLIFICATION_ADJUSTMENT_TIME: u64 = 60*60*24*7;     // Minimum duration of an amplification adjustment
+const MAX_AMPLIFICATION_ADJUSTMENT_TIME: u64 = 60*60*24*365;   // Maximum duration of an amplification adjustment
+const MAX_AMPLIFICATION_CHANGE_FACTOR: u64 = 10;               // Maximum factor by which the amplification may change per adjustment
+
+const MAX_GOVERNANCE_FEE_SHARE_X64: u64 = 13835058055282163712;   // 75% of the vault fee (matches the EVM implementation)
+const MAX_VAULT_FEE_X64: u64 = 1844674407370955161;               // 10% (NOTE: the EVM implementation allows up to 100%)
+
+const POOL_ASSET_WALLET_SEED     : &[u8] = b"poolAsset";
+const POOL_TOKEN_MINT_SEED       : &[u8] = b"poolMint";
+const POOL_AUTHORITY_SEED        : &[u8] = b"poolAuth";
+const POOL_ESCROW_SEED           : &[u8] = b"poolEscrow";
+const POOL_LIQUIDITY_ESCROW_SEED : &[u8] = b"poolLiqEscrow";
+const POOL_UNDERWRITE_SEED       : &[u8] = b"poolUnderwrite";    // NOTE: must match the seed used by the IBCInterface
+
+const MAX_BLOCK_NUMBER_AGE: u32 = 150;   // Maximum age (in slots) of the block number of an outgoing swap (approx. the validity of a recent blockhash)
+
+const UNDERWRITE_EXPIRY: u64 = 60*60*24;   // Time after which an unfilled underwrite can be expired by anyone
 
 #[program]
 pub mod swap_pool_amplified {
@@ -36,26 +51,36 @@ pub mod swap_pool_amplified {
     pub fn initialize<'a, 'b, 'c, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, Initialize<'info>>,
         k: u64,
-        initial_asset_balances: [u64; NUMASSETS],
-        asset_weights: [u64; NUMASSETS]
+        initial_asset_balances: Vec<u64>,
+        asset_weights: Vec<u64>
     ) -> Result<()> {
 
         // ! Save the pubkey of setup_master to verify setup instructions (only allow setup_master to call setup instructions)
         ctx.accounts.swap_pool_state_account.setup_master = ctx.accounts.setup_master.key();
         ctx.accounts.swap_pool_state_account.dao_authority = ctx.accounts.dao_authority.key();
-        ctx.accounts.swap_pool_state_account.amplification_x64 = (ONE_X64 / k).0;   // TODO Add serialisation/desarialisation to U256 struct, avoid having to explicitly extract U256 internal array
+        ctx.accounts.swap_pool_state_account.amplification_x64 = (ONE_X64 / k).0;   // TODO Store as U256, avoid having to explicitly extract the U256 internal array (the Anchor IDL can only describe types defined within the program crate)
+        ctx.accounts.swap_pool_state_account.target_amplification_x64 = ctx.accounts.swap_pool_state_account.amplification_x64;
         ctx.accounts.swap_pool_state_account.authority_bump = ctx.bumps.get("swap_pool_authority").unwrap().to_owned();
         ctx.accounts.swap_pool_state_account.token_mint_bump = ctx.bumps.get("swap_pool_token_mint").unwrap().to_owned();
 
-        let asset_count = match initial_asset_balances.iter().position(|balance| balance == &0u64) {
-            Some(count) => count,
-            None => NUMASSETS
-        };
+        let asset_count = initial_asset_balances.len();
 
         if asset_count == 0 {
             return Err(error!(ErrorCode::NoAssetsProvided));
         }
 
+        if asset_count > MAX_ASSETS || asset_weights.len() != asset_count {
+            return Err(error!(ErrorCode::InvalidAssetCount));
+        }
+
+        // NOTE: the state account has been sized for 'asset_count' assets (see the Initialize context)
+        ctx.accounts.swap_pool_state_account.pool_assets_mints       = vec![Pubkey::default(); asset_count];
+        ctx.accounts.swap_pool_state_account.pool_assets_weights     = vec![0; asset_count];
+        ctx.accounts.swap_pool_state_account.pool_assets_eq_balances = vec![0; asset_count];
+        ctx.accounts.swap_pool_state_account.escrowed_assets         = vec![0; asset_count];
+        ctx.accounts.swap_pool_state_account.accrued_governance_fees = vec![0; asset_count];
+        ctx.accounts.swap_pool_state_account.wallets_bumps           = vec![0; asset_count];
+
         // TODO is the following check required?
         // Verify the count of passed remaining accounts
         // 3 Accounts given per added asset:
@@ -181,40 +206,171 @@ pub mod swap_pool_amplified {
         Ok(())
     }
 
-    //TODO add function to cancel SwapPool setup and recover rent?
+    pub fn cancel_setup<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CancelSetup<'info>>
+    ) -> Result<()> {
+
+        // NOTE: cancel_setup can only be called before 'finish_setup', as the latter resets the setup_master to the default
+        // (empty) pubkey, for which no signature can be provided.
+
+        let swap_pool_state_account = &ctx.accounts.swap_pool_state_account;
+
+        let asset_count = swap_pool_state_account.asset_count();
+
+        let mut refunded_amounts: Vec<u64> = vec![0; asset_count];
+
+        let mut destination_asset_wallets: Vec<Pubkey> = vec![Pubkey::default(); asset_count];
+
+        // Verify the count of passed remaining accounts
+        // 2 Accounts given per pool asset:
+        //      - Swap pool asset wallet
+        //      - Destination asset wallet
+        // Followed (if the interface has been created) by:
+        //      - IBC interface program
+        //      - Interface state account
+        //      - Connection state accounts created during the setup (any count)
+        if ctx.remaining_accounts.len() < 2*asset_count || ctx.remaining_accounts.len() == 2*asset_count + 1 {
+            return Err(error!(ErrorCode::InvalidRemainingAccountsCount));
+        }
+
+        for asset_index in 0..asset_count {
+
+            let swap_pool_asset_wallet   = &ctx.remaining_accounts[asset_index*2];    // Verified below
+            let destination_asset_wallet = &ctx.remaining_accounts[asset_index*2+1];  // No need to verify, chosen by the setup_master
+
+            // Make sure the swap_pool_asset_wallet provided matches the expected pda
+            let seeds: &[&[u8]] = &[
+                &swap_pool_state_account.key().to_bytes(),
+                &swap_pool_state_account.pool_assets_mints[asset_index].key().to_bytes(),
+                POOL_ASSET_WALLET_SEED,
+                &[swap_pool_state_account.wallets_bumps[asset_index]]
+            ];
+
+            let expected_swap_pool_asset_wallet  = Pubkey::create_program_address(seeds, &ID).unwrap();
+
+            if swap_pool_asset_wallet.key() != expected_swap_pool_asset_wallet {
+                return Err(error!(ErrorCode::InvalidSwapPoolAssetWalletAccount));
+            }
+
+            let swap_pool_asset_wallet_balance = TokenAccount::try_deserialize(&mut &swap_pool_asset_wallet.try_borrow_data()?[..])?.amount;
+
+            let authority_seeds: &[&[u8]] = &[
+                &swap_pool_state_account.key().to_bytes(), // SwapPool state account
+                &POOL_AUTHORITY_SEED[..],                  // Pool authority seed
+                &[swap_pool_state_account.authority_bump]  // PDA bump
+            ];
+
+            // Return the initial assets
+            if swap_pool_asset_wallet_balance > 0 {
+                token_utils::transfer_tokens_using_pda_authority(
+                    swap_pool_asset_wallet_balance,
+                    swap_pool_asset_wallet.to_owned(),
+                    destination_asset_wallet.to_owned(),
+                    ctx.accounts.swap_pool_authority.to_account_info(),
+                    authority_seeds,
+                    ctx.accounts.token_program.to_account_info()
+                );
+            }
+
+            // Close the (now empty) asset wallet and refund its rent to the setup_master
+            token_utils::close_token_account_using_pda_authority(
+                swap_pool_asset_wallet.to_owned(),
+                ctx.accounts.setup_master.to_account_info(),
+                ctx.accounts.swap_pool_authority.to_account_info(),
+                authority_seeds,
+                ctx.accounts.token_program.to_account_info()
+            );
+
+            // Save data for logging
+            destination_asset_wallets[asset_index] = destination_asset_wallet.key();
+            refunded_amounts[asset_index] = swap_pool_asset_wallet_balance;
+        }
+
+        // Close the interface and its connections, refunding their rent to the setup_master
+        let ibc_accounts = &ctx.remaining_accounts[2*asset_count..];
+        if !ibc_accounts.is_empty() {
+
+            let ibc_interface_program   = &ibc_accounts[0];
+            let interface_state_account = &ibc_accounts[1];
+
+            if ibc_interface_program.key().ne(&ibc_interface::ID) {
+                return Err(error!(ErrorCode::InvalidIBCInterfaceProgram));
+            }
+
+            if interface_state_account.key().ne(&swap_pool_state_account.ibc_interface) {
+                return Err(error!(ErrorCode::InvalidIBCInterfaceAccount));
+            }
+
+            ibc_interface::cpi::close_interface(
+                CpiContext::new_with_signer(
+                    ibc_interface_program.to_owned(),
+                    interface_accounts::CloseInterface {
+                        swap_pool_authority: ctx.accounts.swap_pool_authority.to_account_info(),
+                        interface_state_account: interface_state_account.to_owned(),
+                        rent_receiver: ctx.accounts.setup_master.to_account_info()
+                    },
+                    &[&[
+                        &swap_pool_state_account.key().to_bytes(),
+                        &POOL_AUTHORITY_SEED[..],
+                        &[swap_pool_state_account.authority_bump]
+                    ]]
+                ).with_remaining_accounts(ibc_accounts[2..].to_vec())
+            )?;
+        }
+
+        // NOTE: The pool token mint cannot be closed (not supported by the SPL Token program), hence its rent cannot be
+        // recovered. The swap_pool_state_account is closed (and its rent refunded) by the 'close' constraint of CancelSetup.
+
+        emit!(CancelSetupEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+
+            refunded_asset_amounts: refunded_amounts,
+            destination_asset_wallets
+        });
+
+        Ok(())
+    }
 
     pub fn deposit<'a, 'b, 'c, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, Deposit<'info>>,
         pool_tokens_amount: u64
     ) -> Result<()> {
 
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::Deposit)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
+
         // Update the liqudity security limit. Since the limit is based on the current totalSupply, changing the totalSupply
         // upwards by depositing changes the limit.
-        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
         ctx.accounts.swap_pool_state_account.update_liquidity_units_inflow(
             0,
             ctx.accounts.swap_pool_token_mint.supply,
             current_timestamp
         )?;
 
-        let mut deposited_amounts: [u64; NUMASSETS] = [0; NUMASSETS];
-
         let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
 
-        let pool_assets_mints: [Pubkey; NUMASSETS] = swap_pool_state_account.pool_assets_mints.clone();
+        let asset_count = swap_pool_state_account.asset_count();
 
-        let mut depositor_asset_wallets: [Pubkey; NUMASSETS] = [Pubkey::default(); NUMASSETS];
+        let mut deposited_amounts: Vec<u64> = vec![0; asset_count];
+
+        let pool_assets_mints: Vec<Pubkey> = swap_pool_state_account.pool_assets_mints.clone();
+
+        let mut depositor_asset_wallets: Vec<Pubkey> = vec![Pubkey::default(); asset_count];
 
         // Verify the count of passed remaining accounts
         // 3 Accounts given per deposited asset:
         //      - Asset mint
         //      - Swap pool asset wallet
         //      - Depositor asset wallet
-        for asset_index in 0..NUMASSETS {
+        if ctx.remaining_accounts.len() != 3*asset_count {
+            return Err(error!(ErrorCode::InvalidRemainingAccountsCount));
+        }
 
-            if swap_pool_state_account.pool_assets_mints[asset_index].eq(&Pubkey::default()) {
-                break;
-            }
+        for asset_index in 0..asset_count {
 
             // Verify the depositor_asset_wallet and the swap_pool_asset_wallet accounts
             // ! TODO VERY IMPORTANT, THIS VERIFICATION HAS TO BE REVISED
@@ -251,7 +407,8 @@ pub mod swap_pool_amplified {
 
             let asset_deposit_amount = calculation_helpers::calc_asset_amount_for_pool_tokens(
                 pool_tokens_for_asset,
-                swap_pool_asset_wallet_balance,     // Escrowed tokens are NOT subtracted from the total balance => deposits should return less
+                swap_pool_asset_wallet_balance
+                    .checked_sub(swap_pool_state_account.accrued_governance_fees[asset_index]).unwrap(),  // Escrowed tokens are NOT subtracted from the total balance => deposits should return less
                 asset_eq_balance
             )?;
 
@@ -303,14 +460,209 @@ pub mod swap_pool_amplified {
         Ok(())
     }
 
+    pub fn deposit_mixed<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, Deposit<'info>>,
+        amounts: Vec<u64>,
+        min_pool_tokens: u64
+    ) -> Result<()> {
+
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::Deposit)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
+
+        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+
+        let asset_count = swap_pool_state_account.asset_count();
+
+        if amounts.len() != asset_count {
+            return Err(error!(ErrorCode::InvalidAssetAmountsCount));
+        }
+
+        let mut depositor_asset_wallets: Vec<Pubkey> = vec![Pubkey::default(); asset_count];
+
+        // Verify the count of passed remaining accounts
+        // 3 Accounts given per pool asset (even if the deposited amount is 0):
+        //      - Asset mint
+        //      - Swap pool asset wallet
+        //      - Depositor asset wallet
+        if ctx.remaining_accounts.len() != 3*asset_count {
+            return Err(error!(ErrorCode::InvalidRemainingAccountsCount));
+        }
+
+        // Compute the units corresponding to the deposited assets
+        let amplification = U256(swap_pool_state_account.amplification_x64);
+        let one_minus_amp_x64 = ONE_X64.checked_sub(amplification).unwrap();
+        let mut aggregate_weight_x64 = U256::from(0);
+        let mut units_x64 = U256::from(0);
+
+        for asset_index in 0..asset_count {
+
+            aggregate_weight_x64 = aggregate_weight_x64.checked_add(
+                U256::from(swap_pool_state_account.pool_assets_weights[asset_index]).checked_mul(
+                    pow_x64(
+                        U256::from(swap_pool_state_account.pool_assets_eq_balances[asset_index]).shl(64), // No overflow guaranteed, going from u64 to u256
+                        one_minus_amp_x64
+                    ).unwrap()
+                ).unwrap()
+            ).unwrap();
+
+            let amount = amounts[asset_index];
+            if amount == 0 {
+                continue;
+            }
+
+            // Verify the depositor_asset_wallet and the swap_pool_asset_wallet accounts
+            let asset_mint             = &ctx.remaining_accounts[asset_index*3];
+            let swap_pool_asset_wallet = &ctx.remaining_accounts[asset_index*3+1];
+            let depositor_asset_wallet = &ctx.remaining_accounts[asset_index*3+2];  // No need to verify, assets taken from here
+
+            // Verify the asset mint
+            if asset_mint.key().ne(&swap_pool_state_account.pool_assets_mints[asset_index]) {
+                return Err(error!(ErrorCode::InvalidAssetMintAccount));
+            }
+
+            // Make sure the swap_pool_asset_wallet provided matches the expected pda
+            let seeds: &[&[u8]] = &[
+                &swap_pool_state_account.key().to_bytes(),
+                &swap_pool_state_account.pool_assets_mints[asset_index].key().to_bytes(),
+                POOL_ASSET_WALLET_SEED,
+                &[swap_pool_state_account.wallets_bumps[asset_index]]
+            ];
+
+            let expected_swap_pool_asset_wallet  = Pubkey::create_program_address(seeds, &ID).unwrap();
+
+            if swap_pool_asset_wallet.key() != expected_swap_pool_asset_wallet {
+                return Err(error!(ErrorCode::InvalidSwapPoolAssetWalletAccount));
+            }
+
+            let swap_pool_asset_wallet_balance = TokenAccount::try_deserialize(&mut &swap_pool_asset_wallet.try_borrow_data()?[..])?.amount;
+
+            // Compute the units corresponding to the deposited amount
+            units_x64 = units_x64.checked_add(
+                calculation_helpers::out_swap_x64(
+                    U256::from(amount),
+                    U256::from(
+                        swap_pool_asset_wallet_balance
+                            .checked_sub(swap_pool_state_account.accrued_governance_fees[asset_index]).unwrap()   // Escrowed tokens are NOT subtracted from the total balance => deposits should return less
+                    ),
+                    U256::from(swap_pool_state_account.pool_assets_weights[asset_index]),
+                    amplification,
+                    None    // Default log2/pow2 precision
+                )?
+            ).unwrap();
+
+            token_utils::transfer_tokens_using_pda_authority(  //TODO use transfer_checked?
+                amount,
+                depositor_asset_wallet.to_owned(),
+                swap_pool_asset_wallet.to_owned(),
+                ctx.accounts.swap_pool_authority.to_account_info(),
+                &[
+                    &swap_pool_state_account.key().to_bytes(), // SwapPool state account
+                    &POOL_AUTHORITY_SEED[..],                  // Pool authority seed
+                    &[swap_pool_state_account.authority_bump]  // PDA bump
+                ],
+                ctx.accounts.token_program.to_account_info()
+            );
+
+            // Save data for logging
+            depositor_asset_wallets[asset_index] = depositor_asset_wallet.key();
+        }
+
+        // Subtract the vault fee from the units, so that deposits and withdrawals cannot be used as a way of swapping.
+        // NOTE: as on the EVM implementation, the governance fee is not taken.
+        let units_x64 = units_x64.checked_sub(
+            units_x64.checked_mul(U256::from(swap_pool_state_account.vault_fee_x64)).unwrap().shr(64u8)
+        ).unwrap();
+
+        // Compute the pool tokens corresponding to the first asset of the pool
+        let asset_0_pool_tokens = calculation_helpers::calc_in_liquidity_swap(
+            units_x64,
+            U256::from(swap_pool_state_account.pool_assets_eq_balances[0]),
+            aggregate_weight_x64,
+            amplification
+        )?;
+
+        // Compute the total pool tokens from the ones corresponding to the first asset
+        let pool_tokens_supply = U256::from(ctx.accounts.swap_pool_token_mint.supply);
+        let total_pool_tokens = asset_0_pool_tokens
+            .checked_mul(pool_tokens_supply).unwrap()
+            .checked_div(U256::from(swap_pool_state_account.pool_assets_eq_balances[0])).unwrap()
+            .as_u64();      // U256 to u64 will panic if overflow
+
+        if total_pool_tokens < min_pool_tokens {
+            return Err(error!(ErrorCode::DepositMinOutputNotFulfilled));
+        }
+
+        // Update the eq balances
+        swap_pool_state_account.pool_assets_eq_balances[0] = swap_pool_state_account.pool_assets_eq_balances[0].checked_add(
+            asset_0_pool_tokens.as_u64()
+        ).unwrap();
+
+        for asset_index in 1..asset_count {
+
+            let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
+
+            swap_pool_state_account.pool_assets_eq_balances[asset_index] = asset_eq_balance.checked_add(
+                U256::from(total_pool_tokens)
+                    .checked_mul(U256::from(asset_eq_balance)).unwrap()
+                    .checked_div(pool_tokens_supply).unwrap()
+                    .as_u64()
+            ).unwrap();
+
+        }
+
+        // Verify and update the security limit
+        swap_pool_state_account.update_liquidity_units_inflow(
+            total_pool_tokens,
+            ctx.accounts.swap_pool_token_mint.supply,
+            current_timestamp
+        )?;
+
+        // Mint pool tokens for the depositor
+        token_utils::mint_tokens_using_pda_authority(
+            total_pool_tokens,
+            ctx.accounts.depositor_pool_token_wallet.to_account_info(),
+            ctx.accounts.swap_pool_token_mint.to_account_info(),
+            ctx.accounts.swap_pool_authority.to_account_info(),
+            &[
+                    &swap_pool_state_account.key().to_bytes(),  // SwapPool state account
+                    &POOL_AUTHORITY_SEED[..],                   // Pool authority seed
+                    &[swap_pool_state_account.authority_bump]   // PDA bump
+                ],
+            ctx.accounts.token_program.to_account_info()
+        );
+
+        emit!(DepositEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+
+            deposited_asset_amounts: amounts,
+            depositor_asset_wallets: depositor_asset_wallets,
+            withdrawn_pool_token_amount: total_pool_tokens,
+            depositor_pool_token_wallet: ctx.accounts.depositor_pool_token_wallet.key()
+
+        });
+
+        Ok(())
+    }
+
     pub fn withdraw<'a, 'b, 'c, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, Withdraw<'info>>,
-        pool_tokens_amount: u64
+        pool_tokens_amount: u64,
+        min_outputs: Vec<u64>
     ) -> Result<()> {
 
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::Withdrawal)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
+
         // Update the liqudity security limit. Since the limit is based on the current totalSupply, changing the totalSupply
         // downwards by withdrawing changes the limit.
-        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
         ctx.accounts.swap_pool_state_account.update_liquidity_units_inflow(
             0,
             ctx.accounts.swap_pool_token_mint.supply,
@@ -320,13 +672,19 @@ pub mod swap_pool_amplified {
         // Burn pool tokens from the withdrawer wallet.
         ctx.accounts.burn_pool_tokens_of_withdrawer(pool_tokens_amount)?;
 
-        let mut withdrawn_amounts: [u64; NUMASSETS] = [0; NUMASSETS];
-
         let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
 
-        let pool_assets_mints: [Pubkey; NUMASSETS] = swap_pool_state_account.pool_assets_mints.clone();
+        let asset_count = swap_pool_state_account.asset_count();
+
+        if min_outputs.len() != asset_count {
+            return Err(error!(ErrorCode::InvalidAssetAmountsCount));
+        }
+
+        let mut withdrawn_amounts: Vec<u64> = vec![0; asset_count];
+
+        let pool_assets_mints: Vec<Pubkey> = swap_pool_state_account.pool_assets_mints.clone();
 
-        let mut withdrawer_asset_wallets: [Pubkey; NUMASSETS] = [Pubkey::default(); NUMASSETS];
+        let mut withdrawer_asset_wallets: Vec<Pubkey> = vec![Pubkey::default(); asset_count];
 
         let initial_pool_tokens_supply = ctx.accounts.swap_pool_token_mint.supply;    // Theoretically, this is not needed, as the 'burn' operation does not update the data that has been loaded from the account, but this is here for clarity
         
@@ -334,11 +692,11 @@ pub mod swap_pool_amplified {
         //      - Asset mint
         //      - Swap pool asset wallet
         //      - Depositor asset wallet
-        for asset_index in 0..NUMASSETS {
+        if ctx.remaining_accounts.len() != 3*asset_count {
+            return Err(error!(ErrorCode::InvalidRemainingAccountsCount));
+        }
 
-            if swap_pool_state_account.pool_assets_mints[asset_index].eq(&Pubkey::default()) {
-                break;
-            }
+        for asset_index in 0..asset_count {
 
             // Verify the withdrawer_asset_wallet and the swap_pool_asset_wallet accounts
             // ! TODO VERY IMPORTANT, THIS VERIFICATION HAS TO BE REVISED
@@ -376,10 +734,15 @@ pub mod swap_pool_amplified {
             let asset_withdraw_amount = calculation_helpers::calc_asset_amount_for_pool_tokens(
                 pool_tokens_for_asset,
                 swap_pool_asset_wallet_balance
+                    .checked_sub(swap_pool_state_account.accrued_governance_fees[asset_index]).unwrap()
                     .checked_sub(swap_pool_state_account.escrowed_assets[asset_index]).unwrap(),         // Escrowed tokens ARE subtracted from the total balance => withdrawals should return less
                 asset_eq_balance
             )?;
 
+            if asset_withdraw_amount < min_outputs[asset_index] {
+                return Err(error!(ErrorCode::WithdrawMinOutputNotFulfilled));
+            }
+
             // Update asset eq balance
             swap_pool_state_account.pool_assets_eq_balances[asset_index] = asset_eq_balance.checked_sub(pool_tokens_for_asset).unwrap();
         
@@ -414,32 +777,233 @@ pub mod swap_pool_amplified {
         Ok(())
     }
 
-    pub fn local_swap(ctx: Context<LocalSwap>, amount: u64, min_yield: u64) -> Result<()> {
+    pub fn withdraw_mixed<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, Withdraw<'info>>,
+        pool_tokens_amount: u64,
+        withdraw_ratios_x64: Vec<[u64; 4]>,
+        min_outputs: Vec<u64>
+    ) -> Result<()> {
 
-        let from_asset_index = ctx.accounts.swap_pool_state_account.get_asset_index(
-            &ctx.accounts.input_asset_mint.key()
-        ).unwrap();
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::Withdrawal)?;
 
-        let to_asset_index = ctx.accounts.swap_pool_state_account.get_asset_index(
-            &ctx.accounts.output_asset_mint.key()
-        ).unwrap();
-        
-        let out: u64 = calculation_helpers::full_swap(
-            U256::from(amount),
-            U256::from(ctx.accounts.swap_pool_input_asset_wallet.amount),
-            U256::from(ctx.accounts.swap_pool_state_account.pool_assets_weights[from_asset_index]),
-            U256::from(
-                ctx.accounts.swap_pool_output_asset_wallet.amount.checked_sub(
-                    ctx.accounts.swap_pool_state_account.escrowed_assets[to_asset_index]
-                ).unwrap()
-            ),
-            U256::from(ctx.accounts.swap_pool_state_account.pool_assets_weights[to_asset_index]),
-            U256(ctx.accounts.swap_pool_state_account.amplification_x64)
-        )?.as_u64();      // U256 to u64 will panic if overflow
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
 
-        if out < min_yield { return Err(error!(ErrorCode::SwapMinYieldNotFulfilled))}
+        // Update the liqudity security limit. Since the limit is based on the current totalSupply, changing the totalSupply
+        // downwards by withdrawing changes the limit.
+        ctx.accounts.swap_pool_state_account.update_liquidity_units_inflow(
+            0,
+            ctx.accounts.swap_pool_token_mint.supply,
+            current_timestamp
+        )?;
 
-        // Transfer input assets to the pool
+        // Burn pool tokens from the withdrawer wallet.
+        ctx.accounts.burn_pool_tokens_of_withdrawer(pool_tokens_amount)?;
+
+        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+
+        let asset_count = swap_pool_state_account.asset_count();
+
+        if withdraw_ratios_x64.len() != asset_count || min_outputs.len() != asset_count {
+            return Err(error!(ErrorCode::InvalidAssetAmountsCount));
+        }
+
+        // The withdraw ratios are applied to the units remaining after each withdrawal, hence they must not exceed 1
+        if withdraw_ratios_x64.iter().any(|ratio_x64| U256(*ratio_x64) > ONE_X64) {
+            return Err(error!(ErrorCode::InvalidWithdrawRatio));
+        }
+
+        let mut withdrawn_amounts: Vec<u64> = vec![0; asset_count];
+
+        let mut withdrawer_asset_wallets: Vec<Pubkey> = vec![Pubkey::default(); asset_count];
+
+        let initial_pool_tokens_supply = ctx.accounts.swap_pool_token_mint.supply;    // Theoretically, this is not needed, as the 'burn' operation does not update the data that has been loaded from the account, but this is here for clarity
+
+        // Compute the units corresponding to the burnt pool tokens
+        let mut units_x64 = U256::from(0);
+
+        for asset_index in 0..asset_count {
+
+            let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
+
+            let pool_tokens_for_asset = pool_tokens_amount
+                .checked_mul(asset_eq_balance).unwrap()
+                .checked_div(initial_pool_tokens_supply).unwrap();
+
+            units_x64 = units_x64.checked_add(
+                calculation_helpers::calc_out_liquidity_swap_x64(
+                    U256::from(pool_tokens_for_asset),
+                    U256::from(asset_eq_balance),
+                    U256::from(swap_pool_state_account.pool_assets_weights[asset_index]),
+                    U256(swap_pool_state_account.amplification_x64)
+                )?
+            ).unwrap();
+
+            // Update asset eq balance
+            swap_pool_state_account.pool_assets_eq_balances[asset_index] = asset_eq_balance.checked_sub(pool_tokens_for_asset).unwrap();
+
+        }
+
+        // 3 Accounts given per pool asset (even if no assets are withdrawn):
+        //      - Asset mint
+        //      - Swap pool asset wallet
+        //      - Withdrawer asset wallet
+        if ctx.remaining_accounts.len() != 3*asset_count {
+            return Err(error!(ErrorCode::InvalidRemainingAccountsCount));
+        }
+
+        for asset_index in 0..asset_count {
+
+            // Units allocated to the asset. NOTE: the withdraw ratios are applied to the units remaining after each withdrawal
+            let asset_units_x64 = units_x64.checked_mul(U256(withdraw_ratios_x64[asset_index])).unwrap().shr(64u8);
+
+            if asset_units_x64.is_zero() {
+                // After a withdraw ratio of 1, all the following withdraw ratios must be 0
+                if !U256(withdraw_ratios_x64[asset_index]).is_zero() {
+                    return Err(error!(ErrorCode::WithdrawRatioNotZero));
+                }
+
+                // The minimum output must be checked as well for the skipped assets
+                if min_outputs[asset_index] != 0 {
+                    return Err(error!(ErrorCode::WithdrawMinOutputNotFulfilled));
+                }
+
+                continue;
+            }
+
+            units_x64 = units_x64.checked_sub(asset_units_x64).unwrap();    // Cannot underflow, as the withdraw ratios are <= 1
+
+            // Verify the withdrawer_asset_wallet and the swap_pool_asset_wallet accounts
+            let asset_mint              = &ctx.remaining_accounts[asset_index*3];
+            let swap_pool_asset_wallet  = &ctx.remaining_accounts[asset_index*3+1];
+            let withdrawer_asset_wallet = &ctx.remaining_accounts[asset_index*3+2];  // No need to verify, assets sent here
+
+            // Verify the asset mint
+            if asset_mint.key().ne(&swap_pool_state_account.pool_assets_mints[asset_index]) {
+                return Err(error!(ErrorCode::InvalidAssetMintAccount));
+            }
+
+            // Make sure the swap_pool_asset_wallet provided matches the expected pda
+            let seeds: &[&[u8]] = &[
+                &swap_pool_state_account.key().to_bytes(),
+                &swap_pool_state_account.pool_assets_mints[asset_index].key().to_bytes(),
+                POOL_ASSET_WALLET_SEED,
+                &[swap_pool_state_account.wallets_bumps[asset_index]]
+            ];
+
+            let expected_swap_pool_asset_wallet  = Pubkey::create_program_address(seeds, &ID).unwrap();
+
+            if swap_pool_asset_wallet.key() != expected_swap_pool_asset_wallet {
+                return Err(error!(ErrorCode::InvalidSwapPoolAssetWalletAccount));
+            }
+
+            let swap_pool_asset_wallet_balance = TokenAccount::try_deserialize(&mut &swap_pool_asset_wallet.try_borrow_data()?[..])?.amount;
+
+            // Convert the units into the asset
+            let asset_withdraw_amount = calculation_helpers::in_swap(
+                asset_units_x64,
+                U256::from(
+                    swap_pool_asset_wallet_balance
+                        .checked_sub(swap_pool_state_account.escrowed_assets[asset_index]).unwrap()            // Escrowed tokens ARE subtracted from the total balance => withdrawals should return less
+                        .checked_sub(swap_pool_state_account.accrued_governance_fees[asset_index]).unwrap()
+                ),
+                U256::from(swap_pool_state_account.pool_assets_weights[asset_index]),
+                U256(swap_pool_state_account.amplification_x64),
+                None    // Default log2/pow2 precision
+            )?.as_u64();      // U256 to u64 will panic if overflow
+
+            if asset_withdraw_amount < min_outputs[asset_index] {
+                return Err(error!(ErrorCode::WithdrawMinOutputNotFulfilled));
+            }
+
+            token_utils::transfer_tokens_using_pda_authority(
+                asset_withdraw_amount,
+                swap_pool_asset_wallet.to_account_info(),
+                withdrawer_asset_wallet.to_account_info(),
+                ctx.accounts.swap_pool_authority.to_account_info(),
+                &[
+                    &swap_pool_state_account.key().to_bytes(), // SwapPool state account
+                    &POOL_AUTHORITY_SEED[..],                  // Pool authority seed
+                    &[swap_pool_state_account.authority_bump]  // PDA bump
+                ],
+                ctx.accounts.token_program.to_account_info()
+            );
+
+            // Save data for logging
+            withdrawer_asset_wallets[asset_index] = withdrawer_asset_wallet.key();
+            withdrawn_amounts[asset_index] = asset_withdraw_amount;
+        }
+
+        // Make sure all the units have been used (i.e. at least one of the withdraw ratios is set to 1)
+        if !units_x64.is_zero() {
+            return Err(error!(ErrorCode::UnusedUnitsAfterWithdrawal));
+        }
+
+        emit!(WithdrawEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+        
+            withdrawn_asset_amounts: withdrawn_amounts,
+            withdrawer_asset_wallets: withdrawer_asset_wallets,
+        
+            burnt_pool_token_amount: pool_tokens_amount,
+            withdrawer_pool_token_wallet: ctx.accounts.withdrawer_pool_token_wallet.key(),
+        });
+
+        Ok(())
+    }
+
+    pub fn local_swap(ctx: Context<LocalSwap>, amount: u64, min_yield: u64) -> Result<()> {
+
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::LocalSwap)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
+
+        let from_asset_index = ctx.accounts.swap_pool_state_account.get_asset_index(
+            &ctx.accounts.input_asset_mint.key()
+        ).unwrap();
+
+        let to_asset_index = ctx.accounts.swap_pool_state_account.get_asset_index(
+            &ctx.accounts.output_asset_mint.key()
+        ).unwrap();
+
+        // Vault fee, taken from the input amount
+        let vault_fee = calculation_helpers::calc_vault_fee(
+            amount,
+            ctx.accounts.swap_pool_state_account.vault_fee_x64
+        )?;
+
+        // Governance fee, taken from the vault fee
+        let governance_fee = calculation_helpers::calc_governance_fee(
+            vault_fee,
+            ctx.accounts.swap_pool_state_account.governance_fee_share_x64
+        )?;
+        
+        let out: u64 = calculation_helpers::full_swap(
+            U256::from(amount - vault_fee),     // vault_fee <= amount guaranteed by calc_vault_fee
+            U256::from(
+                ctx.accounts.swap_pool_input_asset_wallet.amount.checked_sub(
+                    ctx.accounts.swap_pool_state_account.accrued_governance_fees[from_asset_index]
+                ).unwrap()
+            ),
+            U256::from(ctx.accounts.swap_pool_state_account.pool_assets_weights[from_asset_index]),
+            U256::from(
+                ctx.accounts.swap_pool_output_asset_wallet.amount
+                    .checked_sub(ctx.accounts.swap_pool_state_account.escrowed_assets[to_asset_index]).unwrap()
+                    .checked_sub(ctx.accounts.swap_pool_state_account.accrued_governance_fees[to_asset_index]).unwrap()
+            ),
+            U256::from(ctx.accounts.swap_pool_state_account.pool_assets_weights[to_asset_index]),
+            U256(ctx.accounts.swap_pool_state_account.amplification_x64),
+            None    // Default log2/pow2 precision
+        )?.as_u64();      // U256 to u64 will panic if overflow
+
+        if out < min_yield { return Err(error!(ErrorCode::SwapMinYieldNotFulfilled))}
+
+        // Transfer input assets to the pool
         token_utils::transfer_tokens_using_pda_authority(  //TODO use transfer_checked?
             amount,
             ctx.accounts.input_asset_wallet.to_account_info(),
@@ -467,6 +1031,12 @@ pub mod swap_pool_amplified {
             ctx.accounts.token_program.to_account_info()
         );
 
+        // Accrue the governance fee (kept in the pool asset wallet until collected)
+        if governance_fee != 0 {
+            ctx.accounts.swap_pool_state_account.accrued_governance_fees[from_asset_index] =
+                ctx.accounts.swap_pool_state_account.accrued_governance_fees[from_asset_index].checked_add(governance_fee).unwrap();
+        }
+
 
         ctx.accounts.swap_pool_state_account.max_units_inflow_x64 = 
             U256(ctx.accounts.swap_pool_state_account.max_units_inflow_x64)
@@ -499,7 +1069,7 @@ pub mod swap_pool_amplified {
         
             deposited_asset_amount: amount,
             withdrawn_asset_amount: out,
-            fees: 0
+            fees: vault_fee
         });
 
         Ok(())
@@ -513,23 +1083,51 @@ pub mod swap_pool_amplified {
         destination: Pubkey,
         amount: u64,
         min_output: [u64; 4],
-        escrow_nonce: u32,
-        fallback_wallet: Pubkey
+        block_number: u32,          // A recent slot, used to derive the escrow id
+        fallback_wallet: Pubkey,
+        underwrite_incentive_x16: u16
     ) -> Result<()> {
 
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::OutgoingSwap)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
+
         let from_asset_index = ctx.accounts.swap_pool_state_account.get_asset_index(
             &ctx.accounts.input_asset_mint.key()
         ).unwrap();
 
+        // Vault fee, taken from the input amount
+        let vault_fee = calculation_helpers::calc_vault_fee(
+            amount,
+            ctx.accounts.swap_pool_state_account.vault_fee_x64
+        )?;
+
+        // Governance fee, taken from the vault fee
+        let governance_fee = calculation_helpers::calc_governance_fee(
+            vault_fee,
+            ctx.accounts.swap_pool_state_account.governance_fee_share_x64
+        )?;
+
         let units_x64 = calculation_helpers::out_swap_x64(
-            U256::from(amount),
-            U256::from(ctx.accounts.swap_pool_input_asset_wallet.amount),
+            U256::from(amount - vault_fee),     // vault_fee <= amount guaranteed by calc_vault_fee
+            U256::from(
+                ctx.accounts.swap_pool_input_asset_wallet.amount.checked_sub(
+                    ctx.accounts.swap_pool_state_account.accrued_governance_fees[from_asset_index]
+                ).unwrap()
+            ),
             U256::from(ctx.accounts.swap_pool_state_account.pool_assets_weights[from_asset_index]),
-            U256(ctx.accounts.swap_pool_state_account.amplification_x64)
+            U256(ctx.accounts.swap_pool_state_account.amplification_x64),
+            None    // Default log2/pow2 precision
         )?;
 
         //TODO! unitsDiff       line 902
 
+        // The block number must be a recent slot
+        verify_block_number(block_number)?;
+
         let ccsi_program = ctx.accounts.ibc_interface_program.to_account_info(); // Check is the same as the stored program?
         let ccsi_swap_accounts = interface_accounts::CrossChainSwap {
             swap_pool_authority: ctx.accounts.swap_pool_authority.to_account_info(),
@@ -556,14 +1154,16 @@ pub mod swap_pool_amplified {
         ibc_interface::cpi::cross_chain_swap(
             ccsi_swap_ctx,
             chain,
-            false,
             ctx.accounts.swap_pool_state_account.key(),
             target_pool,
             to_asset_index,
             destination,
             units_x64.0,
             min_output,
-            escrow_nonce
+            amount - vault_fee,
+            ctx.accounts.input_asset_mint.key(),
+            block_number,
+            underwrite_incentive_x16
         ).unwrap();
 
 
@@ -581,20 +1181,43 @@ pub mod swap_pool_amplified {
             ctx.accounts.token_program.to_account_info()
         );
 
-        // Escrow the tokens
+        // Accrue the governance fee (kept in the pool asset wallet until collected)
+        if governance_fee != 0 {
+            ctx.accounts.swap_pool_state_account.accrued_governance_fees[from_asset_index] =
+                ctx.accounts.swap_pool_state_account.accrued_governance_fees[from_asset_index].checked_add(governance_fee).unwrap();
+        }
+
+        // Escrow the tokens (the vault fee is not escrowed, as it is not returned on timeout)
+        let escrow_amount = amount - vault_fee;
         ctx.accounts.swap_pool_state_account.escrowed_assets[from_asset_index] =
-            ctx.accounts.swap_pool_state_account.escrowed_assets[from_asset_index].checked_add(amount).unwrap();
+            ctx.accounts.swap_pool_state_account.escrowed_assets[from_asset_index].checked_add(escrow_amount).unwrap();
+
+        // The escrow id is derived from the swap contents, so that it can be recomputed from the payload on ack/timeout
+        let escrow_id = ibc_interface::compute_asset_escrow_id(
+            &destination,
+            &units_x64.0,
+            escrow_amount,
+            &ctx.accounts.input_asset_mint.key(),
+            block_number
+        );
 
-        ctx.accounts.swap_escrow.amount                 = amount;
-        ctx.accounts.swap_escrow.asset_index            = from_asset_index as u8;
-        ctx.accounts.swap_escrow.fallback_wallet        = fallback_wallet;
-        ctx.accounts.swap_escrow.swap_escrow_rent_payer = ctx.accounts.swap_escrow_rent_payer.key();
-        ctx.accounts.swap_escrow.bump                   = ctx.bumps.get("swap_escrow").unwrap().to_owned();
+        ctx.accounts.create_swap_escrow(
+            &escrow_id,
+            SwapEscrow {
+                amount: escrow_amount,
+                asset_index: from_asset_index as u8,
+                fallback_wallet,
+                swap_escrow_rent_payer: ctx.accounts.swap_escrow_rent_payer.key(),
+                bump: 0     // Set by create_swap_escrow
+            }
+        )?;
 
 
         // TODO verify
-        ctx.accounts.swap_pool_state_account.unit_tracker_x64 = ctx.accounts.swap_pool_state_account.unit_tracker_x64
-            .checked_add(units_x64.as_u128().try_into().unwrap()).unwrap();
+        ctx.accounts.swap_pool_state_account.unit_tracker_x64 = I256X64(ctx.accounts.swap_pool_state_account.unit_tracker_x64)
+            .checked_add_u256(units_x64)
+            .map_err(calculation_helpers::fixed_point_error)?
+            .0;
 
         
         ctx.accounts.swap_pool_state_account.max_units_inflow_x64 = 
@@ -629,14 +1252,15 @@ pub mod swap_pool_amplified {
             target_withdrawer: destination,
             target_chain: chain,
 
-            escrow_nonce,
+            escrow_id,
+            underwrite_incentive_x16,
         
             deposited_asset_mint: ctx.accounts.input_asset_mint.key(),
             depositor_asset_wallet: ctx.accounts.input_asset_wallet.key(),
         
             deposited_asset_amount: amount,
             withdrawn_pool_units_x64: units_x64.0,
-            fees: 0,
+            fees: vault_fee,
         });
 
         Ok(())
@@ -644,9 +1268,11 @@ pub mod swap_pool_amplified {
 
     pub fn out_swap_ack(
         ctx: Context<OutSwapAck>,
-        escrow_nonce: u32          // Used in context
+        escrow_id: [u8; 32]         // Used in context
     ) -> Result<()> {
 
+        // NOTE: Acks and timeouts are processed even while the pool is paused, so that the swap escrows can always be released
+
         let escrowed_amount = ctx.accounts.swap_escrow.amount;
         let escrowed_asset_index = ctx.accounts.swap_escrow.asset_index as usize;
 
@@ -655,7 +1281,7 @@ pub mod swap_pool_amplified {
 
         emit!(OutSwapAckEvent {
             swap_pool: ctx.accounts.swap_pool_state_account.key(),
-            escrow_nonce
+            escrow_id
         });
 
         Ok(())
@@ -663,9 +1289,11 @@ pub mod swap_pool_amplified {
 
     pub fn out_swap_timeout(
         ctx: Context<OutSwapTimeout>,
-        escrow_nonce: u32,          // Used in context
+        escrow_id: [u8; 32],        // Used in context
     ) -> Result<()> {
 
+        // NOTE: Acks and timeouts are processed even while the pool is paused, so that the swap escrows can always be released
+
         let escrowed_amount = ctx.accounts.swap_escrow.amount;
         let escrowed_asset_index = ctx.accounts.swap_escrow.asset_index as usize;
 
@@ -688,7 +1316,7 @@ pub mod swap_pool_amplified {
 
         emit!(OutSwapTimeoutEvent {
             swap_pool: ctx.accounts.swap_pool_state_account.key(),
-            escrow_nonce
+            escrow_id
         });
 
         Ok(())
@@ -699,7 +1327,14 @@ pub mod swap_pool_amplified {
         output_asset_index: u8,
         units_x64: [u64; 4],
         min_output: u64
-    ) -> Result<()> {   //TODO! set units_x64 to U256 (add serialisation/deserialisation to type)
+    ) -> Result<()> {   //TODO! set units_x64 to U256 (the Anchor IDL can only describe types defined within the program crate, see the U256 Borsh impl of shared_lib)
+
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::IncomingSwap)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
         // assert sender is chain_interface
 
         let to_asset_index = ctx.accounts.swap_pool_state_account.get_asset_index(
@@ -715,21 +1350,24 @@ pub mod swap_pool_amplified {
             U256::from(
                 ctx.accounts.swap_pool_output_asset_wallet.amount
                     .checked_sub(ctx.accounts.swap_pool_state_account.escrowed_assets[to_asset_index]).unwrap()
+                    .checked_sub(ctx.accounts.swap_pool_state_account.accrued_governance_fees[to_asset_index]).unwrap()
             ),
             U256::from(ctx.accounts.swap_pool_state_account.pool_assets_weights[output_asset_index as usize]),
-            U256(ctx.accounts.swap_pool_state_account.amplification_x64)
+            U256(ctx.accounts.swap_pool_state_account.amplification_x64),
+            None    // Default log2/pow2 precision
         )?.as_u64();      // U256 to u64 will panic if overflow
 
         if output_balance < min_output {
             return Err(error!(ErrorCode::SwapMinYieldNotFulfilled))
         }
 
-        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
         ctx.accounts.swap_pool_state_account.update_units_inflow(U256(units_x64), current_timestamp)?;
 
         // TODO verify
-        ctx.accounts.swap_pool_state_account.unit_tracker_x64 = ctx.accounts.swap_pool_state_account.unit_tracker_x64
-            .checked_sub(U256(units_x64).as_u128().try_into().unwrap()).unwrap();
+        ctx.accounts.swap_pool_state_account.unit_tracker_x64 = I256X64(ctx.accounts.swap_pool_state_account.unit_tracker_x64)
+            .checked_sub_u256(U256(units_x64))
+            .map_err(calculation_helpers::fixed_point_error)?
+            .0;
 
         ctx.accounts.swap_pool_state_account.max_units_inflow_x64 = 
             U256(ctx.accounts.swap_pool_state_account.max_units_inflow_x64)
@@ -773,84 +1411,446 @@ pub mod swap_pool_amplified {
 
 
 
-    // Liquidity Swaps **********************************************************************************************************
+    // Underwriting *************************************************************************************************************
 
-    pub fn out_liquidity_swap(
-        ctx: Context<OutLiquiditySwap>,
-        chain: u64,
-        target_pool: Pubkey,
-        destination: Pubkey,
-        pool_tokens_amount: u64
+    pub fn underwrite(
+        ctx: Context<Underwrite>,
+        identifier: [u8; 32],
+        source_chain: u64,
+        source_pool: Pubkey,
+        block_number: u32,
+        output_asset_index: u8,
+        units_x64: [u64; 4],
+        min_output: u64,
+        underwrite_incentive_x16: u16
     ) -> Result<()> {
 
-        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::IncomingSwap)?;
 
-        // Compute the total amount of liquidity units being transferred
-        let mut out_liquidity_units_x64 = U256::from(0);
-    
-        for asset_index in 0..NUMASSETS {
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
 
-            if swap_pool_state_account.pool_assets_mints[asset_index].eq(&Pubkey::default()) {
-                break;
-            }
+        let to_asset_index = ctx.accounts.swap_pool_state_account.get_asset_index(
+            &ctx.accounts.output_asset_mint.key()
+        ).unwrap();
 
-            let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
+        if output_asset_index as usize != to_asset_index {
+            return Err(error!(ErrorCode::InvalidAssetMintAccount));
+        }
 
-            let pool_tokens_for_asset = pool_tokens_amount
-                .checked_mul(asset_eq_balance).unwrap()
-                .checked_div(ctx.accounts.swap_pool_token_mint.supply).unwrap();
+        // The identifier must be the one of the incoming swap being underwritten, as the IBCInterface will look for
+        // the underwrite escrow using the identifier computed from the swap payload.
+        let expected_identifier = ibc_interface::compute_underwrite_identifier(
+            source_chain,
+            &source_pool,
+            block_number,
+            &ctx.accounts.swap_pool_state_account.key(),
+            output_asset_index,
+            &units_x64,
+            min_output,
+            &ctx.accounts.output_asset_wallet.key(),
+            underwrite_incentive_x16
+        );
 
-            let liquidity_for_asset_x64 = calculation_helpers::calc_out_liquidity_swap_x64(
-                U256::from(pool_tokens_for_asset),
-                U256::from(asset_eq_balance),
-                U256::from(swap_pool_state_account.pool_assets_weights[asset_index]),
-                U256(swap_pool_state_account.amplification_x64)
-            )?;
-            
-            out_liquidity_units_x64 = out_liquidity_units_x64.checked_add(liquidity_for_asset_x64).unwrap();
+        if identifier != expected_identifier {
+            return Err(error!(ErrorCode::InvalidUnderwriteIdentifier));
+        }
 
-            swap_pool_state_account.pool_assets_eq_balances[asset_index] = asset_eq_balance.checked_sub(pool_tokens_for_asset).unwrap();
+        let output_balance = calculation_helpers::in_swap(
+            U256(units_x64),
+            U256::from(
+                ctx.accounts.swap_pool_output_asset_wallet.amount
+                    .checked_sub(ctx.accounts.swap_pool_state_account.escrowed_assets[to_asset_index]).unwrap()
+                    .checked_sub(ctx.accounts.swap_pool_state_account.accrued_governance_fees[to_asset_index]).unwrap()
+            ),
+            U256::from(ctx.accounts.swap_pool_state_account.pool_assets_weights[output_asset_index as usize]),
+            U256(ctx.accounts.swap_pool_state_account.amplification_x64),
+            None    // Default log2/pow2 precision
+        )?.as_u64();      // U256 to u64 will panic if overflow
 
+        if output_balance < min_output {
+            return Err(error!(ErrorCode::SwapMinYieldNotFulfilled))
         }
 
-        // Burn pool tokens
-        ctx.accounts.burn_pool_tokens_of_sender(pool_tokens_amount)?;
+        ctx.accounts.swap_pool_state_account.update_units_inflow(U256(units_x64), current_timestamp)?;
 
-        // Perform cross chain swap
-        let ccsi_program = ctx.accounts.ibc_interface_program.to_account_info(); // Check is the same as the stored program?
-        let ccsi_swap_accounts = interface_accounts::CrossChainSwap {
-            swap_pool_authority: ctx.accounts.swap_pool_authority.to_account_info(),
-            interface_state_account: ctx.accounts.interface_state_account.to_account_info(),
-            connection_state_account: ctx.accounts.connection_state_account.to_account_info(),
-            polymerase_sender_program: ctx.accounts.polymerase_sender_program.to_account_info(),
-            polymerase_ibc_data: ctx.accounts.polymerase_ibc_data.to_account_info(),
-            polymerase_ibc_data_account_payer: ctx.accounts.polymerase_ibc_data_account_payer.to_account_info(),
-            system_program: ctx.accounts.system_program.to_account_info()
-        };
+        // TODO verify
+        ctx.accounts.swap_pool_state_account.unit_tracker_x64 = I256X64(ctx.accounts.swap_pool_state_account.unit_tracker_x64)
+            .checked_sub_u256(U256(units_x64))
+            .map_err(calculation_helpers::fixed_point_error)?
+            .0;
 
-        let signer: &[&[&[u8]]] = &[&[
-            ctx.accounts.swap_pool_state_account.to_account_info().key.as_ref(),
-            &POOL_AUTHORITY_SEED[..],
-            &[ctx.accounts.swap_pool_state_account.authority_bump]   // PDA bump
-        ]];
+        ctx.accounts.swap_pool_state_account.max_units_inflow_x64 = 
+            U256(ctx.accounts.swap_pool_state_account.max_units_inflow_x64)
+                .checked_sub(mul_x64(
+                    U256(ctx.accounts.swap_pool_state_account.units_inflow_amplification_x64),
+                    ctx.accounts.swap_pool_state_account.get_units_inflow_capacity(
+                        ctx.accounts.swap_pool_output_asset_wallet.amount,           // NOTE: the underwritten amount is escrowed (but not transferred)
+                        ctx.accounts.swap_pool_output_asset_wallet.amount - output_balance,
+                        to_asset_index
+                    )?
+                ).unwrap()).unwrap().0;
 
-        let ccsi_swap_ctx = CpiContext::new_with_signer(
-            ccsi_program,
-            ccsi_swap_accounts,
-            signer
+        // The underwriter keeps the incentive, which is paid back (together with the rest of the underwritten amount) once
+        // the swap arrives.
+        let underwrite_incentive = U256::from(output_balance)
+            .checked_mul(U256::from(underwrite_incentive_x16)).unwrap()
+            .shr(16u8).as_u64();
+
+        // Transfer the underwritten assets from the underwriter to the user
+        token_utils::transfer_tokens(  //TODO use transfer_checked?
+            output_balance - underwrite_incentive,  // underwrite_incentive <= output_balance, as underwrite_incentive_x16 < 1
+            ctx.accounts.underwriter_asset_wallet.to_account_info(),
+            ctx.accounts.output_asset_wallet.to_account_info(),
+            ctx.accounts.underwriter.to_account_info(),
+            ctx.accounts.token_program.to_account_info()
         );
 
-        ibc_interface::cpi::cross_chain_liquidity_swap(
-            ccsi_swap_ctx,
-            chain,
-            false,
-            ctx.accounts.swap_pool_state_account.key(),
-            target_pool,
-            destination,
-            out_liquidity_units_x64.0
-        ).unwrap();
+        // Escrow the pool assets until the swap arrives
+        ctx.accounts.swap_pool_state_account.escrowed_assets[to_asset_index] =
+            ctx.accounts.swap_pool_state_account.escrowed_assets[to_asset_index].checked_add(output_balance).unwrap();
 
-        // Correct the routing security limit. (To increase the maximum allowed daily volume)
+        ctx.accounts.underwrite_escrow.amount                       = output_balance;
+        ctx.accounts.underwrite_escrow.asset_index                  = to_asset_index as u8;
+        ctx.accounts.underwrite_escrow.units_x64                    = units_x64;
+        ctx.accounts.underwrite_escrow.underwriter                  = ctx.accounts.underwriter.key();
+        ctx.accounts.underwrite_escrow.underwriter_wallet           = ctx.accounts.underwriter_asset_wallet.key();
+        ctx.accounts.underwrite_escrow.underwrite_escrow_rent_payer = ctx.accounts.underwrite_escrow_rent_payer.key();
+        ctx.accounts.underwrite_escrow.expiry                       = current_timestamp.checked_add(UNDERWRITE_EXPIRY).unwrap();
+        ctx.accounts.underwrite_escrow.bump                         = ctx.bumps.get("underwrite_escrow").unwrap().to_owned();
+
+        emit!(UnderwriteEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            identifier,
+
+            withdrawn_asset_mint: ctx.accounts.output_asset_mint.key(),
+            withdrawer_asset_wallet: ctx.accounts.output_asset_wallet.key(),
+            underwriter_asset_wallet: ctx.accounts.underwriter_asset_wallet.key(),
+
+            deposited_pool_units_x64: units_x64,
+            withdrawn_asset_amount: output_balance,
+            underwrite_incentive
+        });
+
+        Ok(())
+    }
+
+    pub fn fill_underwrite(
+        ctx: Context<FillUnderwrite>,
+        identifier: [u8; 32]    // Used in context
+    ) -> Result<()> {
+
+        // NOTE: Underwrites are filled even while the pool is paused, as the underwriter has already paid out the swap
+        // and the escrowed assets must always be released
+
+        let underwritten_amount = ctx.accounts.underwrite_escrow.amount;
+        let underwritten_asset_index = ctx.accounts.underwrite_escrow.asset_index as usize;
+
+        if ctx.accounts.swap_pool_state_account.pool_assets_mints[underwritten_asset_index].ne(&ctx.accounts.asset_mint.key()) {
+            return Err(error!(ErrorCode::InvalidAssetMintAccount));
+        }
+
+        ctx.accounts.swap_pool_state_account.escrowed_assets[underwritten_asset_index] = 
+            ctx.accounts.swap_pool_state_account.escrowed_assets[underwritten_asset_index].checked_sub(underwritten_amount).unwrap();
+
+        // Repay the underwriter (the paid amount plus the incentive)
+        token_utils::transfer_tokens_using_pda_authority(  //TODO use transfer_checked?
+            underwritten_amount,
+            ctx.accounts.swap_pool_asset_wallet.to_account_info(),
+            ctx.accounts.underwriter_wallet.to_account_info(),
+            ctx.accounts.swap_pool_authority.to_account_info(),
+            &[
+                &ctx.accounts.swap_pool_state_account.key().to_bytes(),  // SwapPool state account
+                &POOL_AUTHORITY_SEED[..],                                // Pool authority seed
+                &[ctx.accounts.swap_pool_state_account.authority_bump]   // PDA bump
+            ],
+            ctx.accounts.token_program.to_account_info()
+        );
+
+        emit!(FillUnderwriteEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            identifier
+        });
+
+        Ok(())
+    }
+
+    pub fn expire_underwrite(
+        ctx: Context<ExpireUnderwrite>,
+        identifier: [u8; 32]    // Used in context
+    ) -> Result<()> {
+
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::Other)?;
+
+        // The underwriter may expire the underwrite at any time, anyone else only once it has expired
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        if ctx.accounts.caller.key().ne(&ctx.accounts.underwrite_escrow.underwriter) &&
+           current_timestamp < ctx.accounts.underwrite_escrow.expiry {
+            return Err(error!(ErrorCode::UnderwriteNotExpired));
+        }
+
+        let underwritten_amount = ctx.accounts.underwrite_escrow.amount;
+        let underwritten_asset_index = ctx.accounts.underwrite_escrow.asset_index as usize;
+
+        // Release the escrowed assets back to the pool
+        ctx.accounts.swap_pool_state_account.escrowed_assets[underwritten_asset_index] = 
+            ctx.accounts.swap_pool_state_account.escrowed_assets[underwritten_asset_index].checked_sub(underwritten_amount).unwrap();
+
+        // Release the units inflow accounted for by the underwrite, as the incoming swap will account for it once executed
+        ctx.accounts.swap_pool_state_account.release_units_inflow(U256(ctx.accounts.underwrite_escrow.units_x64));
+
+        // Revert the units tracking, as the incoming swap will be executed normally once it arrives
+        ctx.accounts.swap_pool_state_account.unit_tracker_x64 = I256X64(ctx.accounts.swap_pool_state_account.unit_tracker_x64)
+            .checked_add_u256(U256(ctx.accounts.underwrite_escrow.units_x64))
+            .map_err(calculation_helpers::fixed_point_error)?
+            .0;
+
+        emit!(ExpireUnderwriteEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            identifier
+        });
+
+        Ok(())
+    }
+
+
+
+    // Quotes *******************************************************************************************************************
+
+    /// Computes the units that would be sent for 'amount' of the input asset (after the vault fee). Intended to be used
+    /// via transaction simulation, the result is returned as the instruction return data ([u64; 4]).
+    pub fn calc_send_asset(
+        ctx: Context<CalcSendAsset>,
+        amount: u64
+    ) -> Result<()> {
+
+        // Quote using the current pool amplification (if an amplification adjustment is in progress), without modifying
+        // the pool state
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let mut swap_pool_state = SwapPoolState::clone(&ctx.accounts.swap_pool_state_account);
+        swap_pool_state.update_amplification(current_timestamp)?;
+
+        let from_asset_index = swap_pool_state.get_asset_index(
+            &ctx.accounts.input_asset_mint.key()
+        ).unwrap();
+
+        let vault_fee = calculation_helpers::calc_vault_fee(amount, swap_pool_state.vault_fee_x64)?;
+
+        let units_x64 = calculation_helpers::out_swap_x64(
+            U256::from(amount - vault_fee),     // vault_fee <= amount guaranteed by calc_vault_fee
+            U256::from(
+                ctx.accounts.swap_pool_input_asset_wallet.amount.checked_sub(
+                    swap_pool_state.accrued_governance_fees[from_asset_index]
+                ).unwrap()
+            ),
+            U256::from(swap_pool_state.pool_assets_weights[from_asset_index]),
+            U256(swap_pool_state.amplification_x64),
+            None    // Default log2/pow2 precision
+        )?;
+
+        set_return_data(&units_x64.0.try_to_vec().unwrap());
+
+        Ok(())
+    }
+
+    /// Computes the output amount of the output asset that would be received for 'units_x64' (incoming swaps are not
+    /// charged the vault fee). Intended to be used via transaction simulation, the result is returned as the instruction
+    /// return data (u64).
+    pub fn calc_receive_asset(
+        ctx: Context<CalcReceiveAsset>,
+        units_x64: [u64; 4]
+    ) -> Result<()> {
+
+        // Quote using the current pool amplification (if an amplification adjustment is in progress), without modifying
+        // the pool state
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let mut swap_pool_state = SwapPoolState::clone(&ctx.accounts.swap_pool_state_account);
+        swap_pool_state.update_amplification(current_timestamp)?;
+
+        let to_asset_index = swap_pool_state.get_asset_index(
+            &ctx.accounts.output_asset_mint.key()
+        ).unwrap();
+
+        let output = calculation_helpers::in_swap(
+            U256(units_x64),
+            U256::from(
+                ctx.accounts.swap_pool_output_asset_wallet.amount
+                    .checked_sub(swap_pool_state.escrowed_assets[to_asset_index]).unwrap()
+                    .checked_sub(swap_pool_state.accrued_governance_fees[to_asset_index]).unwrap()
+            ),
+            U256::from(swap_pool_state.pool_assets_weights[to_asset_index]),
+            U256(swap_pool_state.amplification_x64),
+            None    // Default log2/pow2 precision
+        )?.as_u64();      // U256 to u64 will panic if overflow
+
+        set_return_data(&output.try_to_vec().unwrap());
+
+        Ok(())
+    }
+
+    /// Computes the output of a local swap of 'amount' of the input asset (after the vault fee). Intended to be used via
+    /// transaction simulation, the result is returned as the instruction return data (u64).
+    pub fn calc_local_swap(
+        ctx: Context<CalcLocalSwap>,
+        amount: u64
+    ) -> Result<()> {
+
+        // Quote using the current pool amplification (if an amplification adjustment is in progress), without modifying
+        // the pool state
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        let mut swap_pool_state = SwapPoolState::clone(&ctx.accounts.swap_pool_state_account);
+        swap_pool_state.update_amplification(current_timestamp)?;
+
+        let from_asset_index = swap_pool_state.get_asset_index(
+            &ctx.accounts.input_asset_mint.key()
+        ).unwrap();
+
+        let to_asset_index = swap_pool_state.get_asset_index(
+            &ctx.accounts.output_asset_mint.key()
+        ).unwrap();
+
+        let vault_fee = calculation_helpers::calc_vault_fee(amount, swap_pool_state.vault_fee_x64)?;
+
+        let output = calculation_helpers::full_swap(
+            U256::from(amount - vault_fee),     // vault_fee <= amount guaranteed by calc_vault_fee
+            U256::from(
+                ctx.accounts.swap_pool_input_asset_wallet.amount.checked_sub(
+                    swap_pool_state.accrued_governance_fees[from_asset_index]
+                ).unwrap()
+            ),
+            U256::from(swap_pool_state.pool_assets_weights[from_asset_index]),
+            U256::from(
+                ctx.accounts.swap_pool_output_asset_wallet.amount
+                    .checked_sub(swap_pool_state.escrowed_assets[to_asset_index]).unwrap()
+                    .checked_sub(swap_pool_state.accrued_governance_fees[to_asset_index]).unwrap()
+            ),
+            U256::from(swap_pool_state.pool_assets_weights[to_asset_index]),
+            U256(swap_pool_state.amplification_x64),
+            None    // Default log2/pow2 precision
+        )?.as_u64();      // U256 to u64 will panic if overflow
+
+        set_return_data(&output.try_to_vec().unwrap());
+
+        Ok(())
+    }
+
+
+
+    // Liquidity Swaps **********************************************************************************************************
+
+    pub fn out_liquidity_swap(
+        ctx: Context<OutLiquiditySwap>,
+        chain: u64,
+        target_pool: Pubkey,
+        destination: Pubkey,
+        pool_tokens_amount: u64,
+        min_pool_tokens: [u64; 4],
+        min_reference_asset: [u64; 4],
+        block_number: u32           // A recent slot, used to derive the escrow id
+    ) -> Result<()> {
+
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::OutgoingSwap)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
+
+        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+
+        // Compute the total amount of liquidity units being transferred
+        let mut out_liquidity_units_x64 = U256::from(0);
+        let mut escrowed_eq_balances = [0u64; MAX_ASSETS];
+    
+        for asset_index in 0..swap_pool_state_account.asset_count() {
+
+            let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
+
+            let pool_tokens_for_asset = pool_tokens_amount
+                .checked_mul(asset_eq_balance).unwrap()
+                .checked_div(ctx.accounts.swap_pool_token_mint.supply).unwrap();
+
+            let liquidity_for_asset_x64 = calculation_helpers::calc_out_liquidity_swap_x64(
+                U256::from(pool_tokens_for_asset),
+                U256::from(asset_eq_balance),
+                U256::from(swap_pool_state_account.pool_assets_weights[asset_index]),
+                U256(swap_pool_state_account.amplification_x64)
+            )?;
+            
+            out_liquidity_units_x64 = out_liquidity_units_x64.checked_add(liquidity_for_asset_x64).unwrap();
+
+            swap_pool_state_account.pool_assets_eq_balances[asset_index] = asset_eq_balance.checked_sub(pool_tokens_for_asset).unwrap();
+            escrowed_eq_balances[asset_index] = pool_tokens_for_asset;
+
+        }
+
+        // Burn pool tokens
+        ctx.accounts.burn_pool_tokens_of_sender(pool_tokens_amount)?;
+
+        // The block number must be a recent slot
+        verify_block_number(block_number)?;
+
+        // Escrow the burnt pool tokens, so that they can be returned to the liquidity provider should the swap time out.
+        // The escrow id is derived from the swap contents, so that it can be recomputed from the payload on ack/timeout
+        let escrow_id = ibc_interface::compute_liquidity_escrow_id(
+            &destination,
+            &out_liquidity_units_x64.0,
+            pool_tokens_amount,
+            block_number
+        );
+
+        ctx.accounts.create_liquidity_escrow(
+            &escrow_id,
+            LiquidityEscrow {
+                amount: pool_tokens_amount,
+                eq_balances: escrowed_eq_balances,
+                fallback_wallet: ctx.accounts.liquidity_provider_pool_token_wallet.key(),
+                liquidity_escrow_rent_payer: ctx.accounts.liquidity_escrow_rent_payer.key(),
+                bump: 0     // Set by create_liquidity_escrow
+            }
+        )?;
+
+        // Perform cross chain swap
+        let ccsi_program = ctx.accounts.ibc_interface_program.to_account_info(); // Check is the same as the stored program?
+        let ccsi_swap_accounts = interface_accounts::CrossChainSwap {
+            swap_pool_authority: ctx.accounts.swap_pool_authority.to_account_info(),
+            interface_state_account: ctx.accounts.interface_state_account.to_account_info(),
+            connection_state_account: ctx.accounts.connection_state_account.to_account_info(),
+            polymerase_sender_program: ctx.accounts.polymerase_sender_program.to_account_info(),
+            polymerase_ibc_data: ctx.accounts.polymerase_ibc_data.to_account_info(),
+            polymerase_ibc_data_account_payer: ctx.accounts.polymerase_ibc_data_account_payer.to_account_info(),
+            system_program: ctx.accounts.system_program.to_account_info()
+        };
+
+        let signer: &[&[&[u8]]] = &[&[
+            ctx.accounts.swap_pool_state_account.to_account_info().key.as_ref(),
+            &POOL_AUTHORITY_SEED[..],
+            &[ctx.accounts.swap_pool_state_account.authority_bump]   // PDA bump
+        ]];
+
+        let ccsi_swap_ctx = CpiContext::new_with_signer(
+            ccsi_program,
+            ccsi_swap_accounts,
+            signer
+        );
+
+        ibc_interface::cpi::cross_chain_liquidity_swap(
+            ccsi_swap_ctx,
+            chain,
+            ctx.accounts.swap_pool_state_account.key(),
+            target_pool,
+            destination,
+            out_liquidity_units_x64.0,
+            min_pool_tokens,
+            min_reference_asset,
+            pool_tokens_amount,
+            block_number
+        ).unwrap();
+
+        // Correct the routing security limit. (To increase the maximum allowed daily volume)
         if ctx.accounts.swap_pool_state_account.current_liquidity_inflow > pool_tokens_amount {
             ctx.accounts.swap_pool_state_account.current_liquidity_inflow -= pool_tokens_amount
         }
@@ -863,6 +1863,7 @@ pub mod swap_pool_amplified {
             target_pool: target_pool,
             target_beneficiary: destination,
             target_chain: chain,
+            escrow_id,
             pool_token_mint: ctx.accounts.swap_pool_token_mint.key(),
             source_pool_token_wallet: ctx.accounts.liquidity_provider_pool_token_wallet.key(),
             pool_token_amount: pool_tokens_amount,
@@ -875,11 +1876,78 @@ pub mod swap_pool_amplified {
     }
 
 
+    pub fn out_liquidity_swap_ack(
+        ctx: Context<OutLiquiditySwapAck>,
+        escrow_id: [u8; 32]         // Used in context
+    ) -> Result<()> {
+
+        // NOTE: Acks and timeouts are processed even while the pool is paused, so that the swap escrows can always be released
+
+        // NOTE: The escrowed pool tokens have already been burnt, the escrow account is closed by the context
+
+        emit!(OutLiquiditySwapAckEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            escrow_id
+        });
+
+        Ok(())
+    }
+
+    pub fn out_liquidity_swap_timeout(
+        ctx: Context<OutLiquiditySwapTimeout>,
+        escrow_id: [u8; 32],        // Used in context
+    ) -> Result<()> {
+
+        // NOTE: Acks and timeouts are processed even while the pool is paused, so that the swap escrows can always be released
+
+        let escrowed_amount = ctx.accounts.liquidity_escrow.amount;
+
+        // Return the escrowed liquidity to the pool
+        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+        for asset_index in 0..swap_pool_state_account.asset_count() {
+            swap_pool_state_account.pool_assets_eq_balances[asset_index] = 
+                swap_pool_state_account.pool_assets_eq_balances[asset_index].checked_add(
+                    ctx.accounts.liquidity_escrow.eq_balances[asset_index]
+                ).unwrap();
+        }
+
+        // Mint the burnt pool tokens back to the fallback wallet
+        token_utils::mint_tokens_using_pda_authority(
+            escrowed_amount,
+            ctx.accounts.fallback_wallet.to_account_info(),
+            ctx.accounts.swap_pool_token_mint.to_account_info(),
+            ctx.accounts.swap_pool_authority.to_account_info(),
+            &[
+                &ctx.accounts.swap_pool_state_account.key().to_bytes(),  // SwapPool state account
+                &POOL_AUTHORITY_SEED[..],                                // Pool authority seed
+                &[ctx.accounts.swap_pool_state_account.authority_bump]   // PDA bump
+            ],
+            ctx.accounts.token_program.to_account_info()
+        );
+
+        emit!(OutLiquiditySwapTimeoutEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            escrow_id
+        });
+
+        Ok(())
+    }
+
+
     pub fn in_liquidity_swap<'a, 'b, 'c, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, InLiquiditySwap<'info>>,
-        liquidity_units_x64: [u64; 4]
+        liquidity_units_x64: [u64; 4],
+        min_pool_tokens: u64,
+        min_reference_asset: u64
     ) -> Result<()> {
 
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::IncomingSwap)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
+
         let total_pool_tokens;
         {
             let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
@@ -888,11 +1956,7 @@ pub mod swap_pool_amplified {
             let mut aggregate_weight_x64 = U256::from(0);
             let one_minus_amp_x64 = ONE_X64.checked_sub(amplification).unwrap();
         
-            for asset_index in 0..NUMASSETS {
-
-                if swap_pool_state_account.pool_assets_mints[asset_index].eq(&Pubkey::default()) {
-                    break;
-                }
+            for asset_index in 0..swap_pool_state_account.asset_count() {
 
                 let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
 
@@ -921,74 +1985,357 @@ pub mod swap_pool_amplified {
                 .checked_mul(pool_tokens_supply).unwrap()
                 .checked_div(U256::from(swap_pool_state_account.pool_assets_eq_balances[0])).unwrap();
 
-            // Update the eq balances
-            swap_pool_state_account.pool_assets_eq_balances[0] = swap_pool_state_account.pool_assets_eq_balances[0].checked_add(
-                asset_0_pool_tokens.as_u64()
-            ).unwrap();
+            // Verify the minimum outputs. Reverting makes the packet time out, which returns the liquidity on the source pool.
+            if total_pool_tokens < U256::from(min_pool_tokens) {
+                return Err(error!(ErrorCode::SwapMinYieldNotFulfilled));
+            }
+
+            // As on the EVM implementation, the reference asset value of the received pool tokens is only computed if required
+            if min_reference_asset != 0 {
+                let reference_asset_amount = calculation_helpers::calc_reference_asset_amount(
+                    &swap_pool_state_account.pool_assets_eq_balances,
+                    &swap_pool_state_account.pool_assets_weights,
+                    total_pool_tokens,
+                    pool_tokens_supply
+                )?;
+
+                if reference_asset_amount < U256::from(min_reference_asset) {
+                    return Err(error!(ErrorCode::SwapMinYieldNotFulfilled));
+                }
+            }
+
+            // Update the eq balances
+            swap_pool_state_account.pool_assets_eq_balances[0] = swap_pool_state_account.pool_assets_eq_balances[0].checked_add(
+                asset_0_pool_tokens.as_u64()
+            ).unwrap();
+
+            for asset_index in 1..swap_pool_state_account.asset_count() {
+
+                let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
+
+                swap_pool_state_account.pool_assets_eq_balances[asset_index] = asset_eq_balance.checked_add(
+                    total_pool_tokens
+                        .checked_mul(U256::from(asset_eq_balance)).unwrap()
+                        .checked_div(pool_tokens_supply).unwrap()
+                        .as_u64()
+                ).unwrap();
+
+            }
+        }
+
+        // Verify and update the security limit
+        ctx.accounts.swap_pool_state_account.update_liquidity_units_inflow(
+            total_pool_tokens.as_u64(),
+            ctx.accounts.swap_pool_token_mint.supply,
+            current_timestamp
+        )?;
+        
+        ctx.accounts.mint_pool_tokens_for_recipient(total_pool_tokens.as_u64())?;
+
+        emit!(InLiquiditySwapEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+        
+            pool_token_mint: ctx.accounts.swap_pool_token_mint.key(),
+            target_pool_token_wallet: ctx.accounts.destination_pool_token_wallet.key(),
+        
+            pool_token_amount: total_pool_tokens.as_u64(),
+            liquidity_units_x64: liquidity_units_x64,
+            fees: 0
+        });
+
+        Ok(())
+    }
+
+
+
+    // Amplification ************************************************************************************************************
+
+    pub fn set_amplification(
+        ctx: Context<SetAmplification>,
+        target_timestamp: u64,
+        target_amplification_x64: [u64; 4]
+    ) -> Result<()> {
+
+        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+
+        // Apply any ongoing amplification adjustment before overriding it
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        swap_pool_state_account.update_amplification(current_timestamp)?;
+
+        if target_timestamp < current_timestamp.checked_add(MIN_AMPLIFICATION_ADJUSTMENT_TIME).unwrap() ||
+           target_timestamp > current_timestamp.checked_add(MAX_AMPLIFICATION_ADJUSTMENT_TIME).unwrap() {
+            return Err(error!(ErrorCode::InvalidAmplificationAdjustmentTime));
+        }
+
+        // The amplification must be within (0, 1), and may change at most by MAX_AMPLIFICATION_CHANGE_FACTOR
+        let target_amplification  = U256(target_amplification_x64);
+        let current_amplification = U256(swap_pool_state_account.amplification_x64);
+        if target_amplification.is_zero() ||
+           target_amplification >= ONE_X64 ||
+           target_amplification > current_amplification.checked_mul(U256::from(MAX_AMPLIFICATION_CHANGE_FACTOR)).unwrap() ||
+           target_amplification < current_amplification / MAX_AMPLIFICATION_CHANGE_FACTOR {
+            return Err(error!(ErrorCode::InvalidTargetAmplification));
+        }
+
+        swap_pool_state_account.target_amplification_x64 = target_amplification_x64;
+        swap_pool_state_account.amplification_adjustment_target_timestamp = target_timestamp;
+        swap_pool_state_account.amplification_last_modification_timestamp = current_timestamp;
+
+        emit!(SetAmplificationEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            target_timestamp,
+            target_amplification_x64
+        });
+
+        Ok(())
+    }
+
+
+
+    // Authority ******************************************************************************************************************
+
+    // NOTE: The dao_authority handover is performed in two steps (propose + accept) to prevent transferring the pool to an
+    // account which cannot sign. A pending handover may be cancelled by proposing the default (empty) pubkey.
+    pub fn propose_dao_authority(
+        ctx: Context<ProposeDaoAuthority>,
+        pending_dao_authority: Pubkey
+    ) -> Result<()> {
+
+        ctx.accounts.swap_pool_state_account.pending_dao_authority = pending_dao_authority;
+
+        emit!(ProposeDaoAuthorityEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            pending_dao_authority
+        });
+
+        Ok(())
+    }
+
+    pub fn accept_dao_authority(ctx: Context<AcceptDaoAuthority>) -> Result<()> {
+
+        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+
+        swap_pool_state_account.dao_authority         = ctx.accounts.pending_dao_authority.key();
+        swap_pool_state_account.pending_dao_authority = Pubkey::default();
+
+        emit!(AcceptDaoAuthorityEvent {
+            swap_pool: swap_pool_state_account.key(),
+            dao_authority: swap_pool_state_account.dao_authority
+        });
+
+        Ok(())
+    }
+
+
+
+    // Pause **********************************************************************************************************************
+
+    pub fn set_guardian(
+        ctx: Context<SetGuardian>,
+        guardian: Pubkey
+    ) -> Result<()> {
+
+        // NOTE: The guardian may be set to the default (empty) pubkey to remove it
+        ctx.accounts.swap_pool_state_account.guardian = guardian;
+
+        emit!(SetGuardianEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            guardian
+        });
+
+        Ok(())
+    }
+
+    pub fn set_pause_flags(
+        ctx: Context<SetPauseFlags>,
+        pause_flags: PauseFlags
+    ) -> Result<()> {
+
+        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+
+        // The guardian may only pause operations, lifting a pause is reserved to the dao_authority
+        if ctx.accounts.authority.key() != swap_pool_state_account.dao_authority
+            && !swap_pool_state_account.pause_flags.is_subset_of(&pause_flags)
+        {
+            return Err(error!(ErrorCode::GuardianCannotUnpause));
+        }
+
+        swap_pool_state_account.pause_flags = pause_flags;
+
+        emit!(SetPauseFlagsEvent {
+            swap_pool: swap_pool_state_account.key(),
+            pause_flags
+        });
+
+        Ok(())
+    }
+
+
+
+    // Fees *********************************************************************************************************************
+
+    pub fn set_vault_fee(
+        ctx: Context<SetVaultFee>,
+        vault_fee_x64: u64
+    ) -> Result<()> {
+
+        if vault_fee_x64 > MAX_VAULT_FEE_X64 {
+            return Err(error!(ErrorCode::InvalidVaultFee));
+        }
+
+        ctx.accounts.swap_pool_state_account.vault_fee_x64 = vault_fee_x64;
+
+        emit!(SetVaultFeeEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            vault_fee_x64
+        });
+
+        Ok(())
+    }
+
+    pub fn set_governance_fee(
+        ctx: Context<SetGovernanceFee>,
+        governance_fee_share_x64: u64
+    ) -> Result<()> {
+
+        if governance_fee_share_x64 > MAX_GOVERNANCE_FEE_SHARE_X64 {
+            return Err(error!(ErrorCode::InvalidGovernanceFeeShare));
+        }
+
+        ctx.accounts.swap_pool_state_account.governance_fee_share_x64 = governance_fee_share_x64;
+
+        emit!(SetGovernanceFeeEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            governance_fee_share_x64
+        });
+
+        Ok(())
+    }
+
+    pub fn set_governance_fee_destination(
+        ctx: Context<SetGovernanceFee>,
+        governance_fee_destination: Pubkey
+    ) -> Result<()> {
+
+        ctx.accounts.swap_pool_state_account.governance_fee_destination = governance_fee_destination;
+
+        emit!(SetGovernanceFeeDestinationEvent {
+            swap_pool: ctx.accounts.swap_pool_state_account.key(),
+            governance_fee_destination
+        });
+
+        Ok(())
+    }
+
+    pub fn collect_governance_fees<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, CollectGovernanceFees<'info>>
+    ) -> Result<()> {
+
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::Other)?;
+
+        let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
+
+        let governance_fee_destination = swap_pool_state_account.governance_fee_destination;
+        if governance_fee_destination.eq(&Pubkey::default()) {
+            return Err(error!(ErrorCode::GovernanceFeeDestinationNotSet));
+        }
+
+        let asset_count = swap_pool_state_account.asset_count();
+
+        let mut collected_amounts: Vec<u64> = vec![0; asset_count];
+
+        let mut destination_asset_wallets: Vec<Pubkey> = vec![Pubkey::default(); asset_count];
+
+        // 2 Accounts given per pool asset:
+        //      - Swap pool asset wallet
+        //      - Destination asset wallet (owned by the governance fee destination)
+        if ctx.remaining_accounts.len() != 2*asset_count {
+            return Err(error!(ErrorCode::InvalidRemainingAccountsCount));
+        }
+
+        for asset_index in 0..asset_count {
 
-            for asset_index in 1..NUMASSETS {
+            let swap_pool_asset_wallet   = &ctx.remaining_accounts[asset_index*2];
+            let destination_asset_wallet = &ctx.remaining_accounts[asset_index*2+1];
 
-                if swap_pool_state_account.pool_assets_mints[asset_index].eq(&Pubkey::default()) {
-                    break;
-                }
+            // Make sure the swap_pool_asset_wallet provided matches the expected pda
+            let seeds: &[&[u8]] = &[
+                &swap_pool_state_account.key().to_bytes(),
+                &swap_pool_state_account.pool_assets_mints[asset_index].key().to_bytes(),
+                POOL_ASSET_WALLET_SEED,
+                &[swap_pool_state_account.wallets_bumps[asset_index]]
+            ];
 
-                let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
+            let expected_swap_pool_asset_wallet  = Pubkey::create_program_address(seeds, &ID).unwrap();
 
-                swap_pool_state_account.pool_assets_eq_balances[asset_index] = asset_eq_balance.checked_add(
-                    total_pool_tokens
-                        .checked_mul(U256::from(asset_eq_balance)).unwrap()
-                        .checked_div(pool_tokens_supply).unwrap()
-                        .as_u64()
-                ).unwrap();
+            if swap_pool_asset_wallet.key() != expected_swap_pool_asset_wallet {
+                return Err(error!(ErrorCode::InvalidSwapPoolAssetWalletAccount));
+            }
 
+            // Make sure the destination wallet belongs to the governance fee destination
+            let destination_asset_wallet_as_token_account = TokenAccount::try_deserialize(&mut &destination_asset_wallet.try_borrow_data()?[..])?;
+            if destination_asset_wallet_as_token_account.owner.ne(&governance_fee_destination) ||
+               destination_asset_wallet_as_token_account.mint.ne(&swap_pool_state_account.pool_assets_mints[asset_index]) {
+                return Err(error!(ErrorCode::InvalidGovernanceFeeDestinationWalletAccount));
             }
-        }
 
-        // Verify and update the security limit
-        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
-        ctx.accounts.swap_pool_state_account.update_liquidity_units_inflow(
-            total_pool_tokens.as_u64(),
-            ctx.accounts.swap_pool_token_mint.supply,
-            current_timestamp
-        )?;
-        
-        ctx.accounts.mint_pool_tokens_for_recipient(total_pool_tokens.as_u64())?;
+            let accrued_governance_fee = swap_pool_state_account.accrued_governance_fees[asset_index];
+            if accrued_governance_fee == 0 {
+                continue;
+            }
 
-        emit!(InLiquiditySwapEvent {
+            swap_pool_state_account.accrued_governance_fees[asset_index] = 0;
+
+            token_utils::transfer_tokens_using_pda_authority(  //TODO use transfer_checked?
+                accrued_governance_fee,
+                swap_pool_asset_wallet.to_account_info(),
+                destination_asset_wallet.to_account_info(),
+                ctx.accounts.swap_pool_authority.to_account_info(),
+                &[
+                    &swap_pool_state_account.key().to_bytes(), // SwapPool state account
+                    &POOL_AUTHORITY_SEED[..],                  // Pool authority seed
+                    &[swap_pool_state_account.authority_bump]  // PDA bump
+                ],
+                ctx.accounts.token_program.to_account_info()
+            );
+
+            // Save data for logging
+            destination_asset_wallets[asset_index] = destination_asset_wallet.key();
+            collected_amounts[asset_index] = accrued_governance_fee;
+        }
+
+        emit!(CollectGovernanceFeesEvent {
             swap_pool: ctx.accounts.swap_pool_state_account.key(),
-        
-            pool_token_mint: ctx.accounts.swap_pool_token_mint.key(),
-            target_pool_token_wallet: ctx.accounts.destination_pool_token_wallet.key(),
-        
-            pool_token_amount: total_pool_tokens.as_u64(),
-            liquidity_units_x64: liquidity_units_x64,
-            fees: 0
+            governance_fee_destination,
+
+            collected_asset_amounts: collected_amounts,
+            destination_asset_wallets
         });
 
         Ok(())
     }
 
-
-
-    // Fees *********************************************************************************************************************
-
     pub fn distribute_fees(
         ctx: Context<DistributeFees>
     ) -> Result<()> {
 
+        // Make sure the operation is not paused
+        ctx.accounts.swap_pool_state_account.check_not_paused(PoolOperation::Other)?;
+
+        // Update the pool amplification (if an amplification adjustment is in progress)
+        let current_timestamp: u64 = Clock::get().unwrap().unix_timestamp.try_into().unwrap();
+        ctx.accounts.swap_pool_state_account.update_amplification(current_timestamp)?;
+
         let swap_pool_state_account = &mut ctx.accounts.swap_pool_state_account;
 
         let amplification_x64 = U256(swap_pool_state_account.amplification_x64);
         let one_minus_amp_x64 = ONE_X64.checked_sub(amplification_x64).unwrap();
 
         let mut aggregate_weight_x64 = U256::from(0);
-        let mut calc_outstanding_units_x64: i128 = 0;
+        let mut calc_outstanding_units_x64 = I256X64::ZERO;
     
-        for asset_index in 0..NUMASSETS {
-
-            if swap_pool_state_account.pool_assets_mints[asset_index].eq(&Pubkey::default()) {
-                break;
-            }
+        for asset_index in 0..swap_pool_state_account.asset_count() {
 
             let swap_pool_asset_wallet = &ctx.remaining_accounts[asset_index];  // Verified in 'create_swap_pool_asset_wallet' step
             
@@ -1009,27 +2356,28 @@ pub mod swap_pool_amplified {
             let swap_pool_asset_wallet_as_token_account = TokenAccount::try_deserialize(&mut &swap_pool_asset_wallet.try_borrow_data()?[..])?;    
 
             let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
-            let asset_balance    = swap_pool_asset_wallet_as_token_account.amount;
+            let asset_balance    = swap_pool_asset_wallet_as_token_account.amount
+                .checked_sub(swap_pool_state_account.accrued_governance_fees[asset_index]).unwrap();    // Governance fees are not distributed to the pool
             
             if asset_balance > asset_eq_balance {
-                calc_outstanding_units_x64 = calc_outstanding_units_x64.checked_add(
+                calc_outstanding_units_x64 = calc_outstanding_units_x64.checked_add_u256(
                     calculation_helpers::calc_out_liquidity_swap_x64(
                         U256::from(asset_balance - asset_eq_balance),
                         U256::from(asset_eq_balance),
                         U256::from(swap_pool_state_account.pool_assets_weights[asset_index]),
                         one_minus_amp_x64
-                    )?.try_into().unwrap()
-                ).unwrap();
+                    )?
+                ).map_err(calculation_helpers::fixed_point_error)?;
             }
             else {
-                calc_outstanding_units_x64 = calc_outstanding_units_x64.checked_sub(
+                calc_outstanding_units_x64 = calc_outstanding_units_x64.checked_sub_u256(
                     calculation_helpers::calc_out_liquidity_swap_x64(
                         U256::from(asset_eq_balance - asset_balance),
                         U256::from(asset_balance),
                         U256::from(swap_pool_state_account.pool_assets_weights[asset_index]),
                         one_minus_amp_x64
-                    )?.try_into().unwrap()
-                ).unwrap();
+                    )?
+                ).map_err(calculation_helpers::fixed_point_error)?;
             }
 
             aggregate_weight_x64 = aggregate_weight_x64.checked_add(
@@ -1043,11 +2391,13 @@ pub mod swap_pool_amplified {
             
         }
 
-        calc_outstanding_units_x64 = swap_pool_state_account.unit_tracker_x64.checked_sub(calc_outstanding_units_x64).unwrap();
+        calc_outstanding_units_x64 = I256X64(swap_pool_state_account.unit_tracker_x64)
+            .checked_sub(calc_outstanding_units_x64)
+            .map_err(calculation_helpers::fixed_point_error)?;
 
         // Compute the pool tokens corresponding to the first asset of the pool
         let asset_0_pool_tokens = calculation_helpers::calc_in_liquidity_swap(
-            U256::from(calc_outstanding_units_x64),
+            U256::try_from(calc_outstanding_units_x64).map_err(calculation_helpers::fixed_point_error)?,
             U256::from(swap_pool_state_account.pool_assets_eq_balances[0]),
             aggregate_weight_x64,
             amplification_x64
@@ -1064,11 +2414,7 @@ pub mod swap_pool_amplified {
             asset_0_pool_tokens.as_u64()
         ).unwrap();
 
-        for asset_index in 1..NUMASSETS {
-
-            if swap_pool_state_account.pool_assets_mints[asset_index].eq(&Pubkey::default()) {
-                break;
-            }
+        for asset_index in 1..swap_pool_state_account.asset_count() {
 
             let asset_eq_balance = swap_pool_state_account.pool_assets_eq_balances[asset_index];
 
@@ -1088,11 +2434,12 @@ pub mod swap_pool_amplified {
 
 
 #[derive(Accounts)]
+#[instruction(k: u64, initial_asset_balances: Vec<u64>)]
 pub struct Initialize<'info> {
     #[account(mut)]
     pub setup_master: Signer<'info>,
     pub dao_authority: Signer<'info>,
-    #[account(init, payer = setup_master, space = SwapPoolState::LEN)]  // NOTE: LEN includes discriminator
+    #[account(init, payer = setup_master, space = SwapPoolState::space(initial_asset_balances.len()))]  // NOTE: space includes discriminator
     pub swap_pool_state_account: Account<'info, SwapPoolState>,
 
     // Pool token accounts
@@ -1275,6 +2622,32 @@ pub struct FinishSetup<'info> {
 }
 
 
+#[derive(Accounts)]
+pub struct CancelSetup<'info> {
+    // ! Make sure the provided setup_master matches the one saved in swap_pool_state_account
+    #[account(
+        mut,
+        has_one = setup_master @ ErrorCode::InvalidSetupAuthority,
+        close = setup_master
+    )]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+    #[account(mut)]
+    pub setup_master: Signer<'info>,
+
+    #[account(
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            POOL_AUTHORITY_SEED
+        ],
+        bump = swap_pool_state_account.authority_bump
+    )]
+    /// CHECK: Safe, as we are not reading from the account.
+    pub swap_pool_authority: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>
+}
+
+
 
 #[derive(Accounts)]
 pub struct Deposit<'info> {
@@ -1427,8 +2800,7 @@ pub struct LocalSwap<'info> {
     to_asset_index: u8,
     destination: Pubkey,
     amount: u64,
-    min_output: [u64; 4],
-    escrow_nonce: u32
+    min_output: [u64; 4]
 )]
 pub struct OutSwap<'info> {
 
@@ -1456,18 +2828,9 @@ pub struct OutSwap<'info> {
     pub token_program: Program<'info, Token>,
 
     // Escrow account
-    #[account(
-        init,
-        payer = swap_escrow_rent_payer,
-        space = SwapEscrow::LEN,
-        seeds = [
-            &swap_pool_state_account.key().to_bytes().as_ref(),
-            &escrow_nonce.to_be_bytes().as_ref(),
-            &POOL_ESCROW_SEED,
-        ],
-        bump
-    )]
-    pub swap_escrow: Account<'info, SwapEscrow>,
+    #[account(mut)]
+    /// CHECK: Verified and created by the out_swap handler (the seeds depend on the escrow id)
+    pub swap_escrow: UncheckedAccount<'info>,
     #[account(mut)]
     pub swap_escrow_rent_payer: Signer<'info>,
 
@@ -1499,62 +2862,276 @@ pub struct OutSwap<'info> {
     #[account(mut)]
     pub polymerase_ibc_data_account_payer: Signer<'info>,    //TODO rename
 
-    pub system_program: Program<'info, System> 
+    pub system_program: Program<'info, System> 
+
+}
+
+impl<'info> OutSwap<'info> {
+
+    pub fn create_swap_escrow(
+        &self,
+        escrow_id: &[u8; 32],
+        swap_escrow: SwapEscrow
+    ) -> Result<()> {
+
+        let swap_pool_state_account_key = self.swap_pool_state_account.key();
+
+        let (expected_swap_escrow, swap_escrow_bump) = Pubkey::find_program_address(
+            &[
+                swap_pool_state_account_key.as_ref(),
+                escrow_id.as_ref(),
+                POOL_ESCROW_SEED
+            ],
+            &crate::ID
+        );
+
+        if self.swap_escrow.key().ne(&expected_swap_escrow) {
+            return Err(error!(ErrorCode::InvalidSwapEscrowAccount));
+        }
+
+        // NOTE: Fails if the escrow already exists (i.e. an identical swap has been sent within the same slot)
+        create_pda_account(
+            SwapEscrow::LEN,
+            &crate::ID,
+            self.swap_escrow.to_account_info(),
+            &[
+                swap_pool_state_account_key.as_ref(),
+                escrow_id.as_ref(),
+                POOL_ESCROW_SEED,
+                &[swap_escrow_bump]
+            ],
+            self.swap_escrow_rent_payer.to_account_info(),
+            self.system_program.to_account_info()
+        );
+
+        SwapEscrow {
+            bump: swap_escrow_bump,
+            ..swap_escrow
+        }.try_serialize(&mut &mut self.swap_escrow.try_borrow_mut_data()?[..])
+    }
+
+}
+
+/// Verifies that the block number of an outgoing swap is a recent slot (the block number is truncated to 32 bits, as in the
+/// EVM implementation).
+fn verify_block_number(block_number: u32) -> Result<()> {
+
+    let current_block_number = Clock::get()?.slot as u32;
+
+    if current_block_number.wrapping_sub(block_number) > MAX_BLOCK_NUMBER_AGE {
+        return Err(error!(ErrorCode::InvalidBlockNumber));
+    }
+
+    Ok(())
+}
+
+
+
+#[derive(Accounts)]
+#[instruction(
+    escrow_id: [u8; 32]
+)]
+pub struct OutSwapAck<'info> {
+
+    #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+
+    // Escrow account
+    #[account(
+        mut,
+        close = swap_escrow_rent_payer,
+        has_one = swap_escrow_rent_payer @ ErrorCode::InvalidSwapEscrowRentPayerAccount,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes().as_ref(),
+            escrow_id.as_ref(),
+            &POOL_ESCROW_SEED
+        ],
+        bump = swap_escrow.bump
+    )]
+    pub swap_escrow: Account<'info, SwapEscrow>,
+
+    #[account(mut)]
+    /// CHECK: Must match the one saved in swap_escrow
+    pub swap_escrow_rent_payer: UncheckedAccount<'info>,
+
+    // Chain Interface
+    pub ibc_interface: Signer<'info>,
+
+    pub system_program: Program<'info, System> 
+
+}
+
+
+
+#[derive(Accounts)]
+#[instruction(
+    escrow_id: [u8; 32]
+)]
+pub struct OutSwapTimeout<'info> {
+
+    #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
+
+    // Returned asset
+    #[account()]
+    pub asset_mint: Box<Account<'info, Mint>>,
+    #[account(mut)]
+    pub fallback_wallet: Account<'info, TokenAccount>,
+    #[account(
+        mut,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            &asset_mint.key().to_bytes(),
+            POOL_ASSET_WALLET_SEED
+        ],
+        bump = swap_pool_state_account.wallets_bumps[
+            swap_pool_state_account.get_asset_index(&asset_mint.key()).unwrap()
+        ]
+    )]
+    pub swap_pool_asset_wallet: Account<'info, TokenAccount>,
+
+    /// CHECK: Safe, as we are not reading from the account
+    #[account(
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            POOL_AUTHORITY_SEED
+        ],
+        bump = swap_pool_state_account.authority_bump
+    )]
+    pub swap_pool_authority: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    // Escrow account
+    #[account(
+        mut,
+        close = swap_escrow_rent_payer,
+        has_one = swap_escrow_rent_payer @ ErrorCode::InvalidSwapEscrowRentPayerAccount,
+        has_one = fallback_wallet @ ErrorCode::InvalidFallbackWalletAccount,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes().as_ref(),
+            escrow_id.as_ref(),
+            &POOL_ESCROW_SEED
+        ],
+        bump = swap_escrow.bump
+    )]
+    pub swap_escrow: Account<'info, SwapEscrow>,
+
+    #[account(mut)]
+    /// CHECK: Must match the one saved in swap_escrow
+    pub swap_escrow_rent_payer: UncheckedAccount<'info>,
+
+    // Chain Interface
+    pub ibc_interface: Signer<'info>,
+
+    pub system_program: Program<'info, System> 
+
+}
+
+
+#[derive(Accounts)]
+pub struct InSwap<'info> {
+    #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
+
+    // Output asset
+    #[account()]
+    pub output_asset_mint: Box<Account<'info, Mint>>,
+    #[account(mut)]
+    pub output_asset_wallet: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            &output_asset_mint.key().to_bytes(),
+            POOL_ASSET_WALLET_SEED
+        ],
+        bump = swap_pool_state_account.wallets_bumps[
+            swap_pool_state_account.get_asset_index(&output_asset_mint.key()).unwrap()
+        ]
+    )]
+    pub swap_pool_output_asset_wallet: Account<'info, TokenAccount>,
+
+    // Asset wallet authority
+    #[account(
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            POOL_AUTHORITY_SEED
+        ],
+        bump = swap_pool_state_account.authority_bump
+    )]
+    /// CHECK: Safe, as we are not reading from the account.
+    pub swap_pool_authority: UncheckedAccount<'info>,
 
-}
+    // Chain Interface
+    pub ibc_interface: Signer<'info>,
 
+    pub token_program: Program<'info, Token>
+}
 
 
 #[derive(Accounts)]
-#[instruction(
-    escrow_nonce: u32
-)]
-pub struct OutSwapAck<'info> {
+#[instruction(identifier: [u8; 32])]
+pub struct Underwrite<'info> {
+    #[account(mut)]
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
 
-    #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
-    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+    // Output asset
+    #[account()]
+    pub output_asset_mint: Box<Account<'info, Mint>>,
+    #[account(mut)]
+    pub output_asset_wallet: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            &output_asset_mint.key().to_bytes(),
+            POOL_ASSET_WALLET_SEED
+        ],
+        bump = swap_pool_state_account.wallets_bumps[
+            swap_pool_state_account.get_asset_index(&output_asset_mint.key()).unwrap()
+        ]
+    )]
+    pub swap_pool_output_asset_wallet: Box<Account<'info, TokenAccount>>,
 
-    // Escrow account
+    // Underwriter
+    pub underwriter: Signer<'info>,
     #[account(
         mut,
-        close = swap_escrow_rent_payer,
-        has_one = swap_escrow_rent_payer @ ErrorCode::InvalidSwapEscrowRentPayerAccount,
+        constraint = underwriter_asset_wallet.owner == underwriter.key() @ ErrorCode::InvalidUnderwriterWalletAccount
+    )]
+    pub underwriter_asset_wallet: Box<Account<'info, TokenAccount>>,
+
+    // Underwrite escrow account
+    #[account(
+        init,
+        payer = underwrite_escrow_rent_payer,
+        space = UnderwriteEscrow::LEN,
         seeds = [
             &swap_pool_state_account.key().to_bytes().as_ref(),
-            &escrow_nonce.to_be_bytes().as_ref(),
-            &POOL_ESCROW_SEED
+            identifier.as_ref(),
+            &POOL_UNDERWRITE_SEED,
         ],
-        bump = swap_escrow.bump
+        bump
     )]
-    pub swap_escrow: Account<'info, SwapEscrow>,
-
+    pub underwrite_escrow: Account<'info, UnderwriteEscrow>,
     #[account(mut)]
-    /// CHECK: Must match the one saved in swap_escrow
-    pub swap_escrow_rent_payer: UncheckedAccount<'info>,
-
-    // Chain Interface
-    pub ibc_interface: Signer<'info>,
-
-    pub system_program: Program<'info, System> 
+    pub underwrite_escrow_rent_payer: Signer<'info>,
 
+    pub token_program: Program<'info, Token>,
+    pub system_program: Program<'info, System>
 }
 
 
-
 #[derive(Accounts)]
-#[instruction(
-    escrow_nonce: u32
-)]
-pub struct OutSwapTimeout<'info> {
-
+#[instruction(identifier: [u8; 32])]
+pub struct FillUnderwrite<'info> {
     #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
     pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
 
-    // Returned asset
+    // Underwritten asset
     #[account()]
     pub asset_mint: Box<Account<'info, Mint>>,
-    #[account(mut)]
-    pub fallback_wallet: Account<'info, TokenAccount>,
     #[account(
         mut,
         seeds = [
@@ -1566,7 +3143,9 @@ pub struct OutSwapTimeout<'info> {
             swap_pool_state_account.get_asset_index(&asset_mint.key()).unwrap()
         ]
     )]
-    pub swap_pool_asset_wallet: Account<'info, TokenAccount>,
+    pub swap_pool_asset_wallet: Box<Account<'info, TokenAccount>>,
+    #[account(mut)]
+    pub underwriter_wallet: Box<Account<'info, TokenAccount>>,
 
     /// CHECK: Safe, as we are not reading from the account
     #[account(
@@ -1578,47 +3157,86 @@ pub struct OutSwapTimeout<'info> {
     )]
     pub swap_pool_authority: UncheckedAccount<'info>,
 
-    pub token_program: Program<'info, Token>,
-
-    // Escrow account
+    // Underwrite escrow account
     #[account(
         mut,
-        close = swap_escrow_rent_payer,
-        has_one = swap_escrow_rent_payer @ ErrorCode::InvalidSwapEscrowRentPayerAccount,
-        has_one = fallback_wallet @ ErrorCode::InvalidFallbackWalletAccount,
+        close = underwrite_escrow_rent_payer,
+        has_one = underwrite_escrow_rent_payer @ ErrorCode::InvalidUnderwriteEscrowRentPayerAccount,
+        has_one = underwriter_wallet @ ErrorCode::InvalidUnderwriterWalletAccount,
         seeds = [
             &swap_pool_state_account.key().to_bytes().as_ref(),
-            &escrow_nonce.to_be_bytes().as_ref(),
-            &POOL_ESCROW_SEED
+            identifier.as_ref(),
+            &POOL_UNDERWRITE_SEED
         ],
-        bump = swap_escrow.bump
+        bump = underwrite_escrow.bump
     )]
-    pub swap_escrow: Account<'info, SwapEscrow>,
+    pub underwrite_escrow: Account<'info, UnderwriteEscrow>,
 
     #[account(mut)]
-    /// CHECK: Must match the one saved in swap_escrow
-    pub swap_escrow_rent_payer: UncheckedAccount<'info>,
+    /// CHECK: Must match the one saved in underwrite_escrow
+    pub underwrite_escrow_rent_payer: UncheckedAccount<'info>,
 
     // Chain Interface
     pub ibc_interface: Signer<'info>,
 
-    pub system_program: Program<'info, System> 
+    pub token_program: Program<'info, Token>
+}
+
+
+#[derive(Accounts)]
+#[instruction(identifier: [u8; 32])]
+pub struct ExpireUnderwrite<'info> {
+    #[account(mut)]
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
+
+    pub caller: Signer<'info>,
+
+    // Underwrite escrow account
+    #[account(
+        mut,
+        close = underwrite_escrow_rent_payer,
+        has_one = underwrite_escrow_rent_payer @ ErrorCode::InvalidUnderwriteEscrowRentPayerAccount,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes().as_ref(),
+            identifier.as_ref(),
+            &POOL_UNDERWRITE_SEED
+        ],
+        bump = underwrite_escrow.bump
+    )]
+    pub underwrite_escrow: Account<'info, UnderwriteEscrow>,
 
+    #[account(mut)]
+    /// CHECK: Must match the one saved in underwrite_escrow
+    pub underwrite_escrow_rent_payer: UncheckedAccount<'info>,
 }
 
 
 #[derive(Accounts)]
-pub struct InSwap<'info> {
-    #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
+pub struct CalcSendAsset<'info> {
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
+
+    // Input asset
+    pub input_asset_mint: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            &input_asset_mint.key().to_bytes(),
+            POOL_ASSET_WALLET_SEED
+        ],
+        bump = swap_pool_state_account.wallets_bumps[
+            swap_pool_state_account.get_asset_index(&input_asset_mint.key()).unwrap()
+        ]
+    )]
+    pub swap_pool_input_asset_wallet: Account<'info, TokenAccount>,
+}
+
+#[derive(Accounts)]
+pub struct CalcReceiveAsset<'info> {
     pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
 
     // Output asset
-    #[account()]
     pub output_asset_mint: Box<Account<'info, Mint>>,
-    #[account(mut)]
-    pub output_asset_wallet: Box<Account<'info, TokenAccount>>,
     #[account(
-        mut,
         seeds = [
             &swap_pool_state_account.key().to_bytes(),
             &output_asset_mint.key().to_bytes(),
@@ -1629,22 +3247,39 @@ pub struct InSwap<'info> {
         ]
     )]
     pub swap_pool_output_asset_wallet: Account<'info, TokenAccount>,
+}
 
-    // Asset wallet authority
+#[derive(Accounts)]
+pub struct CalcLocalSwap<'info> {
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
+
+    // Input asset
+    pub input_asset_mint: Box<Account<'info, Mint>>,
     #[account(
         seeds = [
             &swap_pool_state_account.key().to_bytes(),
-            POOL_AUTHORITY_SEED
+            &input_asset_mint.key().to_bytes(),
+            POOL_ASSET_WALLET_SEED
         ],
-        bump = swap_pool_state_account.authority_bump
+        bump = swap_pool_state_account.wallets_bumps[
+            swap_pool_state_account.get_asset_index(&input_asset_mint.key()).unwrap()
+        ]
     )]
-    /// CHECK: Safe, as we are not reading from the account.
-    pub swap_pool_authority: UncheckedAccount<'info>,
-
-    // Chain Interface
-    pub ibc_interface: Signer<'info>,
+    pub swap_pool_input_asset_wallet: Account<'info, TokenAccount>,
 
-    pub token_program: Program<'info, Token>
+    // Output asset
+    pub output_asset_mint: Box<Account<'info, Mint>>,
+    #[account(
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            &output_asset_mint.key().to_bytes(),
+            POOL_ASSET_WALLET_SEED
+        ],
+        bump = swap_pool_state_account.wallets_bumps[
+            swap_pool_state_account.get_asset_index(&output_asset_mint.key()).unwrap()
+        ]
+    )]
+    pub swap_pool_output_asset_wallet: Account<'info, TokenAccount>,
 }
 
 
@@ -1670,6 +3305,13 @@ pub struct OutLiquiditySwap<'info> {
 
     pub token_program: Program<'info, Token>,
 
+    // Escrow account
+    #[account(mut)]
+    /// CHECK: Verified and created by the out_liquidity_swap handler (the seeds depend on the escrow id)
+    pub liquidity_escrow: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub liquidity_escrow_rent_payer: Signer<'info>,
+
     pub ibc_interface_program: Program<'info, IbcInterface>,
 
     /// CHECK: Safe, as we are not reading from the account
@@ -1703,6 +3345,48 @@ pub struct OutLiquiditySwap<'info> {
 }
 
 impl<'info> OutLiquiditySwap<'info> {
+
+    pub fn create_liquidity_escrow(
+        &self,
+        escrow_id: &[u8; 32],
+        liquidity_escrow: LiquidityEscrow
+    ) -> Result<()> {
+
+        let swap_pool_state_account_key = self.swap_pool_state_account.key();
+
+        let (expected_liquidity_escrow, liquidity_escrow_bump) = Pubkey::find_program_address(
+            &[
+                swap_pool_state_account_key.as_ref(),
+                escrow_id.as_ref(),
+                POOL_LIQUIDITY_ESCROW_SEED
+            ],
+            &crate::ID
+        );
+
+        if self.liquidity_escrow.key().ne(&expected_liquidity_escrow) {
+            return Err(error!(ErrorCode::InvalidLiquidityEscrowAccount));
+        }
+
+        // NOTE: Fails if the escrow already exists (i.e. an identical swap has been sent within the same slot)
+        create_pda_account(
+            LiquidityEscrow::LEN,
+            &crate::ID,
+            self.liquidity_escrow.to_account_info(),
+            &[
+                swap_pool_state_account_key.as_ref(),
+                escrow_id.as_ref(),
+                POOL_LIQUIDITY_ESCROW_SEED,
+                &[liquidity_escrow_bump]
+            ],
+            self.liquidity_escrow_rent_payer.to_account_info(),
+            self.system_program.to_account_info()
+        );
+
+        LiquidityEscrow {
+            bump: liquidity_escrow_bump,
+            ..liquidity_escrow
+        }.try_serialize(&mut &mut self.liquidity_escrow.try_borrow_mut_data()?[..])
+    }
     
     pub fn burn_pool_tokens_of_sender(
         &self,
@@ -1727,6 +3411,102 @@ impl<'info> OutLiquiditySwap<'info> {
 
 
 
+#[derive(Accounts)]
+#[instruction(
+    escrow_id: [u8; 32]
+)]
+pub struct OutLiquiditySwapAck<'info> {
+
+    #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
+
+    // Escrow account
+    #[account(
+        mut,
+        close = liquidity_escrow_rent_payer,
+        has_one = liquidity_escrow_rent_payer @ ErrorCode::InvalidLiquidityEscrowRentPayerAccount,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes().as_ref(),
+            escrow_id.as_ref(),
+            &POOL_LIQUIDITY_ESCROW_SEED
+        ],
+        bump = liquidity_escrow.bump
+    )]
+    pub liquidity_escrow: Account<'info, LiquidityEscrow>,
+
+    #[account(mut)]
+    /// CHECK: Must match the one saved in liquidity_escrow
+    pub liquidity_escrow_rent_payer: UncheckedAccount<'info>,
+
+    // Chain Interface
+    pub ibc_interface: Signer<'info>,
+
+    pub system_program: Program<'info, System> 
+
+}
+
+
+#[derive(Accounts)]
+#[instruction(
+    escrow_id: [u8; 32]
+)]
+pub struct OutLiquiditySwapTimeout<'info> {
+
+    #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
+
+    // Pool tokens
+    #[account(
+        mut,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            POOL_TOKEN_MINT_SEED
+        ],
+        bump = swap_pool_state_account.token_mint_bump
+    )]
+    pub swap_pool_token_mint: Box<Account<'info, Mint>>,
+    #[account(mut)]
+    pub fallback_wallet: Account<'info, TokenAccount>,
+
+    /// CHECK: Safe, as we are not reading from the account
+    #[account(
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            POOL_AUTHORITY_SEED
+        ],
+        bump = swap_pool_state_account.authority_bump
+    )]
+    pub swap_pool_authority: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    // Escrow account
+    #[account(
+        mut,
+        close = liquidity_escrow_rent_payer,
+        has_one = liquidity_escrow_rent_payer @ ErrorCode::InvalidLiquidityEscrowRentPayerAccount,
+        has_one = fallback_wallet @ ErrorCode::InvalidFallbackWalletAccount,
+        seeds = [
+            &swap_pool_state_account.key().to_bytes().as_ref(),
+            escrow_id.as_ref(),
+            &POOL_LIQUIDITY_ESCROW_SEED
+        ],
+        bump = liquidity_escrow.bump
+    )]
+    pub liquidity_escrow: Account<'info, LiquidityEscrow>,
+
+    #[account(mut)]
+    /// CHECK: Must match the one saved in liquidity_escrow
+    pub liquidity_escrow_rent_payer: UncheckedAccount<'info>,
+
+    // Chain Interface
+    pub ibc_interface: Signer<'info>,
+
+    pub system_program: Program<'info, System> 
+
+}
+
+
 #[derive(Accounts)]
 pub struct InLiquiditySwap<'info> {
     #[account(mut, has_one = ibc_interface @ ErrorCode::InvalidIBCInterfaceAccount)]
@@ -1803,17 +3583,114 @@ pub struct DistributeFees<'info> {
 
 
 
+#[derive(Accounts)]
+pub struct SetAmplification<'info> {
+    #[account(mut, has_one = dao_authority @ ErrorCode::InvalidAuthority)]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+    pub dao_authority: Signer<'info>,
+}
+
+
+#[derive(Accounts)]
+pub struct ProposeDaoAuthority<'info> {
+    #[account(mut, has_one = dao_authority @ ErrorCode::InvalidAuthority)]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+    pub dao_authority: Signer<'info>,
+}
+
+
+#[derive(Accounts)]
+pub struct AcceptDaoAuthority<'info> {
+    // NOTE: No signature can be provided for the default pubkey, hence no handover can be accepted if none is pending
+    #[account(mut, has_one = pending_dao_authority @ ErrorCode::InvalidPendingAuthority)]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+    pub pending_dao_authority: Signer<'info>,
+}
+
+
+#[derive(Accounts)]
+pub struct SetGuardian<'info> {
+    #[account(mut, has_one = dao_authority @ ErrorCode::InvalidAuthority)]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+    pub dao_authority: Signer<'info>,
+}
+
+
+#[derive(Accounts)]
+pub struct SetPauseFlags<'info> {
+    pub authority: Signer<'info>,
+    #[account(
+        mut,
+        constraint =
+            swap_pool_state_account.dao_authority == authority.key() ||
+            (
+                swap_pool_state_account.guardian != Pubkey::default() &&
+                swap_pool_state_account.guardian == authority.key()
+            )
+            @ ErrorCode::InvalidAuthority
+    )]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+}
+
+
+#[derive(Accounts)]
+pub struct SetVaultFee<'info> {
+    #[account(mut, has_one = dao_authority @ ErrorCode::InvalidAuthority)]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+    pub dao_authority: Signer<'info>,
+}
+
+
+#[derive(Accounts)]
+pub struct SetGovernanceFee<'info> {
+    #[account(mut, has_one = dao_authority @ ErrorCode::InvalidAuthority)]
+    pub swap_pool_state_account: Account<'info, SwapPoolState>,
+    pub dao_authority: Signer<'info>,
+}
+
+
+#[derive(Accounts)]
+pub struct CollectGovernanceFees<'info> {
+    #[account(mut)]
+    pub swap_pool_state_account: Box<Account<'info, SwapPoolState>>,
+
+    // Asset wallet authority
+    #[account(
+        seeds = [
+            &swap_pool_state_account.key().to_bytes(),
+            POOL_AUTHORITY_SEED
+        ],
+        bump = swap_pool_state_account.authority_bump
+    )]
+    /// CHECK: Safe, as we are not reading from the account.
+    pub swap_pool_authority: UncheckedAccount<'info>,
+
+    pub token_program: Program<'info, Token>
+}
+
+
+
 #[account]
 pub struct SwapPoolState {
     pub setup_master: Pubkey,
     pub dao_authority: Pubkey,     // TODO to be replaced/checked once the DAO structure gets finalized
+    pub pending_dao_authority: Pubkey,  // Set to the default pubkey when no handover is pending
+    pub guardian: Pubkey,          // May pause (but not unpause) the pool. Set to the default pubkey when unset
     pub ibc_interface: Pubkey,
-    pub pool_assets_mints: [Pubkey; NUMASSETS],
-    pub pool_assets_weights: [u64; NUMASSETS],
-    pub pool_assets_eq_balances: [u64; NUMASSETS],
+    pub pool_assets_mints: Vec<Pubkey>,
+    pub pool_assets_weights: Vec<u64>,
+    pub pool_assets_eq_balances: Vec<u64>,
     pub amplification_x64: [u64; 4],
+    pub target_amplification_x64: [u64; 4],
+    pub amplification_adjustment_target_timestamp: u64,     // Set to 0 when no amplification adjustment is in progress
+    pub amplification_last_modification_timestamp: u64,
+
+    pub escrowed_assets: Vec<u64>,
 
-    pub escrowed_assets: [u64; NUMASSETS],
+    pub vault_fee_x64: u64,
+    pub governance_fee_share_x64: u64,
+    pub governance_fee_destination: Pubkey,
+    pub accrued_governance_fees: Vec<u64>,
 
     pub max_units_inflow_x64: [u64; 4],
     pub units_inflow_amplification_x64: [u64; 4],
@@ -1823,34 +3700,80 @@ pub struct SwapPoolState {
     pub current_liquidity_inflow: u64,
     pub current_liquidity_inflow_timestamp: u64,
 
-    pub unit_tracker_x64: i128,
+    pub unit_tracker_x64: [u64; 4],     // Net units sent by the pool (outgoing minus incoming swaps), as an I256X64
+
+    pub pause_flags: PauseFlags,
 
     pub authority_bump: u8,
     pub token_mint_bump: u8,
-    pub wallets_bumps: [u8; NUMASSETS]
+    pub wallets_bumps: Vec<u8>
 }
 
 impl SwapPoolState {
-    pub const LEN: usize = 
-        8               // discriminator
-        + 32            // setup_master
-        + 32            // dao_authority
-        + 32            // ibc_interface
-        + 32*NUMASSETS  // pool_assets_mints
-        + 8*NUMASSETS   // pool_assets_weights
-        + 8*NUMASSETS   // pool_assets_eq_balances
-        + 32            // amplification_x64
-        + 8*NUMASSETS   // escrowed_assets
-        + 8*4           // max_units_inflow_x64
-        + 8*4           // units_inflow_amplification_x64
-        + 8*4           // current_units_inflow_x64
-        + 8             // current_units_inflow_timestamp
-        + 8             // current_liquidity_inflow
-        + 8             // current_liquidity_inflow_timestamp
-        + 4*4           // unit_tracker_x64
-        + 1             // authority_bump
-        + 1             // token_mint_bump
-        + NUMASSETS;    // wallets_bumps
+    /// Account size for a pool holding 'asset_count' assets (vectors are prefixed by a 4 byte length).
+    pub fn space(asset_count: usize) -> usize {
+        8                     // discriminator
+        + 32                  // setup_master
+        + 32                  // dao_authority
+        + 32                  // pending_dao_authority
+        + 32                  // guardian
+        + 32                  // ibc_interface
+        + 4 + 32*asset_count  // pool_assets_mints
+        + 4 + 8*asset_count   // pool_assets_weights
+        + 4 + 8*asset_count   // pool_assets_eq_balances
+        + 32                  // amplification_x64
+        + 32                  // target_amplification_x64
+        + 8                   // amplification_adjustment_target_timestamp
+        + 8                   // amplification_last_modification_timestamp
+        + 4 + 8*asset_count   // escrowed_assets
+        + 8                   // vault_fee_x64
+        + 8                   // governance_fee_share_x64
+        + 32                  // governance_fee_destination
+        + 4 + 8*asset_count   // accrued_governance_fees
+        + 8*4                 // max_units_inflow_x64
+        + 8*4                 // units_inflow_amplification_x64
+        + 8*4                 // current_units_inflow_x64
+        + 8                   // current_units_inflow_timestamp
+        + 8                   // current_liquidity_inflow
+        + 8                   // current_liquidity_inflow_timestamp
+        + 8*4                 // unit_tracker_x64
+        + PauseFlags::LEN     // pause_flags
+        + 1                   // authority_bump
+        + 1                   // token_mint_bump
+        + 4 + asset_count     // wallets_bumps
+    }
+
+    pub fn asset_count(&self) -> usize {
+        self.pool_assets_mints.len()
+    }
+
+
+    pub fn check_not_paused(&self, operation: PoolOperation) -> Result<()> {
+
+        // No pool operations are allowed until the setup is finished, as cancel_setup refunds the full pool balances
+        if self.setup_master.ne(&Pubkey::default()) {
+            return Err(error!(ErrorCode::SetupNotFinished));
+        }
+
+        if self.pause_flags.paused {
+            return Err(error!(ErrorCode::PoolPaused));
+        }
+
+        let operation_paused = match operation {
+            PoolOperation::LocalSwap    => self.pause_flags.local_swaps,
+            PoolOperation::OutgoingSwap => self.pause_flags.outgoing_swaps,
+            PoolOperation::IncomingSwap => self.pause_flags.incoming_swaps,
+            PoolOperation::Deposit      => self.pause_flags.deposits,
+            PoolOperation::Withdrawal   => self.pause_flags.withdrawals,
+            PoolOperation::Other        => false
+        };
+
+        if operation_paused {
+            return Err(error!(ErrorCode::OperationPaused));
+        }
+
+        Ok(())
+    }
 
 
     pub fn get_asset_index(&self, asset: &Pubkey) -> Option<usize> {
@@ -1858,6 +3781,69 @@ impl SwapPoolState {
     }
 
 
+    pub fn update_amplification(&mut self, current_timestamp: u64) -> Result<()> {
+
+        let adjustment_target_timestamp = self.amplification_adjustment_target_timestamp;
+
+        // No amplification adjustment in progress
+        if adjustment_target_timestamp == 0 {
+            return Ok(());
+        }
+
+        let last_modification_timestamp = self.amplification_last_modification_timestamp;
+
+        // If no time has passed since the last update, there is nothing to update
+        if current_timestamp == last_modification_timestamp {
+            return Ok(());
+        }
+
+        self.amplification_last_modification_timestamp = current_timestamp;
+
+        let target_amplification_x64  = U256(self.target_amplification_x64);
+        let current_amplification_x64 = U256(self.amplification_x64);
+
+        // If the target time has been reached, finalize the amplification adjustment
+        let new_amplification_x64 = if current_timestamp >= adjustment_target_timestamp {
+            self.amplification_adjustment_target_timestamp = 0;
+            target_amplification_x64
+        }
+        // Otherwise, linearly move the amplification towards the target one
+        else {
+            let elapsed_time   = U256::from(current_timestamp - last_modification_timestamp);       // Safe, as the timestamp only increases
+            let remaining_time = U256::from(adjustment_target_timestamp - last_modification_timestamp);    // Safe, as current_timestamp < adjustment_target_timestamp
+
+            if target_amplification_x64 >= current_amplification_x64 {
+                current_amplification_x64 + (target_amplification_x64 - current_amplification_x64) * elapsed_time / remaining_time
+            }
+            else {
+                current_amplification_x64 - (current_amplification_x64 - target_amplification_x64) * elapsed_time / remaining_time
+            }
+        };
+
+        self.amplification_x64 = new_amplification_x64.0;
+
+        // Recompute the security limit for the new amplification.
+        // NOTE: the asset eq balances are used, as the asset wallets are not available to every instruction.
+        let one_minus_amp_x64 = ONE_X64.checked_sub(new_amplification_x64).unwrap();
+
+        let mut max_units_inflow_x64 = U256::from(0);
+        for asset_index in 0..self.asset_count() {
+            max_units_inflow_x64 = max_units_inflow_x64.checked_add(
+                U256::from(self.pool_assets_weights[asset_index]).checked_mul(
+                    pow_x64(U256::from(self.pool_assets_eq_balances[asset_index]).shl(64), one_minus_amp_x64).unwrap()
+                ).unwrap()
+            ).unwrap();
+        }
+
+        let units_inflow_amplification_x64 = ONE_X64.checked_sub(inv_pow2_x64(one_minus_amp_x64).unwrap()).unwrap();
+
+        self.units_inflow_amplification_x64 = units_inflow_amplification_x64.0;
+        self.max_units_inflow_x64 = mul_x64(units_inflow_amplification_x64, max_units_inflow_x64).unwrap().0;
+
+        Ok(())
+    }
+
+
     pub fn update_units_inflow(
         &mut self,
         units_inflow_x64: U256,
@@ -1910,6 +3896,20 @@ impl SwapPoolState {
     }
 
 
+    /// Reverts (part of) the units inflow that has been accounted for by update_units_inflow.
+    pub fn release_units_inflow(
+        &mut self,
+        units_x64: U256
+    ) {
+        let current_units_inflow_x64 = U256(self.current_units_inflow_x64);
+        if current_units_inflow_x64 > units_x64 {
+            self.current_units_inflow_x64 = (current_units_inflow_x64 - units_x64).0;
+        }
+        else if !current_units_inflow_x64.is_zero() {
+            self.current_units_inflow_x64 = [0, 0, 0, 0];
+        }
+    }
+
     pub fn update_liquidity_units_inflow(
         &mut self,
         pool_tokens_flow: u64,
@@ -1994,6 +3994,42 @@ impl SwapPoolState {
 
 }
 
+/// Operations which may be individually halted by the dao_authority or the guardian (see 'PauseFlags').
+pub enum PoolOperation {
+    LocalSwap,
+    OutgoingSwap,
+    IncomingSwap,
+    Deposit,
+    Withdrawal,
+    Other       // Only halted by the global 'paused' flag
+}
+
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PauseFlags {
+    pub paused: bool,           // Halts every pool operation (except for governance ones and swap acks/timeouts)
+    pub local_swaps: bool,
+    pub outgoing_swaps: bool,   // Asset and liquidity swaps
+    pub incoming_swaps: bool,   // Asset and liquidity swaps and underwrites. Refused swaps time out and get refunded on the source chain
+    pub deposits: bool,
+    pub withdrawals: bool,
+}
+
+impl PauseFlags {
+    pub const LEN: usize = 6;
+
+    /// Whether every operation paused by 'self' is also paused by 'other'.
+    pub fn is_subset_of(&self, other: &PauseFlags) -> bool {
+        (!self.paused         || other.paused)         &&
+        (!self.local_swaps    || other.local_swaps)    &&
+        (!self.outgoing_swaps || other.outgoing_swaps) &&
+        (!self.incoming_swaps || other.incoming_swaps) &&
+        (!self.deposits       || other.deposits)       &&
+        (!self.withdrawals    || other.withdrawals)
+    }
+}
+
+
 
 #[account]
 pub struct SwapEscrow {
@@ -2015,16 +4051,71 @@ impl SwapEscrow {
 }
 
 
+#[account]
+pub struct LiquidityEscrow {
+    pub amount: u64,                        // Burnt pool tokens
+    pub eq_balances: [u64; MAX_ASSETS],     // Pool assets eq balances taken by the swap
+    pub fallback_wallet: Pubkey,
+    pub liquidity_escrow_rent_payer: Pubkey,
+    pub bump: u8,
+}
+
+impl LiquidityEscrow {
+    pub const LEN: usize = 
+        8                   // discriminator
+        + 8                 // amount
+        + 8*MAX_ASSETS      // eq_balances
+        + 32                // fallback_wallet
+        + 32                // liquidity_escrow_rent_payer
+        + 1;                // bump
+}
+
+
+
+#[account]
+pub struct UnderwriteEscrow {
+    pub amount: u64,
+    pub asset_index: u8,
+    pub units_x64: [u64; 4],
+    pub underwriter: Pubkey,
+    pub underwriter_wallet: Pubkey,
+    pub underwrite_escrow_rent_payer: Pubkey,
+    pub expiry: u64,
+    pub bump: u8,
+}
+
+impl UnderwriteEscrow {
+    pub const LEN: usize = 
+        8       // discriminator
+        + 8     // amount
+        + 1     // asset_index
+        + 8*4   // units_x64
+        + 32    // underwriter
+        + 32    // underwriter_wallet
+        + 32    // underwrite_escrow_rent_payer
+        + 8     // expiry
+        + 1;    // bump
+}
+
+
 // Events ***********************************************************************************************************************
 
+#[event]
+pub struct CancelSetupEvent {
+    swap_pool: Pubkey,
+
+    refunded_asset_amounts: Vec<u64>,
+    destination_asset_wallets: Vec<Pubkey>
+}
+
 // TODO add assets weights to events?
 // TODO add asset mints to deposit event?
 #[event]
 pub struct DepositEvent {
     swap_pool: Pubkey,
 
-    deposited_asset_amounts: [u64; NUMASSETS],
-    depositor_asset_wallets: [Pubkey; NUMASSETS],
+    deposited_asset_amounts: Vec<u64>,
+    depositor_asset_wallets: Vec<Pubkey>,
 
     withdrawn_pool_token_amount: u64,
     depositor_pool_token_wallet: Pubkey
@@ -2035,8 +4126,8 @@ pub struct DepositEvent {
 pub struct WithdrawEvent {
     swap_pool: Pubkey,
 
-    withdrawn_asset_amounts: [u64; NUMASSETS],
-    withdrawer_asset_wallets: [Pubkey; NUMASSETS],
+    withdrawn_asset_amounts: Vec<u64>,
+    withdrawer_asset_wallets: Vec<Pubkey>,
 
     burnt_pool_token_amount: u64,
     withdrawer_pool_token_wallet: Pubkey
@@ -2078,7 +4169,8 @@ pub struct OutSwapEvent {
     target_withdrawer: Pubkey,
     target_chain: u64,
 
-    escrow_nonce: u32,
+    escrow_id: [u8; 32],
+    underwrite_incentive_x16: u16,
 
     deposited_asset_mint: Pubkey,
     depositor_asset_wallet: Pubkey,
@@ -2091,13 +4183,97 @@ pub struct OutSwapEvent {
 #[event]
 pub struct OutSwapAckEvent {
     swap_pool: Pubkey,
-    escrow_nonce: u32
+    escrow_id: [u8; 32]
 }
 
 #[event]
 pub struct OutSwapTimeoutEvent {
     swap_pool: Pubkey,
-    escrow_nonce: u32
+    escrow_id: [u8; 32]
+}
+
+#[event]
+pub struct UnderwriteEvent {
+    swap_pool: Pubkey,
+    identifier: [u8; 32],
+
+    withdrawn_asset_mint: Pubkey,
+    withdrawer_asset_wallet: Pubkey,
+    underwriter_asset_wallet: Pubkey,
+
+    deposited_pool_units_x64: [u64; 4],
+    withdrawn_asset_amount: u64,
+    underwrite_incentive: u64
+}
+
+#[event]
+pub struct FillUnderwriteEvent {
+    swap_pool: Pubkey,
+    identifier: [u8; 32]
+}
+
+#[event]
+pub struct ExpireUnderwriteEvent {
+    swap_pool: Pubkey,
+    identifier: [u8; 32]
+}
+
+#[event]
+pub struct SetAmplificationEvent {
+    swap_pool: Pubkey,
+    target_timestamp: u64,
+    target_amplification_x64: [u64; 4]
+}
+
+#[event]
+pub struct ProposeDaoAuthorityEvent {
+    swap_pool: Pubkey,
+    pending_dao_authority: Pubkey
+}
+
+#[event]
+pub struct AcceptDaoAuthorityEvent {
+    swap_pool: Pubkey,
+    dao_authority: Pubkey
+}
+
+#[event]
+pub struct SetGuardianEvent {
+    swap_pool: Pubkey,
+    guardian: Pubkey
+}
+
+#[event]
+pub struct SetPauseFlagsEvent {
+    swap_pool: Pubkey,
+    pause_flags: PauseFlags
+}
+
+#[event]
+pub struct SetVaultFeeEvent {
+    swap_pool: Pubkey,
+    vault_fee_x64: u64
+}
+
+#[event]
+pub struct SetGovernanceFeeEvent {
+    swap_pool: Pubkey,
+    governance_fee_share_x64: u64
+}
+
+#[event]
+pub struct SetGovernanceFeeDestinationEvent {
+    swap_pool: Pubkey,
+    governance_fee_destination: Pubkey
+}
+
+#[event]
+pub struct CollectGovernanceFeesEvent {
+    swap_pool: Pubkey,
+    governance_fee_destination: Pubkey,
+
+    collected_asset_amounts: Vec<u64>,
+    destination_asset_wallets: Vec<Pubkey>
 }
 
 #[event]
@@ -2119,6 +4295,7 @@ pub struct OutLiquiditySwapEvent {
     target_pool: Pubkey,
     target_beneficiary: Pubkey,
     target_chain: u64,
+    escrow_id: [u8; 32],
 
     pool_token_mint: Pubkey,
     source_pool_token_wallet: Pubkey,
@@ -2128,6 +4305,18 @@ pub struct OutLiquiditySwapEvent {
     fees: u64,
 }
 
+#[event]
+pub struct OutLiquiditySwapAckEvent {
+    swap_pool: Pubkey,
+    escrow_id: [u8; 32]
+}
+
+#[event]
+pub struct OutLiquiditySwapTimeoutEvent {
+    swap_pool: Pubkey,
+    escrow_id: [u8; 32]
+}
+
 
 
 // Errors ***********************************************************************************************************************
@@ -2142,11 +4331,17 @@ pub enum ErrorCode {
     #[msg("All asset weights must be non-zero.")]
     InvalidAssetWeight,
     
-    #[msg("There must be at least one asset with a non-zero balance to create the pool.")]
+    #[msg("There must be at least one asset to create the pool.")]
     NoAssetsProvided,
+
+    #[msg("The asset count exceeds the maximum allowed, or the count of balances and weights do not match.")]
+    InvalidAssetCount,
     
     #[msg("The swap interface has not been linked to the pool.")]
     SwapInterfaceNotLinked, //TODO allow fully local pools?
+
+    #[msg("The pool setup has not been finished.")]
+    SetupNotFinished,
     
     
     // Swaps
@@ -2184,4 +4379,89 @@ pub enum ErrorCode {
     
     #[msg("The provided IBC state account does not match the expected one.")]
     InvalidIBCInterfaceAccount,
+
+    #[msg("The provided IBC interface program does not match the expected one.")]
+    InvalidIBCInterfaceProgram,
+
+
+    // Fees
+    #[msg("The vault fee exceeds the maximum allowed.")]
+    InvalidVaultFee,
+
+    #[msg("The governance fee share exceeds the maximum allowed.")]
+    InvalidGovernanceFeeShare,
+
+    #[msg("The governance fee destination has not been set.")]
+    GovernanceFeeDestinationNotSet,
+
+    #[msg("The provided governance fee destination wallet does not match the expected owner/mint.")]
+    InvalidGovernanceFeeDestinationWalletAccount,
+
+
+    // Underwriting
+    #[msg("The provided underwrite identifier does not match the one of the underwritten swap.")]
+    InvalidUnderwriteIdentifier,
+
+    #[msg("The underwrite has not expired yet.")]
+    UnderwriteNotExpired,
+
+    #[msg("The provided underwriter wallet does not match the expected one.")]
+    InvalidUnderwriterWalletAccount,
+
+    #[msg("The provided underwrite escrow rent payer does not match with the original payer of the escrow account rent.")]
+    InvalidUnderwriteEscrowRentPayerAccount,
+
+
+    // Deposits and Withdrawals
+    #[msg("The count of provided asset amounts does not match the pool asset count.")]
+    InvalidAssetAmountsCount,
+
+    #[msg("The deposit output is less than the specified minimum.")]
+    DepositMinOutputNotFulfilled,
+
+    #[msg("The withdraw output is less than the specified minimum.")]
+    WithdrawMinOutputNotFulfilled,
+    #[msg("A withdraw ratio exceeds 1.")]
+    InvalidWithdrawRatio,
+
+    #[msg("A withdraw ratio must be 0 once all the units have been allocated.")]
+    WithdrawRatioNotZero,
+
+    #[msg("Not all the withdrawn units have been allocated (at least one withdraw ratio must be set to 1).")]
+    UnusedUnitsAfterWithdrawal,
+
+
+    // Amplification
+    #[msg("The amplification adjustment target time is too close or too far into the future.")]
+    InvalidAmplificationAdjustmentTime,
+
+    #[msg("The target amplification must be within (0, 1), and at most a factor of 10 away from the current one.")]
+    InvalidTargetAmplification,
+
+
+    // Pause
+    #[msg("The pool is paused.")]
+    PoolPaused,
+
+    #[msg("The requested operation is paused.")]
+    OperationPaused,
+
+    #[msg("The guardian is only allowed to pause operations.")]
+    GuardianCannotUnpause,
+
+
+    // Authority
+    #[msg("The provided authority does not match the pending dao authority.")]
+    InvalidPendingAuthority,
+
+
+    // Escrow
+    #[msg("The provided swap escrow account does not match the one derived from the swap.")]
+    InvalidSwapEscrowAccount,
+    #[msg("The block number must be a recent slot.")]
+    InvalidBlockNumber,
+    #[msg("The provided liquidity escrow account does not match the one derived from the swap.")]
+    InvalidLiquidityEscrowAccount,
+    #[msg("The provided liquidity escrow rent payer does not match with the original payer of the escrow account rent.")]
+    InvalidLiquidityEscrowRentPayerAccount,
 }
\ No newline at end of file
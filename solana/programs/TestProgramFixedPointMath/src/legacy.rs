@@ -0,0 +1,49 @@
+// Former implementation of shared_lib's mul_x64, based on a bit-by-bit (double-and-add) mulmod. Kept only to benchmark
+// the compute units of the current implementation against it.
+
+use shared_lib::u256::U256;
+
+const P_XX      : u64  = 64;
+const P_XX_MAX  : U256 = U256([0xFFFFFFFFFFFFFFFFu64, 0, 0, 0]);
+const P_XX_ONE  : U256 = U256([1, 0, 0, 0]);
+
+pub fn legacy_mul_x64(a: U256, b: U256) -> Option<U256> {
+    let (r0, _) = a.overflowing_mul(b);
+    let mut r1 = mulmod(a, b, U256::max_value());
+
+    r1 = r1.overflowing_sub(r0).0 - U256::from((r1 < r0) as u64);
+
+    if r1 > P_XX_MAX { return None; }
+
+    Some((r1 << (256-P_XX)) + (r0 >> P_XX))
+}
+
+// https://stackoverflow.com/questions/12168348/ways-to-do-modulo-multiplication-with-primitive-types
+fn mulmod(a: U256, b: U256, m: U256) -> U256 {
+    let mut res = U256::zero();
+    let mut a   = a;
+    let mut b   = b;
+
+    if b >= m {
+        if m > (U256::max_value() >> P_XX_ONE) { b -= m }
+        else { b %= m };
+    }
+
+    while !a.is_zero() {
+        if !(a & P_XX_ONE).is_zero() {
+            if b >= m - res {
+                res = res.overflowing_sub(m).0;
+            }
+            res = res.overflowing_add(b).0;
+        }
+        a >>= P_XX_ONE;
+
+        let mut temp_b = b;
+        if b >= m - b {
+            temp_b = temp_b.overflowing_sub(m).0
+        }
+        b = b.overflowing_add(temp_b).0;
+    }
+
+    res
+}
@@ -2,6 +2,9 @@ use anchor_lang::prelude::*;
 use anchor_lang::error::Error;
 
 use shared_lib::u256::U256;
+use shared_lib::fixed_point_math_x64::Precision;
+
+mod legacy;
 
 declare_id!("FixedPointMathsPoLymer1111111111111111111111");
 
@@ -19,6 +22,13 @@ pub mod fixed_point_math {
         Ok(())
     }
         
+    /// Former (bit-by-bit mulmod based) mul_x64 implementation, used to benchmark the compute units of mul_x64
+    pub fn legacy_mul_x64(ctx: Context<MathOperation>, a: [u64; 4], b: [u64; 4]) -> Result<()> {
+        let calculation_data = &mut ctx.accounts.calculation_data;
+        calculation_data.result = legacy::legacy_mul_x64(U256(a), U256(b)).unwrap().0;
+        Ok(())
+    }
+        
     pub fn div_x64(ctx: Context<MathOperation>, a: [u64; 4], b: [u64; 4]) -> Result<()> {
         let calculation_data = &mut ctx.accounts.calculation_data;
         calculation_data.result = shared_lib::fixed_point_math_x64::div_x64(U256(a), U256(b)).unwrap().0;
@@ -31,6 +41,12 @@ pub mod fixed_point_math {
         Ok(())
     }
         
+    pub fn log2_x64_with_precision(ctx: Context<MathOperation>, x: [u64; 4], fractional_bits: u8) -> Result<()> {
+        let calculation_data = &mut ctx.accounts.calculation_data;
+        calculation_data.result = shared_lib::fixed_point_math_x64::log2_x64_with_precision(U256(x), precision_from_bits(fractional_bits)?).unwrap().0;
+        Ok(())
+    }
+        
     pub fn ln_x64(ctx: Context<MathOperation>, x: [u64; 4]) -> Result<()> {
         let calculation_data = &mut ctx.accounts.calculation_data;
         calculation_data.result = shared_lib::fixed_point_math_x64::ln_x64(U256(x)).unwrap().0;
@@ -43,6 +59,12 @@ pub mod fixed_point_math {
         Ok(())
     }
         
+    pub fn pow2_x64_with_precision(ctx: Context<MathOperation>, x: [u64; 4], fractional_bits: u8) -> Result<()> {
+        let calculation_data = &mut ctx.accounts.calculation_data;
+        calculation_data.result = shared_lib::fixed_point_math_x64::pow2_x64_with_precision(U256(x), precision_from_bits(fractional_bits)?).unwrap().0;
+        Ok(())
+    }
+        
     pub fn inv_pow2_x64(ctx: Context<MathOperation>, x: [u64; 4]) -> Result<()> {
         let calculation_data = &mut ctx.accounts.calculation_data;
         calculation_data.result = shared_lib::fixed_point_math_x64::inv_pow2_x64(U256(x)).unwrap().0;
@@ -80,6 +102,15 @@ pub mod fixed_point_math {
     }
 }
 
+fn precision_from_bits(fractional_bits: u8) -> Result<Precision> {
+    match fractional_bits {
+        32 => Ok(Precision::Bits32),
+        48 => Ok(Precision::Bits48),
+        64 => Ok(Precision::Bits64),
+        _  => Err(error!(ErrorCode::InvalidPrecision))
+    }
+}
+
 #[derive(Accounts)]
 pub struct Initialize<'info> {
     #[account(init, payer=deployer, space=256 + 8)]
@@ -105,4 +136,6 @@ pub struct CalculationData {
 pub enum ErrorCode {
     #[msg("Arithmetic Error. Possible overflow/underflow.")]
     ArithmeticError,
+    #[msg("Unsupported precision. Only 32, 48 and 64 fractional bits are supported.")]
+    InvalidPrecision,
 }
\ No newline at end of file
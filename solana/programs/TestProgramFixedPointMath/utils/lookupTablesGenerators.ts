@@ -14,6 +14,15 @@ function computeTwoTwoMinusI(elCount: number) {
         .map(bn => bn.round())
 }
 
+// Used by the precision-parameterised pow2 (x127, see P_HP in fixed_point_math_x64.rs)
+function computeTwoTwoMinusIX127(elCount: number) {
+    return [...Array(elCount).keys()]
+        .map(i => {
+            return mathjs.bignumber(2).pow(mathjs.bignumber(2).pow(-(i+1))).times(mathjs.bignumber(2).pow(127))
+        })
+        .map(bn => bn.round())
+}
+
 function computeTwoMinusTwoMinusI(elCount: number) {
     return [...Array(elCount).keys()]
         .map(i => {
@@ -62,13 +71,13 @@ function bigNumberArrToUint256StructArr(bNArr: BigNumber[]): string {
         .join(', ')
 }
 
-function bigNumberToUint64Arr(bN: BigNumber): number[] {
+function bigNumberToUint64Arr(bN: BigNumber): string[] {
     let bigint = BigInt(bN.toFixed(0));
     return [0n, 1n, 2n, 3n]
         .map(i => {
             return (bigint >> (64n*i)) & 0xFFFFFFFFFFFFFFFFn
         })
-        .map(bigintNum => Number(bigintNum))
+        .map(bigintNum => bigintNum.toString())     // Number() would lose precision for values larger than 2**53
 }
 
 function formatAndLogValues(bNArr: BigNumber[]): void {
@@ -86,6 +95,8 @@ function formatAndLogValues(bNArr: BigNumber[]): void {
 let twoTwoMinusI      = computeTwoTwoMinusI(20);
 let twoMinusTwoMinusI = computeTwoMinusTwoMinusI(20);
 
+let twoTwoMinusIX127  = computeTwoTwoMinusIX127(64);
+
 let expPos            = computeExpPos(9);
 let expNeg            = computeExpNeg(20)
 
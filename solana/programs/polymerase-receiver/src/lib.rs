@@ -1,15 +1,52 @@
 use anchor_lang::prelude::*;
-use anchor_lang::solana_program::{instruction::Instruction, program::invoke};
+use anchor_lang::solana_program::{instruction::Instruction, program::invoke_signed, pubkey};
 use anchor_lang::{AnchorDeserialize, AnchorSerialize};
 use polymerase_register::{PortRegistration, ID as POLYMERASE_REGISTER_ID};
 
 declare_id!("6NUbfXpGgo9dUKiEv8NQsGJoHxteMBVoAXLtnRHbJH7J");
 
+
+// Dapp callbacks sighashs
+const SIGHASH_ON_ACKNOWLEDGEMENT_PACKET : [u8; 8] = [1, 142, 48, 169, 216, 66, 198, 31];
+const SIGHASH_ON_TIMEOUT_PACKET         : [u8; 8] = [152, 10, 26, 185, 36, 193, 95, 76];
+
+// Seed of the authority which signs the dapp invocations. Dapps may verify the invocations by deriving it from this program id.
+pub const AUTHORITY_SEED : &[u8] = b"polymeraseAuthority";
+
+// Seed of the account which holds the relayer allowed to deliver packets, acks and timeouts.
+pub const RECEIVER_CONFIG_SEED : &[u8] = b"receiverConfig";
+
+// Key allowed to initialize the receiver config (the deployer of the program). Set to the wallet of the test environment.
+pub const DEPLOYER : Pubkey = pubkey!("GM9PCzkrfqXL7tBKo43h6n8CGvP1YqymvsHAsa742RAt");
+
 #[program]
 pub mod polymerase_receiver {
 
     use super::*;
 
+    // initialize the receiver config. Only the deployer may initialize it, and becomes the admin of the config.
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        relayer: Pubkey
+    ) -> Result<()> {
+        let receiver_config = &mut ctx.accounts.receiver_config;
+
+        receiver_config.admin   = ctx.accounts.admin.key();
+        receiver_config.relayer = relayer;
+        receiver_config.bump    = *ctx.bumps.get("receiver_config").unwrap();
+
+        Ok(())
+    }
+
+    // set the relayer allowed to deliver packets, acks and timeouts
+    pub fn set_relayer(
+        ctx: Context<SetRelayer>,
+        relayer: Pubkey
+    ) -> Result<()> {
+        ctx.accounts.receiver_config.relayer = relayer;
+        Ok(())
+    }
+
     // receive an ibc packet and forward to dapp
     pub fn on_receive<'a, 'b, 'c, 'info>(
         ctx: Context<'a, 'b, 'c, 'info, OnReceive<'info>>,
@@ -17,55 +54,142 @@ pub mod polymerase_receiver {
         _src_port: String,
         _src_channel: String,
         _dest_port: String,
-        _dest_channel: String,
+        dest_channel: String,
         _sequence: u64,
         data: Vec<u8>,
         _timeout_block: u64,
         _timeout_timestamp: u64
     ) -> Result<()> {
 
-        // Full CPI data (sighash + arguments)
-        let mut data_vec = ctx.accounts.port_registration.on_receive_sighash.try_to_vec().unwrap();
-        data_vec.append(&mut data.try_to_vec().unwrap());
-
-        let mut meta_accounts = vec![
-            AccountMeta::new_readonly(ctx.accounts.port_registration.key(), false),
-            AccountMeta::new_readonly(ctx.accounts.authority.key(), true)
-        ];
-
-        // add remaining accounts
-        meta_accounts.extend(ctx.remaining_accounts.iter().map(|a| AccountMeta {
-            pubkey: a.key(),
-            is_signer: a.is_signer,
-            is_writable: a.is_writable,
-        }));
-
-        let instruction = Instruction {
-            data: data_vec,
-            program_id: ctx.accounts.dapp_program.key(),
-            accounts: meta_accounts,
-        };
-
-        let mut accounts: Vec<AccountInfo> = vec![
-            ctx.accounts.port_registration.to_account_info(),
-            ctx.accounts.authority.to_account_info(),           // TODO Make the receiver sign the invocation instead?
-            ctx.accounts.dapp_program.to_account_info(),
-        ];
-        accounts.extend_from_slice(ctx.remaining_accounts);
-
-        invoke(&instruction, &accounts).unwrap();
+        let sighash = ctx.accounts.port_registration.on_receive_sighash;
 
-        Ok(())
+        // The (local) channel through which the packet has been received is forwarded, so that the dapp can derive the
+        // source chain of the packet
+        let mut args = dest_channel.try_to_vec().unwrap();
+        args.append(&mut data.try_to_vec().unwrap());
+
+        invoke_dapp(ctx, sighash, args)
+    }
+
+    // forward the acknowledgement of a sent ibc packet to the dapp
+    pub fn on_acknowledgement<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, OnReceive<'info>>,
+        _port_account_id: Pubkey,
+        data: Vec<u8>
+    ) -> Result<()> {
+        invoke_dapp(ctx, SIGHASH_ON_ACKNOWLEDGEMENT_PACKET, data.try_to_vec().unwrap())
+    }
+
+    // forward the timeout of a sent ibc packet to the dapp
+    pub fn on_timeout<'a, 'b, 'c, 'info>(
+        ctx: Context<'a, 'b, 'c, 'info, OnReceive<'info>>,
+        _port_account_id: Pubkey,
+        data: Vec<u8>
+    ) -> Result<()> {
+        invoke_dapp(ctx, SIGHASH_ON_TIMEOUT_PACKET, data.try_to_vec().unwrap())
     }
 }
 
 
+fn invoke_dapp<'a, 'b, 'c, 'info>(
+    ctx: Context<'a, 'b, 'c, 'info, OnReceive<'info>>,
+    sighash: [u8; 8],
+    mut args: Vec<u8>       // Serialized arguments
+) -> Result<()> {
+
+    // Full CPI data (sighash + arguments)
+    let mut data_vec = sighash.try_to_vec().unwrap();
+    data_vec.append(&mut args);
+
+    let mut meta_accounts = vec![
+        AccountMeta::new_readonly(ctx.accounts.port_registration.key(), false),
+        AccountMeta::new_readonly(ctx.accounts.authority.key(), true)
+    ];
+
+    // add remaining accounts
+    meta_accounts.extend(ctx.remaining_accounts.iter().map(|a| AccountMeta {
+        pubkey: a.key(),
+        is_signer: a.is_signer,
+        is_writable: a.is_writable,
+    }));
+
+    let instruction = Instruction {
+        data: data_vec,
+        program_id: ctx.accounts.dapp_program.key(),
+        accounts: meta_accounts,
+    };
+
+    let mut accounts: Vec<AccountInfo> = vec![
+        ctx.accounts.port_registration.to_account_info(),
+        ctx.accounts.authority.to_account_info(),
+        ctx.accounts.dapp_program.to_account_info(),
+    ];
+    accounts.extend_from_slice(ctx.remaining_accounts);
+
+    invoke_signed(
+        &instruction,
+        &accounts,
+        &[&[
+            AUTHORITY_SEED,
+            &[*ctx.bumps.get("authority").unwrap()]
+        ]]
+    ).unwrap();
+
+    Ok(())
+}
+
+
 
 // Contexts ****************************************************************************************
 
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = admin,
+        space = ReceiverConfig::LEN,
+        seeds = [
+            RECEIVER_CONFIG_SEED
+        ],
+        bump
+    )]
+    pub receiver_config: Account<'info, ReceiverConfig>,
+    #[account(
+        mut,
+        constraint = admin.key() == DEPLOYER @ Errors::InvalidDeployer
+    )]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetRelayer<'info> {
+    #[account(
+        mut,
+        seeds = [
+            RECEIVER_CONFIG_SEED
+        ],
+        bump = receiver_config.bump,
+        has_one = admin @ Errors::InvalidAdmin
+    )]
+    pub receiver_config: Account<'info, ReceiverConfig>,
+    pub admin: Signer<'info>,
+}
+
 #[derive(Accounts)]
 #[instruction(port_account_id: Pubkey)]
 pub struct OnReceive<'info> {
+    // only the configured relayer may deliver packets, acks and timeouts (the dapp invocations are signed by this program)
+    #[account(
+        seeds = [
+            RECEIVER_CONFIG_SEED
+        ],
+        bump = receiver_config.bump,
+        has_one = relayer @ Errors::InvalidRelayer
+    )]
+    pub receiver_config: Account<'info, ReceiverConfig>,
+    pub relayer: Signer<'info>,
+
     // registration account (to read program id and sighash)
     #[account(
         seeds = [
@@ -78,15 +202,49 @@ pub struct OnReceive<'info> {
     pub port_registration: Account<'info, PortRegistration>,
     /// CHECK: Crosschecked with the one saved in port_registration
     pub dapp_program: UncheckedAccount<'info>,
-    #[account()]
-    pub authority: Signer<'info>    // ! TODO verification required
+    /// CHECK: Safe, as we are not reading from the account. Signs the dapp invocation
+    #[account(
+        seeds = [
+            AUTHORITY_SEED
+        ],
+        bump
+    )]
+    pub authority: UncheckedAccount<'info>
 }
 
 
 
 // Accounts ****************************************************************************************
 
+const DISCRIMINATOR_LENGTH : usize = 8;
+const PUBLIC_KEY_LENGTH    : usize = 32;
+const BUMP_LENGTH          : usize = 1;
+
+#[account]
+pub struct ReceiverConfig {
+    pub admin: Pubkey,   // 32 bytes
+    pub relayer: Pubkey, // 32 bytes
+    pub bump: u8,        // 1 byte
+}
+
+impl ReceiverConfig {
+    const LEN: usize = DISCRIMINATOR_LENGTH
+        + PUBLIC_KEY_LENGTH // admin
+        + PUBLIC_KEY_LENGTH // relayer
+        + BUMP_LENGTH;      // bump
+}
+
 #[derive(PartialEq, Debug, Clone, AnchorSerialize)]
 pub struct CallOnReceiveArgs {
     data: Vec<u8>,
 }
+
+#[error_code]
+pub enum Errors {
+    #[msg("Only the configured relayer may deliver packets")]
+    InvalidRelayer,
+    #[msg("Only the admin may modify the receiver config")]
+    InvalidAdmin,
+    #[msg("Only the deployer may initialize the receiver config")]
+    InvalidDeployer,
+}
@@ -8,6 +8,7 @@ from pytest import fixture
 settings.register_profile('default', deadline=timedelta(seconds=30)) # Increase test timeout to 30s
 settings.load_profile('default')
 
+from utils.polymerase_endpoint_utils import initialize_polymerase_receiver
 from utils.transaction_utils import DEFAULT_SKIP_PREFLIGHT, confirm_transaction
 
 from solana.keypair import Keypair
@@ -101,6 +102,10 @@ async def polymerase_sender_program(workspace: WorkspaceType) -> Program:
 async def polymerase_receiver_program(workspace: WorkspaceType) -> Program:
     program = workspace["polymerase_receiver"]
     program.provider.opts = TxOpts(skip_preflight=DEFAULT_SKIP_PREFLIGHT)
+
+    # The tests relay the packets using the provider wallet
+    await initialize_polymerase_receiver(program, program.provider.wallet.public_key)
+
     return program
 
 
@@ -218,7 +223,7 @@ async def simple_pool_context_factory(
     swap_interface_program: Program,
     swap_interface_setup_master_keypair: Keypair,
     polymerase_register_program: Program,
-    polymerase_sender_program: Program,
+    polymerase_receiver_program: Program,
     mint_authority: Keypair,
     generic_payer: Keypair
 ) -> FactoryFixture:
@@ -244,7 +249,7 @@ async def simple_pool_context_factory(
             swap_interface_program,
             swap_interface_setup_master_keypair,
             polymerase_register_program,
-            polymerase_sender_program,
+            polymerase_receiver_program,
             mint_authority,
             generic_payer
         )
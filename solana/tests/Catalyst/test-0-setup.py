@@ -4,15 +4,18 @@ from solana.keypair import Keypair
 from solana.publickey import PublicKey
 from solana.rpc.commitment import Finalized
 
+from solana.transaction import AccountMeta
+from spl.token.constants import TOKEN_PROGRAM_ID
+
 from anchorpy import Context, Program, Provider
 
-from conftest import create_mints, create_users
-from utils.account_utils import get_swap_pool_authority
-from utils.swap_interface_utils import initialize_swap_interface_state, register_swap_interface_polymerase_port
+from conftest import FactoryFixture, create_mints, create_users
+from utils.account_utils import get_swap_pool_asset_wallet, get_swap_pool_authority, get_swap_pool_token_mint
+from utils.swap_interface_utils import SOLANA_CHAIN_ID, initialize_swap_interface_state, register_swap_interface_polymerase_port
 from utils.verify_utils import verify_catalyst_configuration
-from utils.transaction_utils import confirm_transaction
-from utils.swap_pool_utils import POOL_MAX_ASSET_COUNT, finish_swap_pool_setup, initialize_swap_pool_state, link_swap_interface_to_swap_pool
-from utils.token_utils import fund_accounts
+from utils.transaction_utils import TxEventListener, confirm_transaction
+from utils.swap_pool_utils import POOL_MAX_ASSET_COUNT, add_liquidity_to_swap_pool, create_connection, finish_swap_pool_setup, initialize_swap_pool_state, link_swap_interface_to_swap_pool
+from utils.token_utils import fund_accounts, get_account_info
 
 
 @given(asset_count = st.integers(min_value=0, max_value=POOL_MAX_ASSET_COUNT+1))
@@ -205,6 +208,7 @@ async def test_pool_setup_configurator(
         - The pool setup cannot be finished without a swap interface
         - Only the setup_master can link the swap interface
         - Only the setup_master can finish the pool setup
+        - Deposits are refused until the pool setup is finished
         - Assets cannot be added after the pool setup is complete
         - The setup cannot be finished twice
     """
@@ -259,6 +263,20 @@ async def test_pool_setup_configurator(
     )
     swap_pool_state = initialize_swap_pool_state_result.swap_pool_state
 
+    # Deposits are refused until the setup is finished (cancel_setup refunds the full pool balances)
+    with pytest.raises(Exception):
+        await add_liquidity_to_swap_pool(
+            swap_pool_program,
+            1,
+            assets[0],
+            depositor_asset_wallets[0],
+            depositor.user_keypair,
+            get_swap_pool_asset_wallet(swap_pool_program.program_id, swap_pool_state, assets[0])[0],
+            get_swap_pool_token_mint(swap_pool_program.program_id, swap_pool_state)[0],
+            swap_pool_authority,
+            swap_pool_state
+        )
+
     # The pool setup cannot be finished without a swap interface
     with pytest.raises(Exception):
         await finish_swap_pool_setup(
@@ -356,3 +374,163 @@ async def test_swap_interface_setup(
 
 
 
+
+
+async def test_cancel_setup(
+    swap_pool_program: Program,
+    swap_pool_setup_master_keypair: Keypair,
+    swap_interface_program: Program,
+    swap_interface_setup_master_keypair: Keypair,
+    polymerase_sender_program: Program,
+    provider: Provider,
+    mint_authority: Keypair,
+    generic_payer: Keypair,
+    simple_pool_context_factory: FactoryFixture
+):
+    swap_pool_state_keypair = Keypair()
+    swap_pool_state         = swap_pool_state_keypair.public_key
+
+    swap_pool_authority = get_swap_pool_authority(
+        swap_pool_program.program_id,
+        swap_pool_state,
+    )[0]
+
+    assets             = await create_mints(provider, mint_authority, 2)
+    creator            = (await create_users(provider, assets, generic_payer, 1))[0]
+    init_asset_amounts = [1000*10**6, 2000*10**6]
+
+    await fund_accounts(
+        provider                 = provider,
+        mints                    = assets,
+        mints_authority          = mint_authority,
+        token_accounts           = creator.token_accounts,
+        balance                  = init_asset_amounts,
+        delegate_authority       = swap_pool_authority,
+        delegate_balance         = init_asset_amounts,
+        token_accounts_authority = creator.user_keypair
+    )
+
+    # Partially setup the pool: initialize it, link an interface and create a connection
+    initialize_swap_pool_state_result = await initialize_swap_pool_state(
+        swap_pool_program,
+        swap_pool_setup_master_keypair,
+        None,
+        [1, 1],
+        init_asset_amounts,
+        assets,
+        creator.token_accounts,
+        creator.user_keypair.public_key,
+        swap_pool_state_keypair=swap_pool_state_keypair
+    )
+    swap_pool_asset_wallets = initialize_swap_pool_state_result.swap_pool_asset_wallets
+
+    swap_interface_state = (await initialize_swap_interface_state(
+        swap_interface_program,
+        swap_pool_program,
+        swap_pool_state,
+        polymerase_sender_program,
+        swap_interface_setup_master_keypair
+    )).swap_interface_state
+
+    await link_swap_interface_to_swap_pool(
+        swap_pool_program,
+        swap_pool_state,
+        swap_pool_setup_master_keypair,
+        swap_interface_state
+    )
+
+    connection_state = (await create_connection(
+        SOLANA_CHAIN_ID,
+        swap_interface_state,
+        swap_interface_program.program_id,
+        swap_pool_program,
+        swap_pool_state,
+        swap_pool_setup_master_keypair,
+        swap_interface_program,
+        swap_interface_state,
+        generic_payer
+    )).connection_state
+
+    async def cancel_setup(setup_master_keypair: Keypair, remaining_accounts: list[AccountMeta]):
+        tx = await swap_pool_program.rpc["cancel_setup"](
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "setup_master": setup_master_keypair.public_key,
+                    "swap_pool_authority": swap_pool_authority,
+                    "token_program": TOKEN_PROGRAM_ID
+                },
+                remaining_accounts=remaining_accounts,
+                signers=[setup_master_keypair]
+            )
+        )
+        await confirm_transaction(provider, tx)
+
+    remaining_accounts: list[AccountMeta] = []
+    for swap_pool_asset_wallet, creator_asset_wallet in zip(swap_pool_asset_wallets, creator.token_accounts):
+        remaining_accounts.append(AccountMeta(pubkey=swap_pool_asset_wallet, is_signer=False, is_writable=True))
+        remaining_accounts.append(AccountMeta(pubkey=creator_asset_wallet,   is_signer=False, is_writable=True))
+
+    ibc_accounts = [
+        AccountMeta(pubkey=swap_interface_program.program_id, is_signer=False, is_writable=False),
+        AccountMeta(pubkey=swap_interface_state,              is_signer=False, is_writable=True ),
+        AccountMeta(pubkey=connection_state,                  is_signer=False, is_writable=True )
+    ]
+
+    # Only the setup master may cancel the setup
+    with pytest.raises(Exception):
+        await cancel_setup(Keypair(), remaining_accounts + ibc_accounts)
+
+    # The interface state account must be provided together with the interface program
+    with pytest.raises(Exception):
+        await cancel_setup(swap_pool_setup_master_keypair, remaining_accounts + ibc_accounts[:1])
+
+    # Rent of the accounts closed by the cancellation
+    closed_accounts = [swap_pool_state, *swap_pool_asset_wallets, swap_interface_state, connection_state]
+    closed_accounts_rent = sum([(await provider.connection.get_balance(account)).value for account in closed_accounts])
+
+    setup_master_balance = (await provider.connection.get_balance(swap_pool_setup_master_keypair.public_key)).value
+
+    async with TxEventListener("CancelSetupEvent") as ev_listener:
+        await cancel_setup(swap_pool_setup_master_keypair, remaining_accounts + ibc_accounts)
+
+        cancel_setup_event = (await ev_listener.get_events(swap_pool_program))[0]
+        assert cancel_setup_event.data.refundedAssetAmounts == init_asset_amounts
+        assert cancel_setup_event.data.destinationAssetWallets == creator.token_accounts
+
+    # The initial assets are refunded
+    for asset, creator_asset_wallet, amount in zip(assets, creator.token_accounts, init_asset_amounts):
+        assert (await get_account_info(provider, asset, creator_asset_wallet)).amount == amount
+
+    # The pool, its asset wallets, the interface and its connections are closed, and their rent refunded to the setup master
+    for account in closed_accounts:
+        assert (await provider.connection.get_account_info(account)).value is None
+
+    assert (await provider.connection.get_balance(swap_pool_setup_master_keypair.public_key)).value == setup_master_balance + closed_accounts_rent
+
+
+    # The setup cannot be cancelled once finished
+    spc = await simple_pool_context_factory(1, 2, [1000, 3000], [1, 1], None, False)
+
+    finished_remaining_accounts: list[AccountMeta] = []
+    for swap_pool_asset_wallet, user_asset_wallet in zip(
+        spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.swap_pool_asset_wallets,
+        spc.users[0].token_accounts
+    ):
+        finished_remaining_accounts.append(AccountMeta(pubkey=swap_pool_asset_wallet, is_signer=False, is_writable=True))
+        finished_remaining_accounts.append(AccountMeta(pubkey=user_asset_wallet,      is_signer=False, is_writable=True))
+
+    with pytest.raises(Exception):
+        tx = await swap_pool_program.rpc["cancel_setup"](
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": spc.swap_pool_state,
+                    "setup_master": swap_pool_setup_master_keypair.public_key,
+                    "swap_pool_authority": spc.swap_pool_authority,
+                    "token_program": TOKEN_PROGRAM_ID
+                },
+                remaining_accounts=finished_remaining_accounts,
+                signers=[swap_pool_setup_master_keypair]
+            )
+        )
+        await confirm_transaction(provider, tx)
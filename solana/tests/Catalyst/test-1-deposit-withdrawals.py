@@ -1,15 +1,17 @@
+import math
+import pytest
 from solana.keypair import Keypair
 from solana.transaction import AccountMeta
 
 from anchorpy import Context, Program, Provider
-from conftest import UserWallet, create_mints, create_users
+from conftest import FactoryFixture, UserWallet, create_mints, create_users
 from utils.simulator_utils import create_and_verify_catalyst_simulator
 from utils.account_utils import get_swap_pool_asset_wallet, get_swap_pool_authority
 from utils.verify_utils import verify_catalyst_state, verify_deposit_event, verify_withdraw_event
 from utils.transaction_utils import DEFAULT_TX_COMMITMENT, TxEventListener, confirm_transaction
 
-from utils.swap_pool_utils import create_and_setup_swap_pool
-from utils.token_utils import approve, create_token_account, fund_accounts, get_account_info
+from utils.swap_pool_utils import create_and_setup_swap_pool, perform_deposit_mixed, perform_withdraw_mixed
+from utils.token_utils import approve, create_token_account, fund_accounts, get_account_info, get_mint_info, mint_to
 from spl.token.constants import TOKEN_PROGRAM_ID
 
 
@@ -19,7 +21,7 @@ async def test_deposits(
     swap_interface_program: Program,
     swap_interface_setup_master_keypair: Keypair,
     polymerase_register_program: Program,
-    polymerase_sender_program: Program,
+    polymerase_receiver_program: Program,
     mint_authority: Keypair,
     provider: Provider,
     generic_payer: Keypair
@@ -70,7 +72,7 @@ async def test_deposits(
         swap_interface_program,
         swap_interface_setup_master_keypair,
         polymerase_register_program,
-        polymerase_sender_program,
+        polymerase_receiver_program,
         swap_pool_state_keypair=swap_pool_state_keypair
     )
 
@@ -231,7 +233,7 @@ async def test_withdrawals(
     swap_interface_program: Program,
     swap_interface_setup_master_keypair: Keypair,
     polymerase_register_program: Program,
-    polymerase_sender_program: Program,
+    polymerase_receiver_program: Program,
     mint_authority: Keypair,
     provider: Provider,
     generic_payer: Keypair
@@ -282,7 +284,7 @@ async def test_withdrawals(
         swap_interface_program,
         swap_interface_setup_master_keypair,
         polymerase_register_program,
-        polymerase_sender_program,
+        polymerase_receiver_program,
         swap_pool_state_keypair=swap_pool_state_keypair
     )
 
@@ -362,6 +364,7 @@ async def test_withdrawals(
     async with TxEventListener("WithdrawEvent") as ev_listener:
         tx_result = await swap_pool_program.rpc["withdraw"](
             withdrawn_pool_tokens_base,
+            [0 for _ in range(asset_count)],
             ctx=Context(
                 accounts={
                     "swap_pool_state_account": swap_pool_state,
@@ -423,3 +426,200 @@ async def test_withdrawals(
         )
         assert(withdrawer_account_info.amount == asset_withdrawal_amounts[asset_index])
 
+
+
+async def test_deposit_mixed(
+    swap_pool_program: Program,
+    mint_authority: Keypair,
+    generic_payer: Keypair,
+    simple_pool_context_factory: FactoryFixture
+):
+    provider = swap_pool_program.provider
+
+    pool_assets_balances = [1000*10**6, 1000*10**6]
+
+    spc = await simple_pool_context_factory(2, 2, pool_assets_balances, [1, 1], None, False)
+
+    initialize_swap_pool_state_result = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result
+    swap_pool_asset_wallets           = initialize_swap_pool_state_result.swap_pool_asset_wallets
+    swap_pool_token_mint              = initialize_swap_pool_state_result.swap_pool_token_mint
+
+    depositor                = spc.users[1]
+    depositor_asset_wallets  = depositor.token_accounts
+
+    for asset, wallet, balance in zip(spc.swap_pool_assets, depositor_asset_wallets, pool_assets_balances):
+        await mint_to(provider, wallet, asset, mint_authority, balance)
+
+    depositor_pool_token_wallet = await create_token_account(
+        provider,
+        swap_pool_token_mint,
+        depositor.user_keypair.public_key,
+        generic_payer
+    )
+
+    async def deposit_mixed(amounts: list[int], min_pool_tokens: int):
+        return await perform_deposit_mixed(
+            amounts,
+            min_pool_tokens,
+            depositor_pool_token_wallet,
+            depositor.user_keypair,
+            depositor_asset_wallets,
+            swap_pool_program,
+            spc.swap_pool_state,
+            spc.swap_pool_assets,
+            swap_pool_asset_wallets,
+            swap_pool_token_mint,
+            spc.swap_pool_authority
+        )
+
+    async def expected_pool_tokens(amounts: list[int]) -> float:
+        # Units of the deposit (unit weights): sum_i log2((b_i + a_i)/b_i), less the vault fee. The minted pool tokens
+        # are given by supply * (2^(U/W_SUM) - 1).
+        swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(spc.swap_pool_state)
+        pool_balances        = [(await get_account_info(provider, asset, wallet)).amount for asset, wallet in zip(spc.swap_pool_assets, swap_pool_asset_wallets)]
+        pool_tokens_supply   = (await get_mint_info(provider, swap_pool_token_mint)).supply
+
+        units = sum(math.log2((balance + amount) / balance) for balance, amount in zip(pool_balances, amounts))
+        units = units * (1 - swap_pool_state_data.vault_fee_x64 / 2**64)
+
+        return pool_tokens_supply * (2 ** (units / len(amounts)) - 1)
+
+
+    # The deposit may not exceed the liquidity security limit (half of the pool token supply)
+    with pytest.raises(Exception):
+        await deposit_mixed([pool_assets_balances[0], pool_assets_balances[1]], 0)
+
+    # Balanced deposit, yielding the deposited share of the pool
+    deposit_amounts = [400*10**6, 400*10**6]
+    expected = await expected_pool_tokens(deposit_amounts)
+
+    # The minimum pool tokens must be fulfilled
+    with pytest.raises(Exception):
+        await deposit_mixed(deposit_amounts, math.ceil(expected) + 1)
+
+    deposit_result = await deposit_mixed(deposit_amounts, math.floor(expected * (1 - 1e-6)))
+    minted_pool_tokens = deposit_result.deposit_event.data.withdrawnPoolTokenAmount
+
+    assert minted_pool_tokens <= expected
+    assert minted_pool_tokens == pytest.approx(expected, rel=1e-6)
+    assert deposit_result.deposit_event.data.depositedAssetAmounts == deposit_amounts
+
+    assert (await get_account_info(provider, swap_pool_token_mint, depositor_pool_token_wallet)).amount == minted_pool_tokens
+    for asset, wallet, balance, amount in zip(spc.swap_pool_assets, depositor_asset_wallets, pool_assets_balances, deposit_amounts):
+        assert (await get_account_info(provider, asset, wallet)).amount == balance - amount
+
+
+    # The security limit accounts for the recent deposits: the following deposit would be allowed on its own, but
+    # not together with the previous one
+    with pytest.raises(Exception):
+        await deposit_mixed([420*10**6, 420*10**6], 0)
+
+
+    # Single-sided deposit
+    deposit_amounts = [100*10**6, 0]
+    expected = await expected_pool_tokens(deposit_amounts)
+
+    deposit_result = await deposit_mixed(deposit_amounts, 0)
+    minted_pool_tokens = deposit_result.deposit_event.data.withdrawnPoolTokenAmount
+
+    assert minted_pool_tokens <= expected
+    assert minted_pool_tokens == pytest.approx(expected, rel=1e-6)
+
+
+
+ONE_X64 = 2**64
+
+
+async def test_withdraw_mixed(
+    swap_pool_program: Program,
+    simple_pool_context_factory: FactoryFixture
+):
+    provider = swap_pool_program.provider
+
+    pool_assets_balances = [1000*10**6, 1000*10**6]
+
+    spc = await simple_pool_context_factory(2, 2, pool_assets_balances, [1, 1], None, False)
+
+    initialize_swap_pool_state_result = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result
+    swap_pool_asset_wallets           = initialize_swap_pool_state_result.swap_pool_asset_wallets
+    swap_pool_token_mint              = initialize_swap_pool_state_result.swap_pool_token_mint
+
+    withdrawer                   = spc.users[0]     # Liquidity provider
+    withdrawer_pool_token_wallet = initialize_swap_pool_state_result.depositor_pool_token_wallet_keypair.public_key
+    withdrawer_asset_wallets     = withdrawer.token_accounts
+
+    async def withdraw_mixed(pool_tokens_amount: int, withdraw_ratios_x64: list[int], min_outputs: list[int]):
+        return await perform_withdraw_mixed(
+            pool_tokens_amount,
+            withdraw_ratios_x64,
+            min_outputs,
+            withdrawer_pool_token_wallet,
+            withdrawer.user_keypair,
+            withdrawer_asset_wallets,
+            swap_pool_program,
+            spc.swap_pool_state,
+            spc.swap_pool_assets,
+            swap_pool_asset_wallets,
+            swap_pool_token_mint,
+            spc.swap_pool_authority
+        )
+
+    async def get_pool_balances() -> list[int]:
+        return [
+            (await get_account_info(provider, asset, wallet)).amount for asset, wallet in zip(spc.swap_pool_assets, swap_pool_asset_wallets)
+        ]
+
+    pool_tokens_supply = (await get_mint_info(provider, swap_pool_token_mint)).supply
+    withdrawn_pool_tokens = pool_tokens_supply // 100
+
+    # The withdraw ratios may not exceed 1
+    with pytest.raises(Exception):
+        await withdraw_mixed(withdrawn_pool_tokens, [ONE_X64 + 1, 0], [0, 0])
+
+    # All the units must be used (i.e. one of the ratios must be 1)
+    with pytest.raises(Exception):
+        await withdraw_mixed(withdrawn_pool_tokens, [ONE_X64 // 2, ONE_X64 // 2], [0, 0])
+
+    # Once all the units have been allocated, the following ratios must be 0
+    with pytest.raises(Exception):
+        await withdraw_mixed(withdrawn_pool_tokens, [ONE_X64, ONE_X64 // 2], [0, 0])
+
+    # The minimum outputs of the assets which are not withdrawn must be 0
+    with pytest.raises(Exception):
+        await withdraw_mixed(withdrawn_pool_tokens, [ONE_X64, 0], [0, 1])
+
+
+    # Splitting the units in halves (the ratios apply to the remaining units) is equivalent to a balanced withdrawal
+    pool_balances = await get_pool_balances()
+
+    withdraw_result = await withdraw_mixed(withdrawn_pool_tokens, [ONE_X64 // 2, ONE_X64], [0, 0])
+    withdrawn_amounts = withdraw_result.withdraw_event.data.withdrawnAssetAmounts
+
+    for asset_index in range(2):
+        expected_amount = pool_balances[asset_index] * withdrawn_pool_tokens / pool_tokens_supply
+        assert withdrawn_amounts[asset_index] <= expected_amount
+        assert withdrawn_amounts[asset_index] == pytest.approx(expected_amount, rel=1e-6)
+
+        withdrawer_asset_info = await get_account_info(provider, spc.swap_pool_assets[asset_index], withdrawer_asset_wallets[asset_index])
+        assert withdrawer_asset_info.amount == withdrawn_amounts[asset_index]
+
+    assert (await get_account_info(provider, swap_pool_token_mint, withdrawer_pool_token_wallet)).amount == pool_tokens_supply - withdrawn_pool_tokens
+
+
+    # The minimum outputs must be fulfilled
+    with pytest.raises(Exception):
+        await withdraw_mixed(withdrawn_pool_tokens, [ONE_X64 // 2, ONE_X64], [2 * withdrawn_amounts[0], 0])
+
+
+    # Withdraw all the units as the first asset
+    pool_balances      = await get_pool_balances()
+    pool_tokens_supply = (await get_mint_info(provider, swap_pool_token_mint)).supply
+
+    withdraw_result = await withdraw_mixed(withdrawn_pool_tokens, [ONE_X64, 0], [withdrawn_amounts[0], 0])
+    single_asset_withdrawn_amounts = withdraw_result.withdraw_event.data.withdrawnAssetAmounts
+
+    # Units of both assets (equal weights) converted into the first asset: b * (1 - (1 - pt/supply)^2)
+    expected_amount = pool_balances[0] * (1 - (1 - withdrawn_pool_tokens / pool_tokens_supply) ** 2)
+    assert single_asset_withdrawn_amounts[0] <= expected_amount
+    assert single_asset_withdrawn_amounts[0] == pytest.approx(expected_amount, rel=1e-6)
+    assert single_asset_withdrawn_amounts[1] == 0
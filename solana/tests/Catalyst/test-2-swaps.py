@@ -1,15 +1,21 @@
+import pytest
 from solana.keypair import Keypair
 from solana.transaction import AccountMeta
 from solana.system_program import SYS_PROGRAM_ID
 
+from solana.publickey import PublicKey
+from solders.signature import Signature
+
 from anchorpy import Context, Program
 from conftest import FactoryFixture, generic_payer
-from utils.common_utils import SOLANA_CHAIN_ID
+from utils.common_utils import SOLANA_CHAIN_ID, SimplePoolContext
+from utils.swap_interface_utils import SOLANA_CHANNEL_ID, get_channel_state_account
 
-from utils.swap_pool_utils import perform_cross_chain_swap, perform_local_swap
-from utils.transaction_utils import TxEventListener, confirm_transaction
+from utils.swap_pool_utils import UnderwriteSwapResult, expire_underwrite, perform_cross_chain_swap, perform_local_swap, set_pause_flags, underwrite_swap
+from utils.account_utils import compute_payload_underwrite_identifier, get_polymerase_authority, get_polymerase_receiver_config, get_swap_interface_receipt, get_swap_pool_underwrite_escrow
+from utils.transaction_utils import TxEventListener, confirm_transaction, get_return_data
 from utils.token_utils import get_account_info, mint_to
-from utils.verify_utils import u256_array_to_int, verify_catalyst_state, verify_in_swap_event
+from utils.verify_utils import int_to_u256_array, u256_array_to_int, verify_catalyst_state, verify_in_swap_event
 from spl.token.constants import TOKEN_PROGRAM_ID
 
 from hypothesis import given, strategies as st
@@ -249,6 +255,7 @@ async def test_self_cross_chain_swap(
 
     assert spc.create_connection_result is not None
     connection_state = spc.create_connection_result.connection_state
+    channel_state    = get_channel_state_account(swap_interface_program.program_id, swap_interface_state, SOLANA_CHANNEL_ID)[0]
 
 
     # Fund swapper with tokens for the swap
@@ -284,7 +291,6 @@ async def test_self_cross_chain_swap(
         target_pool_id,
         swapper_output_asset_index,
         swapper_output_asset_wallet,
-        source_swap_id,  # Escrow nonce
         generic_payer,
         swap_pool_program,
         swap_pool_state,
@@ -317,10 +323,24 @@ async def test_self_cross_chain_swap(
     # Fetch payload
     data = await polymerase_sender_program.account["IbcData"].fetch(cross_chain_swap_result.ibc_data)
 
-    authority = Keypair() #not used for now
+    authority = get_polymerase_authority(polymerase_receiver_program.program_id)[0]
 
-    async with TxEventListener("InSwapEvent") as ev_listener:
-        rpc_result = await polymerase_receiver_program.rpc["on_receive"](
+    receipt = get_swap_interface_receipt(
+        swap_interface_program.program_id,
+        swap_interface_state,
+        target_chain_id,
+        bytes(data.payload)
+    )[0]
+
+    # The underwrite escrow must always be provided (the swap has not been underwritten)
+    underwrite_escrow = get_swap_pool_underwrite_escrow(
+        swap_pool_program.program_id,
+        swap_pool_state,
+        compute_payload_underwrite_identifier(target_chain_id, swap_pool_state, bytes(data.payload))
+    )[0]
+
+    async def receive_swap():
+        return await polymerase_receiver_program.rpc["on_receive"](
             swap_interface_state,
             "",
             "",
@@ -334,22 +354,40 @@ async def test_self_cross_chain_swap(
                 accounts={
                     "port_registration": port_registration,
                     "dapp_program": target_program_id,
-                    "authority": authority.public_key
+                    "authority": authority,
+                    "receiver_config": get_polymerase_receiver_config(polymerase_receiver_program.program_id)[0],
+                    "relayer": provider.wallet.public_key
                 },
                 remaining_accounts=[
                     AccountMeta(swap_interface_state,          is_signer=False, is_writable=False ), # interface_state_account
                     AccountMeta(swap_pool_state,               is_signer=False, is_writable=True  ), # swap_pool
                     AccountMeta(swap_pool_program.program_id,  is_signer=False, is_writable=False ), # swap_pool_program
                     AccountMeta(TOKEN_PROGRAM_ID,              is_signer=False, is_writable=False ), # token_program
+                    AccountMeta(channel_state,                 is_signer=False, is_writable=False ), # channel_state_account
                     AccountMeta(connection_state,              is_signer=False, is_writable=False ), # connection_state_account
+                    AccountMeta(receipt,                       is_signer=False, is_writable=True  ), # receipt
+                    AccountMeta(provider.wallet.public_key,    is_signer=True,  is_writable=True  ), # receipt_rent_payer
+                    AccountMeta(SYS_PROGRAM_ID,                is_signer=False, is_writable=False ), # system_program
                     AccountMeta(swapper_output_asset,          is_signer=False, is_writable=False ), # output_asset_mint
                     AccountMeta(swapper_output_asset_wallet,   is_signer=False, is_writable=True  ), # output_asset_wallet
                     AccountMeta(swap_pool_output_asset_wallet, is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
                     AccountMeta(swap_pool_authority,           is_signer=False, is_writable=False ), # swap_pool_authority
-                ],
-                signers=[authority]
+                    AccountMeta(underwrite_escrow,             is_signer=False, is_writable=True  ), # underwrite_escrow
+                ]
             )
         )
+
+    # The swap is refused while incoming swaps are paused
+    dao_authority_keypair = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.dao_authority_keypair
+    await set_pause_flags(swap_pool_program, swap_pool_state, dao_authority_keypair, incoming_swaps=True)
+
+    with pytest.raises(Exception):
+        await confirm_transaction(provider, await receive_swap())
+
+    await set_pause_flags(swap_pool_program, swap_pool_state, dao_authority_keypair)
+
+    async with TxEventListener("InSwapEvent") as ev_listener:
+        rpc_result = await receive_swap()
         await confirm_transaction(provider, rpc_result)
 
         # Verify the deposit event
@@ -389,20 +427,28 @@ async def test_self_cross_chain_swap(
 
 
     # Step 3: Trigger ibc ack on source chain (simulates polymer chain endpoint contract)
+    # NOTE: Acks are processed even while the pool is paused
+    await set_pause_flags(swap_pool_program, swap_pool_state, dao_authority_keypair, paused=True)
+
     swap_escrow = cross_chain_swap_result.swap_escrow
-    ack_result = await swap_interface_program.rpc["on_acknowledgement_packet"](
+    ack_result = await polymerase_receiver_program.rpc["on_acknowledgement"](
+        swap_interface_state,
         data.payload,
         ctx=Context(
             accounts={
                 "port_registration": port_registration,
-                "interface_state_account": swap_interface_state,
-                "swap_pool": swap_pool_state,
-                "swap_pool_program": swap_pool_program.program_id,
-                "swap_pool_authority": swap_pool_authority,
-                "token_program": TOKEN_PROGRAM_ID,
-                "system_program": SYS_PROGRAM_ID
+                "dapp_program": swap_interface_program.program_id,
+                "authority": get_polymerase_authority(polymerase_receiver_program.program_id)[0],
+                "receiver_config": get_polymerase_receiver_config(polymerase_receiver_program.program_id)[0],
+                "relayer": provider.wallet.public_key
             },
             remaining_accounts=[
+                AccountMeta(swap_interface_state,          is_signer=False, is_writable=False ), # interface_state_account
+                AccountMeta(swap_pool_state,               is_signer=False, is_writable=True  ), # swap_pool
+                AccountMeta(swap_pool_program.program_id,  is_signer=False, is_writable=False ), # swap_pool_program
+                AccountMeta(swap_pool_authority,           is_signer=False, is_writable=False ), # swap_pool_authority
+                AccountMeta(TOKEN_PROGRAM_ID,              is_signer=False, is_writable=False ), # token_program
+                AccountMeta(SYS_PROGRAM_ID,                is_signer=False, is_writable=False ), # system_program
                 AccountMeta(swapper_output_asset,          is_signer=False, is_writable=False ), # output_asset_mint
                 AccountMeta(swapper_output_asset_wallet,   is_signer=False, is_writable=True  ), # output_asset_wallet
                 AccountMeta(swap_pool_output_asset_wallet, is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
@@ -413,6 +459,8 @@ async def test_self_cross_chain_swap(
     )
     await confirm_transaction(provider, ack_result)
 
+    await set_pause_flags(swap_pool_program, swap_pool_state, dao_authority_keypair)
+
     catalyst_simulator.out_swap_ack(source_swap_id)
 
     await verify_catalyst_state(swap_pool_program, swap_pool_state, catalyst_simulator)
@@ -522,7 +570,6 @@ async def test_self_cross_chain_swap_timeout(
         target_pool_id,
         swapper_output_asset_index,
         swapper_output_asset_wallet,
-        source_swap_id,  # Escrow nonce
         generic_payer,
         swap_pool_program,
         swap_pool_state,
@@ -572,27 +619,41 @@ async def test_self_cross_chain_swap_timeout(
 
     # Step 3: Trigger ibc timeout on source chain (simulates polymer chain endpoint contract)
     swap_escrow = cross_chain_swap_result.swap_escrow
-    ack_result = await swap_interface_program.rpc["on_timeout_packet"](
-        data.payload,
-        ctx=Context(
-            accounts={
-                "port_registration": port_registration,
-                "interface_state_account": swap_interface_state,
-                "swap_pool": swap_pool_state,
-                "swap_pool_program": swap_pool_program.program_id,
-                "swap_pool_authority": swap_pool_authority,
-                "token_program": TOKEN_PROGRAM_ID,
-                "system_program": SYS_PROGRAM_ID
-            },
-            remaining_accounts=[
-                AccountMeta(swapper_output_asset,          is_signer=False, is_writable=False ), # output_asset_mint
-                AccountMeta(swapper_output_asset_wallet,   is_signer=False, is_writable=True  ), # output_asset_wallet
-                AccountMeta(swap_pool_output_asset_wallet, is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
-                AccountMeta(swap_escrow,                   is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
-                AccountMeta(generic_payer.public_key,      is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
-            ]
+
+    async def timeout_swap(relayer_keypair: Keypair):
+        return await polymerase_receiver_program.rpc["on_timeout"](
+            swap_interface_state,
+            data.payload,
+            ctx=Context(
+                accounts={
+                    "port_registration": port_registration,
+                    "dapp_program": swap_interface_program.program_id,
+                    "authority": get_polymerase_authority(polymerase_receiver_program.program_id)[0],
+                    "receiver_config": get_polymerase_receiver_config(polymerase_receiver_program.program_id)[0],
+                    "relayer": relayer_keypair.public_key
+                },
+                remaining_accounts=[
+                    AccountMeta(swap_interface_state,          is_signer=False, is_writable=False ), # interface_state_account
+                    AccountMeta(swap_pool_state,               is_signer=False, is_writable=True  ), # swap_pool
+                    AccountMeta(swap_pool_program.program_id,  is_signer=False, is_writable=False ), # swap_pool_program
+                    AccountMeta(swap_pool_authority,           is_signer=False, is_writable=False ), # swap_pool_authority
+                    AccountMeta(TOKEN_PROGRAM_ID,              is_signer=False, is_writable=False ), # token_program
+                    AccountMeta(SYS_PROGRAM_ID,                is_signer=False, is_writable=False ), # system_program
+                    AccountMeta(swapper_output_asset,          is_signer=False, is_writable=False ), # output_asset_mint
+                    AccountMeta(swapper_output_asset_wallet,   is_signer=False, is_writable=True  ), # output_asset_wallet
+                    AccountMeta(swap_pool_output_asset_wallet, is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
+                    AccountMeta(swap_escrow,                   is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
+                    AccountMeta(generic_payer.public_key,      is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
+                ],
+                signers=[relayer_keypair]
+            )
         )
-    )
+
+    # Only the configured relayer may deliver timeouts
+    with pytest.raises(Exception):
+        await timeout_swap(Keypair())
+
+    ack_result = await timeout_swap(provider.wallet.payer)
     await confirm_transaction(provider, ack_result)
 
     catalyst_simulator.out_swap_timeout(source_swap_id)
@@ -610,3 +671,471 @@ async def test_self_cross_chain_swap_timeout(
     after_timeout_balance  = swapper_output_account_info_after_swap_timeout.amount
 
     assert before_balance == after_timeout_balance   # Timeout must return assets
+
+
+
+async def receive_asset_swap(
+    payload: bytes,
+    output_asset: PublicKey,
+    output_asset_wallet: PublicKey,
+    spc: SimplePoolContext,
+    swap_pool_program: Program,
+    swap_interface_program: Program,
+    polymerase_receiver_program: Program,
+    underwriter_asset_wallet: PublicKey | None = None,
+    underwrite_escrow_payer: PublicKey | None = None
+) -> Signature:
+    # Delivers an asset swap packet sent by the pool to itself (via the Solana 'channel')
+    provider = swap_pool_program.provider
+
+    swap_interface_state = spc.create_and_setup_swap_pool_result.initialize_swap_interface_result.swap_interface_state
+    output_asset_index   = spc.swap_pool_assets.index(output_asset)
+    assert spc.create_connection_result is not None
+
+    remaining_accounts = [
+        AccountMeta(swap_interface_state,              is_signer=False, is_writable=False ), # interface_state_account
+        AccountMeta(spc.swap_pool_state,               is_signer=False, is_writable=True  ), # swap_pool
+        AccountMeta(swap_pool_program.program_id,      is_signer=False, is_writable=False ), # swap_pool_program
+        AccountMeta(TOKEN_PROGRAM_ID,                  is_signer=False, is_writable=False ), # token_program
+        AccountMeta(get_channel_state_account(swap_interface_program.program_id, swap_interface_state, SOLANA_CHANNEL_ID)[0],
+                                                       is_signer=False, is_writable=False ), # channel_state_account
+        AccountMeta(spc.create_connection_result.connection_state,
+                                                       is_signer=False, is_writable=False ), # connection_state_account
+        AccountMeta(get_swap_interface_receipt(swap_interface_program.program_id, swap_interface_state, SOLANA_CHAIN_ID, payload)[0],
+                                                       is_signer=False, is_writable=True  ), # receipt
+        AccountMeta(provider.wallet.public_key,        is_signer=True,  is_writable=True  ), # receipt_rent_payer
+        AccountMeta(SYS_PROGRAM_ID,                    is_signer=False, is_writable=False ), # system_program
+        AccountMeta(output_asset,                      is_signer=False, is_writable=False ), # output_asset_mint
+        AccountMeta(output_asset_wallet,               is_signer=False, is_writable=True  ), # output_asset_wallet
+        AccountMeta(spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.swap_pool_asset_wallets[output_asset_index],
+                                                       is_signer=False, is_writable=True  ), # swap_pool_output_asset_wallet
+        AccountMeta(spc.swap_pool_authority,           is_signer=False, is_writable=False ), # swap_pool_authority
+        AccountMeta(get_swap_pool_underwrite_escrow(
+            swap_pool_program.program_id,
+            spc.swap_pool_state,
+            compute_payload_underwrite_identifier(SOLANA_CHAIN_ID, spc.swap_pool_state, payload)
+        )[0],                                          is_signer=False, is_writable=True  ), # underwrite_escrow
+    ]
+
+    # The underwriter accounts are only required if the swap has been underwritten
+    if underwriter_asset_wallet is not None and underwrite_escrow_payer is not None:
+        remaining_accounts.append(AccountMeta(underwriter_asset_wallet, is_signer=False, is_writable=True)) # underwriter_wallet
+        remaining_accounts.append(AccountMeta(underwrite_escrow_payer,  is_signer=False, is_writable=True)) # underwrite_escrow_rent_payer
+
+    tx = await polymerase_receiver_program.rpc["on_receive"](
+        swap_interface_state,
+        "",
+        "",
+        "",
+        "",
+        1,
+        payload,
+        0,
+        0,
+        ctx=Context(
+            accounts={
+                "port_registration": spc.create_and_setup_swap_pool_result.register_polymerase_port_result.polymerase_port,
+                "dapp_program": swap_interface_program.program_id,
+                "authority": get_polymerase_authority(polymerase_receiver_program.program_id)[0],
+                "receiver_config": get_polymerase_receiver_config(polymerase_receiver_program.program_id)[0],
+                "relayer": provider.wallet.public_key
+            },
+            remaining_accounts=remaining_accounts
+        )
+    )
+    await confirm_transaction(provider, tx)
+
+    return tx
+
+
+async def test_underwrite_cross_chain_swap(
+    swap_pool_program: Program,
+    swap_interface_program: Program,
+    polymerase_emulator_setup_master_keypair: Keypair,
+    polymerase_sender_program: Program,
+    polymerase_receiver_program: Program,
+    mint_authority: Keypair,
+    generic_payer: Keypair,
+    simple_pool_context_factory: FactoryFixture,
+):
+    provider = swap_pool_program.provider
+
+    spc = await simple_pool_context_factory(3, 2, [1000*10**6, 1000*10**6], [1, 1], None, True)
+
+    swap_pool_state     = spc.swap_pool_state
+    swap_pool_assets    = spc.swap_pool_assets
+    swap_pool_wallets   = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.swap_pool_asset_wallets
+    swap_interface_state = spc.create_and_setup_swap_pool_result.initialize_swap_interface_result.swap_interface_state
+
+    assert spc.create_connection_result is not None
+
+    swapper                     = spc.users[1]
+    swapper_input_asset         = swap_pool_assets[0]
+    swapper_input_asset_wallet  = swapper.token_accounts[0]
+    swapper_input_asset_balance = 10**6
+    swapper_output_asset        = swap_pool_assets[1]
+    swapper_output_asset_wallet = swapper.token_accounts[1]
+
+    underwriter              = spc.users[2]
+    underwriter_asset_wallet = underwriter.token_accounts[1]
+    underwriter_balance      = 10*10**6
+
+    underwrite_incentive_x16 = 2**16 // 100     # 1%
+
+    await mint_to(provider, swapper_input_asset_wallet, swapper_input_asset, mint_authority, swapper_input_asset_balance)
+    await mint_to(provider, underwriter_asset_wallet, swapper_output_asset, mint_authority, underwriter_balance)
+
+    cross_chain_swap_result = await perform_cross_chain_swap(
+        swapper_input_asset_balance,
+        swapper_input_asset,
+        swapper_input_asset_wallet,
+        swapper.user_keypair,
+        SOLANA_CHAIN_ID,
+        swap_interface_state,
+        1,
+        swapper_output_asset_wallet,
+        generic_payer,
+        swap_pool_program,
+        swap_pool_state,
+        swap_pool_wallets[0],
+        spc.swap_pool_authority,
+        swap_interface_program,
+        swap_interface_state,
+        spc.create_connection_result.connection_state,
+        polymerase_sender_program,
+        polymerase_emulator_setup_master_keypair, # Payer, can be anyone
+        underwrite_incentive_x16=underwrite_incentive_x16
+    )
+
+    payload = bytes((await polymerase_sender_program.account["IbcData"].fetch(cross_chain_swap_result.ibc_data)).payload)
+
+    async def underwrite(identifier: bytes | None = None) -> UnderwriteSwapResult:
+        return await underwrite_swap(
+            payload,
+            SOLANA_CHAIN_ID,
+            swapper_output_asset,
+            underwriter.user_keypair,
+            underwriter_asset_wallet,
+            generic_payer,
+            swap_pool_program,
+            swap_pool_state,
+            swap_pool_wallets[1],
+            identifier=identifier
+        )
+
+    # The identifier must match the one of the underwritten swap
+    with pytest.raises(Exception):
+        await underwrite(bytes(32))
+
+
+    # Underwrite the swap
+    underwrite_result = await underwrite()
+
+    underwritten_amount  = underwrite_result.underwrite_event.data.withdrawnAssetAmount
+    underwrite_incentive = underwrite_result.underwrite_event.data.underwriteIncentive
+    assert underwrite_incentive == (underwritten_amount * underwrite_incentive_x16) >> 16
+
+    # The user is paid by the underwriter, while the pool escrows the underwritten amount
+    swapper_output_info = await get_account_info(provider, swapper_output_asset, swapper_output_asset_wallet)
+    assert swapper_output_info.amount == underwritten_amount - underwrite_incentive
+
+    underwriter_info = await get_account_info(provider, swapper_output_asset, underwriter_asset_wallet)
+    assert underwriter_info.amount == underwriter_balance - (underwritten_amount - underwrite_incentive)
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.escrowed_assets[1] == underwritten_amount
+
+    # The same swap cannot be underwritten twice
+    with pytest.raises(Exception):
+        await underwrite()
+
+
+    # The arrival of the swap repays the underwriter (plus the incentive) instead of executing the swap. The underwrite
+    # is filled even while incoming swaps are paused.
+    dao_authority_keypair = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.dao_authority_keypair
+    await set_pause_flags(swap_pool_program, swap_pool_state, dao_authority_keypair, incoming_swaps=True)
+
+    async with TxEventListener("FillUnderwriteEvent") as ev_listener:
+        await receive_asset_swap(
+            payload,
+            swapper_output_asset,
+            swapper_output_asset_wallet,
+            spc,
+            swap_pool_program,
+            swap_interface_program,
+            polymerase_receiver_program,
+            underwriter_asset_wallet=underwriter_asset_wallet,
+            underwrite_escrow_payer=generic_payer.public_key
+        )
+
+        fill_underwrite_event = (await ev_listener.get_events(swap_pool_program))[0]
+        assert bytes(fill_underwrite_event.data.identifier) == underwrite_result.identifier
+
+    await set_pause_flags(swap_pool_program, swap_pool_state, dao_authority_keypair)
+
+    underwriter_info = await get_account_info(provider, swapper_output_asset, underwriter_asset_wallet)
+    assert underwriter_info.amount == underwriter_balance + underwrite_incentive
+
+    swapper_output_info = await get_account_info(provider, swapper_output_asset, swapper_output_asset_wallet)
+    assert swapper_output_info.amount == underwritten_amount - underwrite_incentive
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.escrowed_assets[1] == 0
+
+    # The underwrite escrow is closed
+    assert (await provider.connection.get_account_info(underwrite_result.underwrite_escrow)).value is None
+
+
+async def test_expire_underwrite(
+    swap_pool_program: Program,
+    swap_interface_program: Program,
+    polymerase_emulator_setup_master_keypair: Keypair,
+    polymerase_sender_program: Program,
+    polymerase_receiver_program: Program,
+    mint_authority: Keypair,
+    generic_payer: Keypair,
+    simple_pool_context_factory: FactoryFixture,
+):
+    provider = swap_pool_program.provider
+
+    spc = await simple_pool_context_factory(3, 2, [1000*10**6, 1000*10**6], [1, 1], None, True)
+
+    swap_pool_state     = spc.swap_pool_state
+    swap_pool_assets    = spc.swap_pool_assets
+    swap_pool_wallets   = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.swap_pool_asset_wallets
+    swap_interface_state = spc.create_and_setup_swap_pool_result.initialize_swap_interface_result.swap_interface_state
+
+    assert spc.create_connection_result is not None
+
+    swapper                     = spc.users[1]
+    swapper_input_asset         = swap_pool_assets[0]
+    swapper_input_asset_wallet  = swapper.token_accounts[0]
+    swapper_input_asset_balance = 10**6
+    swapper_output_asset        = swap_pool_assets[1]
+    swapper_output_asset_wallet = swapper.token_accounts[1]
+
+    underwriter              = spc.users[2]
+    underwriter_asset_wallet = underwriter.token_accounts[1]
+
+    await mint_to(provider, swapper_input_asset_wallet, swapper_input_asset, mint_authority, swapper_input_asset_balance)
+    await mint_to(provider, underwriter_asset_wallet, swapper_output_asset, mint_authority, 10*10**6)
+
+    cross_chain_swap_result = await perform_cross_chain_swap(
+        swapper_input_asset_balance,
+        swapper_input_asset,
+        swapper_input_asset_wallet,
+        swapper.user_keypair,
+        SOLANA_CHAIN_ID,
+        swap_interface_state,
+        1,
+        swapper_output_asset_wallet,
+        generic_payer,
+        swap_pool_program,
+        swap_pool_state,
+        swap_pool_wallets[0],
+        spc.swap_pool_authority,
+        swap_interface_program,
+        swap_interface_state,
+        spc.create_connection_result.connection_state,
+        polymerase_sender_program,
+        polymerase_emulator_setup_master_keypair # Payer, can be anyone
+    )
+
+    payload = bytes((await polymerase_sender_program.account["IbcData"].fetch(cross_chain_swap_result.ibc_data)).payload)
+
+    unit_tracker_before_underwrite = (await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)).unit_tracker_x64
+
+    underwrite_result = await underwrite_swap(
+        payload,
+        SOLANA_CHAIN_ID,
+        swapper_output_asset,
+        underwriter.user_keypair,
+        underwriter_asset_wallet,
+        generic_payer,
+        swap_pool_program,
+        swap_pool_state,
+        swap_pool_wallets[1]
+    )
+
+    swapper_output_balance = (await get_account_info(provider, swapper_output_asset, swapper_output_asset_wallet)).amount
+
+    # Only the underwriter may expire the underwrite before its expiry
+    with pytest.raises(Exception):
+        await expire_underwrite(
+            underwrite_result.identifier,
+            spc.users[1].user_keypair,
+            generic_payer.public_key,
+            swap_pool_program,
+            swap_pool_state
+        )
+
+    async with TxEventListener("ExpireUnderwriteEvent") as ev_listener:
+        await expire_underwrite(
+            underwrite_result.identifier,
+            underwriter.user_keypair,
+            generic_payer.public_key,
+            swap_pool_program,
+            swap_pool_state
+        )
+
+        expire_underwrite_event = (await ev_listener.get_events(swap_pool_program))[0]
+        assert bytes(expire_underwrite_event.data.identifier) == underwrite_result.identifier
+
+    # The escrowed assets are released and the units tracking reverted
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.escrowed_assets[1] == 0
+    assert swap_pool_state_data.unit_tracker_x64 == unit_tracker_before_underwrite
+
+    assert (await provider.connection.get_account_info(underwrite_result.underwrite_escrow)).value is None
+
+
+    # The swap is executed normally once it arrives
+    async with TxEventListener("InSwapEvent") as ev_listener:
+        await receive_asset_swap(
+            payload,
+            swapper_output_asset,
+            swapper_output_asset_wallet,
+            spc,
+            swap_pool_program,
+            swap_interface_program,
+            polymerase_receiver_program
+        )
+
+        in_swap_event = (await ev_listener.get_events(swap_pool_program))[0]
+
+    swapper_output_info = await get_account_info(provider, swapper_output_asset, swapper_output_asset_wallet)
+    assert swapper_output_info.amount == swapper_output_balance + in_swap_event.data.withdrawnAssetAmount
+
+
+async def test_calc_swaps(
+    swap_pool_program: Program,
+    swap_interface_program: Program,
+    polymerase_emulator_setup_master_keypair: Keypair,
+    polymerase_sender_program: Program,
+    polymerase_receiver_program: Program,
+    mint_authority: Keypair,
+    generic_payer: Keypair,
+    simple_pool_context_factory: FactoryFixture,
+):
+    provider = swap_pool_program.provider
+
+    spc = await simple_pool_context_factory(2, 2, [1000*10**6, 3000*10**6], [1, 1], None, True)
+
+    swap_pool_state      = spc.swap_pool_state
+    swap_pool_assets     = spc.swap_pool_assets
+    swap_pool_wallets    = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.swap_pool_asset_wallets
+    swap_interface_state = spc.create_and_setup_swap_pool_result.initialize_swap_interface_result.swap_interface_state
+
+    assert spc.create_connection_result is not None
+
+    swapper     = spc.users[1]
+    swap_amount = 10**6
+
+    await mint_to(provider, swapper.token_accounts[0], swap_pool_assets[0], mint_authority, 2*swap_amount)
+
+    # The quotes are returned as the instructions return data (little endian)
+    async def simulate_quote(instruction: str, args: list, accounts: dict) -> int:
+        result = await swap_pool_program.simulate[instruction](
+            *args,
+            ctx=Context(accounts={"swap_pool_state_account": swap_pool_state, **accounts})
+        )
+        return int.from_bytes(get_return_data(result.raw, swap_pool_program.program_id), 'little')
+
+
+    # Local swap
+    quoted_local_swap_output = await simulate_quote(
+        "calc_local_swap",
+        [swap_amount, False],
+        {
+            "input_asset_mint": swap_pool_assets[0],
+            "swap_pool_input_asset_wallet": swap_pool_wallets[0],
+            "output_asset_mint": swap_pool_assets[1],
+            "swap_pool_output_asset_wallet": swap_pool_wallets[1]
+        }
+    )
+
+    swapper_output_balance_before = (await get_account_info(provider, swap_pool_assets[1], swapper.token_accounts[1])).amount
+
+    await perform_local_swap(
+        swap_amount,
+        0,
+        False, # approx
+        swap_pool_assets[0],
+        swapper.token_accounts[0],
+        swapper.user_keypair,
+        swap_pool_assets[1],
+        swapper.token_accounts[1],
+        swap_pool_program,
+        swap_pool_state,
+        swap_pool_wallets[0],
+        swap_pool_wallets[1],
+        spc.swap_pool_authority
+    )
+
+    swapper_output_balance_after = (await get_account_info(provider, swap_pool_assets[1], swapper.token_accounts[1])).amount
+    assert quoted_local_swap_output > 0
+    assert swapper_output_balance_after - swapper_output_balance_before == quoted_local_swap_output
+
+
+    # Send asset (out_swap)
+    quoted_units_x64 = await simulate_quote(
+        "calc_send_asset",
+        [swap_amount, False],
+        {
+            "input_asset_mint": swap_pool_assets[0],
+            "swap_pool_input_asset_wallet": swap_pool_wallets[0]
+        }
+    )
+
+    cross_chain_swap_result = await perform_cross_chain_swap(
+        swap_amount,
+        swap_pool_assets[0],
+        swapper.token_accounts[0],
+        swapper.user_keypair,
+        SOLANA_CHAIN_ID,
+        swap_interface_state,
+        1,
+        swapper.token_accounts[1],
+        generic_payer,
+        swap_pool_program,
+        swap_pool_state,
+        swap_pool_wallets[0],
+        spc.swap_pool_authority,
+        swap_interface_program,
+        swap_interface_state,
+        spc.create_connection_result.connection_state,
+        polymerase_sender_program,
+        polymerase_emulator_setup_master_keypair # Payer, can be anyone
+    )
+
+    assert quoted_units_x64 > 0
+    assert u256_array_to_int(cross_chain_swap_result.out_swap_event.data.withdrawnPoolUnitsX64) == quoted_units_x64
+
+
+    # Receive asset (in_swap)
+    quoted_receive_output = await simulate_quote(
+        "calc_receive_asset",
+        [int_to_u256_array(quoted_units_x64), False],
+        {
+            "output_asset_mint": swap_pool_assets[1],
+            "swap_pool_output_asset_wallet": swap_pool_wallets[1]
+        }
+    )
+
+    payload = bytes((await polymerase_sender_program.account["IbcData"].fetch(cross_chain_swap_result.ibc_data)).payload)
+
+    swapper_output_balance_before = (await get_account_info(provider, swap_pool_assets[1], swapper.token_accounts[1])).amount
+
+    await receive_asset_swap(
+        payload,
+        swap_pool_assets[1],
+        swapper.token_accounts[1],
+        spc,
+        swap_pool_program,
+        swap_interface_program,
+        polymerase_receiver_program
+    )
+
+    swapper_output_balance_after = (await get_account_info(provider, swap_pool_assets[1], swapper.token_accounts[1])).amount
+    assert quoted_receive_output > 0
+    assert swapper_output_balance_after - swapper_output_balance_before == quoted_receive_output
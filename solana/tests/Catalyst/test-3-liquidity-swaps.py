@@ -1,3 +1,4 @@
+import pytest
 from solana.keypair import Keypair
 from solana.transaction import AccountMeta
 
@@ -5,9 +6,11 @@ from anchorpy import Context, Program
 from conftest import FactoryFixture
 from utils.swap_pool_utils import create_connection
 from utils.common_utils import SOLANA_CHAIN_ID
+from utils.swap_interface_utils import SOLANA_CHANNEL_ID, get_channel_state_account
+from utils.account_utils import compute_liquidity_escrow_id, get_polymerase_authority, get_polymerase_receiver_config, get_swap_interface_receipt, get_swap_pool_liquidity_escrow
 from utils.transaction_utils import TxEventListener, confirm_transaction
 from utils.token_utils import approve, get_account_info
-from utils.verify_utils import verify_catalyst_state, verify_in_liquidity_swap_event, verify_out_liquidity_swap_event
+from utils.verify_utils import int_to_u256_array, verify_catalyst_state, verify_in_liquidity_swap_event, verify_out_liquidity_swap_event
 from spl.token.constants import TOKEN_PROGRAM_ID
 
 from solana.system_program import SYS_PROGRAM_ID
@@ -58,6 +61,7 @@ async def test_liquidity_swap_with_self(
 
     assert simple_pool_context.create_connection_result is not None
     connection_state = simple_pool_context.create_connection_result.connection_state
+    channel_state    = get_channel_state_account(swap_interface_program.program_id, swap_interface_state, SOLANA_CHANNEL_ID)[0]
 
 
     # Perform cross-chain liquidity swap (first part): OutLiquiditySwap => CrossChainLiquiditySwap => CallMultichain
@@ -87,6 +91,14 @@ async def test_liquidity_swap_with_self(
 
     polymerase_ibc_data_account_keypair = Keypair() 
 
+    # The liquidity escrow is derived from the swap contents (including the simulated swap units) and a recent slot
+    block_number = (await provider.connection.get_slot()).value % 2**32
+    liquidity_escrow = get_swap_pool_liquidity_escrow(
+        swap_pool_program.program_id,
+        swap_pool_state,
+        compute_liquidity_escrow_id(liquidity_provider_pool_token_wallet, sim_liquidity_units_x64, swap_amount, block_number)
+    )[0]
+
     # OutLiquiditySwapEvent
     async with TxEventListener("OutLiquiditySwapEvent") as ev_listener:
         tx = await swap_pool_program.rpc["out_liquidity_swap"](
@@ -94,12 +106,17 @@ async def test_liquidity_swap_with_self(
             target_pool_id,
             liquidity_provider_pool_token_wallet,
             swap_amount,
+            int_to_u256_array(0),   # min_pool_tokens
+            int_to_u256_array(0),   # min_reference_asset
+            block_number,
             ctx=Context(
                 accounts={
                     "swap_pool_state_account": swap_pool_state,
                     "liquidity_provider_pool_token_wallet": liquidity_provider_pool_token_wallet,
                     "swap_pool_token_mint": swap_pool_token_mint,
                     "token_program": TOKEN_PROGRAM_ID,
+                    "liquidity_escrow": liquidity_escrow,
+                    "liquidity_escrow_rent_payer": polymerase_emulator_setup_master_keypair.public_key,
                     "ibc_interface_program": swap_interface_program.program_id,
                     "swap_pool_authority": swap_pool_authority,
                     "interface_state_account": swap_interface_state,
@@ -153,7 +170,14 @@ async def test_liquidity_swap_with_self(
     # Fetch payload
     data = await polymerase_sender_program.account["IbcData"].fetch(polymerase_ibc_data_account_keypair.public_key)
 
-    authority = Keypair() #not used for now
+    authority = get_polymerase_authority(polymerase_receiver_program.program_id)[0]
+
+    receipt = get_swap_interface_receipt(
+        swap_interface_program.program_id,
+        swap_interface_state,
+        target_chain_id,
+        bytes(data.payload)
+    )[0]
 
     async with TxEventListener("InLiquiditySwapEvent") as ev_listener:
         rpc_result = await polymerase_receiver_program.rpc["on_receive"](
@@ -170,19 +194,24 @@ async def test_liquidity_swap_with_self(
                 accounts={
                     "port_registration": port_registration,
                     "dapp_program": target_program_id,
-                    "authority": authority.public_key
+                    "authority": authority,
+                    "receiver_config": get_polymerase_receiver_config(polymerase_receiver_program.program_id)[0],
+                    "relayer": provider.wallet.public_key
                 },
                 remaining_accounts=[
                     AccountMeta(swap_interface_state,          is_signer=False, is_writable=False ), # interface_state_account
                     AccountMeta(swap_pool_state,               is_signer=False, is_writable=True  ), # swap_pool
                     AccountMeta(swap_pool_program.program_id,  is_signer=False, is_writable=False ), # swap_pool_program
                     AccountMeta(TOKEN_PROGRAM_ID,              is_signer=False, is_writable=False ), # token_program
+                    AccountMeta(channel_state,                 is_signer=False, is_writable=False ), # channel_state_account
                     AccountMeta(connection_state,              is_signer=False, is_writable=False ), # connection_state_account
+                    AccountMeta(receipt,                       is_signer=False, is_writable=True  ), # receipt
+                    AccountMeta(provider.wallet.public_key,    is_signer=True,  is_writable=True  ), # receipt_rent_payer
+                    AccountMeta(SYS_PROGRAM_ID,                is_signer=False, is_writable=False ), # system_program
                     AccountMeta(swap_pool_token_mint,          is_signer=False, is_writable=True ), # output_asset_mint
                     AccountMeta(liquidity_provider_pool_token_wallet,   is_signer=False, is_writable=True  ), # output_asset_wallet
                     AccountMeta(swap_pool_authority,           is_signer=False, is_writable=False ), # swap_pool_authority
-                ],
-                signers=[authority]
+                ]
             )
         )
         await confirm_transaction(provider, rpc_result)
@@ -222,7 +251,10 @@ async def test_liquidity_swap_with_self(
 
 
 
+@pytest.mark.parametrize("min_reference_asset, min_reference_asset_fulfilled", [(0, True), (10**15, False)])
 async def test_liquidity_swap(
+    min_reference_asset: int,
+    min_reference_asset_fulfilled: bool,
     swap_pool_program: Program,
     swap_interface_program: Program,
     polymerase_emulator_setup_master_keypair: Keypair,
@@ -324,6 +356,7 @@ async def test_liquidity_swap(
         rent_payer_keypair     = generic_payer
     )
     connection_state_2 = create_connection_result_2.connection_state
+    channel_state_2    = get_channel_state_account(swap_interface_program.program_id, swap_interface_state_2, SOLANA_CHANNEL_ID)[0]
 
 
     # Perform cross-chain liquidity swap (first part): OutLiquiditySwap => CrossChainLiquiditySwap => CallMultichain
@@ -351,6 +384,14 @@ async def test_liquidity_swap(
 
     polymerase_ibc_data_account_keypair = Keypair() 
 
+    # The liquidity escrow is derived from the swap contents (including the simulated swap units) and a recent slot
+    block_number = (await provider.connection.get_slot()).value % 2**32
+    liquidity_escrow = get_swap_pool_liquidity_escrow(
+        swap_pool_program.program_id,
+        swap_pool_state_1,
+        compute_liquidity_escrow_id(liquidity_provider_pool_token_wallet_2, sim_liquidity_units_x64, swap_amount, block_number)
+    )[0]
+
     # OutLiquiditySwapEvent
     async with TxEventListener("OutLiquiditySwapEvent") as ev_listener:
         tx = await swap_pool_program.rpc["out_liquidity_swap"](
@@ -358,12 +399,17 @@ async def test_liquidity_swap(
             target_pool_id,
             liquidity_provider_pool_token_wallet_2,
             swap_amount,
+            int_to_u256_array(0),   # min_pool_tokens
+            int_to_u256_array(min_reference_asset),
+            block_number,
             ctx=Context(
                 accounts={
                     "swap_pool_state_account": swap_pool_state_1,
                     "liquidity_provider_pool_token_wallet": liquidity_provider_pool_token_wallet_1,
                     "swap_pool_token_mint": swap_pool_token_mint_1,
                     "token_program": TOKEN_PROGRAM_ID,
+                    "liquidity_escrow": liquidity_escrow,
+                    "liquidity_escrow_rent_payer": polymerase_emulator_setup_master_keypair.public_key,
                     "ibc_interface_program": swap_interface_program.program_id,
                     "swap_pool_authority": swap_pool_authority_1,
                     "interface_state_account": swap_interface_state_1,
@@ -417,10 +463,17 @@ async def test_liquidity_swap(
     # Fetch payload
     data = await polymerase_sender_program.account["IbcData"].fetch(polymerase_ibc_data_account_keypair.public_key)
 
-    authority = Keypair() #not used for now
+    authority = get_polymerase_authority(polymerase_receiver_program.program_id)[0]
 
-    async with TxEventListener("InLiquiditySwapEvent") as ev_listener:
-        rpc_result = await polymerase_receiver_program.rpc["on_receive"](
+    receipt = get_swap_interface_receipt(
+        swap_interface_program.program_id,
+        swap_interface_state_2,
+        target_chain_id,
+        bytes(data.payload)
+    )[0]
+
+    async def receive_liquidity_swap():
+        return await polymerase_receiver_program.rpc["on_receive"](
             swap_interface_state_2,
             "",
             "",
@@ -434,21 +487,35 @@ async def test_liquidity_swap(
                 accounts={
                     "port_registration": port_registration_2,
                     "dapp_program": target_program_id,
-                    "authority": authority.public_key
+                    "authority": authority,
+                    "receiver_config": get_polymerase_receiver_config(polymerase_receiver_program.program_id)[0],
+                    "relayer": provider.wallet.public_key
                 },
                 remaining_accounts=[
                     AccountMeta(swap_interface_state_2,                  is_signer=False, is_writable=False ), # interface_state_account
                     AccountMeta(swap_pool_state_2,                       is_signer=False, is_writable=True  ), # swap_pool
                     AccountMeta(swap_pool_program.program_id,            is_signer=False, is_writable=False ), # swap_pool_program
                     AccountMeta(TOKEN_PROGRAM_ID,                        is_signer=False, is_writable=False ), # token_program
+                    AccountMeta(channel_state_2,                         is_signer=False, is_writable=False ), # channel_state_account
                     AccountMeta(connection_state_2,                      is_signer=False, is_writable=False ), # connection_state_account
+                    AccountMeta(receipt,                                 is_signer=False, is_writable=True  ), # receipt
+                    AccountMeta(provider.wallet.public_key,              is_signer=True,  is_writable=True  ), # receipt_rent_payer
+                    AccountMeta(SYS_PROGRAM_ID,                          is_signer=False, is_writable=False ), # system_program
                     AccountMeta(swap_pool_token_mint_2,                  is_signer=False, is_writable=True ),  # output_asset_mint
                     AccountMeta(liquidity_provider_pool_token_wallet_2,  is_signer=False, is_writable=True  ), # output_asset_wallet
                     AccountMeta(swap_pool_authority_2,                   is_signer=False, is_writable=False ), # swap_pool_authority
-                ],
-                signers=[authority]
+                ]
             )
         )
+
+    # The reference asset value of the received pool tokens is below the required minimum
+    if not min_reference_asset_fulfilled:
+        with pytest.raises(Exception):
+            await receive_liquidity_swap()
+        return
+
+    async with TxEventListener("InLiquiditySwapEvent") as ev_listener:
+        rpc_result = await receive_liquidity_swap()
         await confirm_transaction(provider, rpc_result)
 
         # Verify the event
@@ -0,0 +1,548 @@
+import asyncio
+import time
+import pytest
+from solana.keypair import Keypair
+from solana.publickey import PublicKey
+from solana.transaction import AccountMeta
+from spl.token.constants import TOKEN_PROGRAM_ID
+
+from anchorpy import Context, Program, Provider
+from conftest import FactoryFixture
+from utils.account_utils import get_swap_pool_authority
+from utils.common_utils import create_mints, create_users
+from utils.swap_pool_utils import initialize_swap_pool_state, perform_local_swap
+from utils.token_utils import create_token_account, fund_accounts, get_account_info, mint_to
+from utils.transaction_utils import TxEventListener, confirm_transaction
+from utils.verify_utils import int_to_u256_array, u256_array_to_int
+
+
+MAX_VAULT_FEE_X64 = 1844674407370955161     # 10%, must match the SwapPool program
+
+
+async def test_vault_fee_cap(
+    swap_pool_program: Program,
+    simple_pool_context_factory: FactoryFixture
+):
+    spc = await simple_pool_context_factory(2, 2, [1000, 3000], [1, 1], None, False)
+
+    swap_pool_state       = spc.swap_pool_state
+    dao_authority_keypair = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.dao_authority_keypair
+
+    async def set_vault_fee(vault_fee_x64: int, authority_keypair: Keypair):
+        await swap_pool_program.rpc["set_vault_fee"](
+            vault_fee_x64,
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "dao_authority": authority_keypair.public_key
+                },
+                signers=[authority_keypair]
+            )
+        )
+
+    # Only the dao authority may set the fee
+    with pytest.raises(Exception):
+        await set_vault_fee(MAX_VAULT_FEE_X64, Keypair())
+
+    # The fee cannot exceed the maximum
+    with pytest.raises(Exception):
+        await set_vault_fee(MAX_VAULT_FEE_X64 + 1, dao_authority_keypair)
+
+    await set_vault_fee(MAX_VAULT_FEE_X64, dao_authority_keypair)
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.vault_fee_x64 == MAX_VAULT_FEE_X64
+
+
+MIN_RECEIPT_RECLAIM_DELAY = 2 * 60 * 60 * 24 * 7    # Twice the maximum packet validity, must match the IBCInterface program
+
+
+async def test_receipt_reclaim_delay_minimum(
+    swap_interface_program: Program,
+    swap_interface_setup_master_keypair: Keypair,
+    simple_pool_context_factory: FactoryFixture
+):
+    spc = await simple_pool_context_factory(2, 2, [1000, 3000], [1, 1], None, False)
+
+    swap_interface_state = spc.create_and_setup_swap_pool_result.initialize_swap_interface_result.swap_interface_state
+
+    async def set_receipt_reclaim_delay(receipt_reclaim_delay: int):
+        await swap_interface_program.rpc["set_receipt_reclaim_delay"](
+            receipt_reclaim_delay,
+            ctx=Context(
+                accounts={
+                    "interface_state_account": swap_interface_state,
+                    "configurator": swap_interface_setup_master_keypair.public_key
+                },
+                signers=[swap_interface_setup_master_keypair]
+            )
+        )
+
+    # The delay must be longer than the maximum packet validity
+    with pytest.raises(Exception):
+        await set_receipt_reclaim_delay(MIN_RECEIPT_RECLAIM_DELAY - 1)
+
+    await set_receipt_reclaim_delay(MIN_RECEIPT_RECLAIM_DELAY)
+
+    swap_interface_state_data = await swap_interface_program.account["CrossChainSwapInterfaceState"].fetch(swap_interface_state)
+    assert swap_interface_state_data.receipt_reclaim_delay == MIN_RECEIPT_RECLAIM_DELAY
+
+
+MIN_WEIGHTS_ADJUSTMENT_TIME = 60*60*24*7      # Must match the SwapPool program
+MAX_WEIGHTS_ADJUSTMENT_TIME = 60*60*24*365    # Must match the SwapPool program
+
+
+async def test_set_weights(
+    swap_pool_program: Program,
+    simple_pool_context_factory: FactoryFixture
+):
+    initial_weights = [10**6, 10**6]
+
+    spc = await simple_pool_context_factory(2, 2, [1000, 3000], initial_weights, None, False)
+
+    swap_pool_state       = spc.swap_pool_state
+    dao_authority_keypair = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.dao_authority_keypair
+
+    async def set_weights(target_timestamp: int, target_weights: list[int], authority_keypair: Keypair = dao_authority_keypair):
+        tx = await swap_pool_program.rpc["set_weights"](
+            target_timestamp,
+            target_weights,
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "dao_authority": authority_keypair.public_key
+                },
+                signers=[authority_keypair]
+            )
+        )
+        await confirm_transaction(swap_pool_program.provider, tx)
+
+    now = int(time.time())
+    target_timestamp = now + MIN_WEIGHTS_ADJUSTMENT_TIME + 60*60
+    target_weights   = [10 * initial_weights[0], initial_weights[1] // 2]
+
+    # Only the dao authority may set the weights
+    with pytest.raises(Exception):
+        await set_weights(target_timestamp, target_weights, Keypair())
+
+    # The adjustment duration must be within the allowed bounds
+    with pytest.raises(Exception):
+        await set_weights(now + MIN_WEIGHTS_ADJUSTMENT_TIME - 60*60, target_weights)
+
+    with pytest.raises(Exception):
+        await set_weights(now + MAX_WEIGHTS_ADJUSTMENT_TIME + 60*60, target_weights)
+
+    # A target weight must be given for every asset
+    with pytest.raises(Exception):
+        await set_weights(target_timestamp, target_weights[:1])
+
+    # The weights must be non-zero and may change at most by a factor of 10
+    for invalid_target_weights in [
+        [0, initial_weights[1]],
+        [10 * initial_weights[0] + 1, initial_weights[1]],
+        [initial_weights[0], initial_weights[1] // 10 - 1]
+    ]:
+        with pytest.raises(Exception):
+            await set_weights(target_timestamp, invalid_target_weights)
+
+
+    await set_weights(target_timestamp, target_weights)
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.target_weights[:2] == target_weights
+    assert swap_pool_state_data.weights_adjustment_target_timestamp == target_timestamp
+    assert swap_pool_state_data.pool_assets_weights[:2] == initial_weights     # The weights are updated over time
+
+    start_timestamp = swap_pool_state_data.weights_last_modification_timestamp
+
+
+    # The weights are linearly interpolated towards the target ones (the update is applied before overriding the
+    # ongoing adjustment)
+    await asyncio.sleep(5)
+
+    await set_weights(target_timestamp, target_weights)
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    update_timestamp = swap_pool_state_data.weights_last_modification_timestamp
+    assert update_timestamp > start_timestamp
+
+    expected_weights = [
+        initial_weights[0] + (target_weights[0] - initial_weights[0]) * (update_timestamp - start_timestamp) // (target_timestamp - start_timestamp),
+        initial_weights[1] - (initial_weights[1] - target_weights[1]) * (update_timestamp - start_timestamp) // (target_timestamp - start_timestamp)
+    ]
+    assert swap_pool_state_data.pool_assets_weights[:2] == expected_weights
+    assert expected_weights[0] > initial_weights[0] and expected_weights[1] < initial_weights[1]
+
+    # The security limit follows the weights
+    assert u256_array_to_int(swap_pool_state_data.max_units_inflow_x64) == sum(expected_weights) << 64
+
+
+MIN_AMPLIFICATION_ADJUSTMENT_TIME = 60*60*24*7      # Must match the SwapPoolAmplified program
+MAX_AMPLIFICATION_ADJUSTMENT_TIME = 60*60*24*365    # Must match the SwapPoolAmplified program
+MAX_AMPLIFICATION_CHANGE_FACTOR   = 10              # Must match the SwapPoolAmplified program
+
+
+async def test_set_amplification(
+    swap_pool_amplified_program: Program,
+    swap_pool_setup_master_keypair: Keypair,
+    mint_authority: Keypair,
+    provider: Provider,
+    generic_payer: Keypair
+):
+    swap_pool_state_keypair = Keypair()
+    swap_pool_state         = swap_pool_state_keypair.public_key
+
+    swap_pool_authority = get_swap_pool_authority(
+        swap_pool_amplified_program.program_id,
+        swap_pool_state
+    )[0]
+
+    # Create a new amplified pool (the setup does not need to be finished to adjust the amplification)
+    k                  = 20     # amplification = 1/k
+    assets             = await create_mints(provider, mint_authority, 2)
+    creator            = (await create_users(provider, assets, generic_payer, 1))[0]
+    init_asset_amounts = [1000*10**6, 1000*10**6]
+
+    await fund_accounts(
+        provider                 = provider,
+        mints                    = assets,
+        mints_authority          = mint_authority,
+        token_accounts           = creator.token_accounts,
+        balance                  = init_asset_amounts,
+        delegate_authority       = swap_pool_authority,
+        delegate_balance         = init_asset_amounts,
+        token_accounts_authority = creator.user_keypair
+    )
+
+    initialize_swap_pool_state_result = await initialize_swap_pool_state(
+        swap_pool_amplified_program,
+        swap_pool_setup_master_keypair,
+        k,
+        [1, 1],
+        init_asset_amounts,
+        assets,
+        creator.token_accounts,
+        creator.user_keypair.public_key,
+        swap_pool_state_keypair=swap_pool_state_keypair
+    )
+    dao_authority_keypair = initialize_swap_pool_state_result.dao_authority_keypair
+
+    async def set_amplification(target_timestamp: int, target_amplification_x64: int, authority_keypair: Keypair = dao_authority_keypair):
+        tx = await swap_pool_amplified_program.rpc["set_amplification"](
+            target_timestamp,
+            int_to_u256_array(target_amplification_x64),
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "dao_authority": authority_keypair.public_key
+                },
+                signers=[authority_keypair]
+            )
+        )
+        await confirm_transaction(provider, tx)
+
+    initial_amplification_x64 = 2**64 // k
+
+    swap_pool_state_data = await swap_pool_amplified_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert u256_array_to_int(swap_pool_state_data.amplification_x64) == initial_amplification_x64
+
+    now = int(time.time())
+    target_timestamp         = now + MIN_AMPLIFICATION_ADJUSTMENT_TIME + 60*60
+    target_amplification_x64 = 4 * initial_amplification_x64
+
+    # Only the dao authority may set the amplification
+    with pytest.raises(Exception):
+        await set_amplification(target_timestamp, target_amplification_x64, Keypair())
+
+    # The adjustment duration must be within the allowed bounds
+    with pytest.raises(Exception):
+        await set_amplification(now + MIN_AMPLIFICATION_ADJUSTMENT_TIME - 60*60, target_amplification_x64)
+
+    with pytest.raises(Exception):
+        await set_amplification(now + MAX_AMPLIFICATION_ADJUSTMENT_TIME + 60*60, target_amplification_x64)
+
+    # The amplification must be within (0, 1), and may change at most by MAX_AMPLIFICATION_CHANGE_FACTOR
+    for invalid_target_amplification_x64 in [
+        0,
+        2**64,
+        MAX_AMPLIFICATION_CHANGE_FACTOR * initial_amplification_x64 + 1,
+        initial_amplification_x64 // MAX_AMPLIFICATION_CHANGE_FACTOR - 1
+    ]:
+        with pytest.raises(Exception):
+            await set_amplification(target_timestamp, invalid_target_amplification_x64)
+
+
+    await set_amplification(target_timestamp, target_amplification_x64)
+
+    swap_pool_state_data = await swap_pool_amplified_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert u256_array_to_int(swap_pool_state_data.target_amplification_x64) == target_amplification_x64
+    assert swap_pool_state_data.amplification_adjustment_target_timestamp == target_timestamp
+    assert u256_array_to_int(swap_pool_state_data.amplification_x64) == initial_amplification_x64    # Updated over time
+
+    start_timestamp = swap_pool_state_data.amplification_last_modification_timestamp
+
+
+    # The amplification is linearly interpolated towards the target one (the update is applied before overriding the
+    # ongoing adjustment)
+    await asyncio.sleep(5)
+
+    await set_amplification(target_timestamp, target_amplification_x64)
+
+    swap_pool_state_data = await swap_pool_amplified_program.account["SwapPoolState"].fetch(swap_pool_state)
+    update_timestamp = swap_pool_state_data.amplification_last_modification_timestamp
+    assert update_timestamp > start_timestamp
+
+    expected_amplification_x64 = initial_amplification_x64 + (
+        (target_amplification_x64 - initial_amplification_x64) * (update_timestamp - start_timestamp) // (target_timestamp - start_timestamp)
+    )
+    assert u256_array_to_int(swap_pool_state_data.amplification_x64) == expected_amplification_x64
+    assert expected_amplification_x64 > initial_amplification_x64
+
+
+async def test_dao_authority_handover(
+    swap_pool_program: Program,
+    simple_pool_context_factory: FactoryFixture
+):
+    spc = await simple_pool_context_factory(2, 2, [1000, 3000], [1, 1], None, False)
+
+    swap_pool_state       = spc.swap_pool_state
+    dao_authority_keypair = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result.dao_authority_keypair
+    new_authority_keypair = Keypair()
+
+    async def propose_dao_authority(pending_dao_authority: PublicKey, authority_keypair: Keypair):
+        tx = await swap_pool_program.rpc["propose_dao_authority"](
+            pending_dao_authority,
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "dao_authority": authority_keypair.public_key
+                },
+                signers=[authority_keypair]
+            )
+        )
+        await confirm_transaction(swap_pool_program.provider, tx)
+
+    async def accept_dao_authority(pending_authority_keypair: Keypair):
+        tx = await swap_pool_program.rpc["accept_dao_authority"](
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "pending_dao_authority": pending_authority_keypair.public_key
+                },
+                signers=[pending_authority_keypair]
+            )
+        )
+        await confirm_transaction(swap_pool_program.provider, tx)
+
+    # Only the dao authority may propose a new authority
+    with pytest.raises(Exception):
+        await propose_dao_authority(new_authority_keypair.public_key, new_authority_keypair)
+
+    await propose_dao_authority(new_authority_keypair.public_key, dao_authority_keypair)
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.pending_dao_authority == new_authority_keypair.public_key
+    assert swap_pool_state_data.dao_authority == dao_authority_keypair.public_key
+
+    # Only the pending authority may accept the handover
+    with pytest.raises(Exception):
+        await accept_dao_authority(Keypair())
+
+
+    # Cancel the handover by proposing the default (empty) pubkey
+    await propose_dao_authority(PublicKey(0), dao_authority_keypair)
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.pending_dao_authority == PublicKey(0)
+
+    with pytest.raises(Exception):
+        await accept_dao_authority(new_authority_keypair)
+
+
+    # Complete the handover
+    await propose_dao_authority(new_authority_keypair.public_key, dao_authority_keypair)
+    await accept_dao_authority(new_authority_keypair)
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.dao_authority == new_authority_keypair.public_key
+    assert swap_pool_state_data.pending_dao_authority == PublicKey(0)
+
+    # The handover cannot be accepted twice
+    with pytest.raises(Exception):
+        await accept_dao_authority(new_authority_keypair)
+
+    # The previous authority is revoked
+    with pytest.raises(Exception):
+        await propose_dao_authority(dao_authority_keypair.public_key, dao_authority_keypair)
+
+    await propose_dao_authority(dao_authority_keypair.public_key, new_authority_keypair)
+
+
+
+MAX_GOVERNANCE_FEE_SHARE_X64 = 13835058055282163712     # 75%, must match the SwapPool program
+
+
+async def test_governance_fees(
+    swap_pool_program: Program,
+    mint_authority: Keypair,
+    simple_pool_context_factory: FactoryFixture
+):
+    provider = swap_pool_program.provider
+
+    spc = await simple_pool_context_factory(2, 2, [1000*10**10, 1000*10**10], [1, 1], None, False)
+
+    swap_pool_state       = spc.swap_pool_state
+    swap_pool_authority   = spc.swap_pool_authority
+    swap_pool_assets      = spc.swap_pool_assets
+    swapper               = spc.users[1]
+
+    initialize_swap_pool_state_result = spc.create_and_setup_swap_pool_result.initialize_swap_pool_state_result
+    dao_authority_keypair             = initialize_swap_pool_state_result.dao_authority_keypair
+    swap_pool_asset_wallets           = initialize_swap_pool_state_result.swap_pool_asset_wallets
+
+    vault_fee_x64            = MAX_VAULT_FEE_X64
+    governance_fee_share_x64 = MAX_GOVERNANCE_FEE_SHARE_X64
+    swap_amount              = 5000000000
+
+    governance_fee_destination = Keypair().public_key
+    destination_asset_wallets  = [
+        await create_token_account(provider, asset, governance_fee_destination) for asset in swap_pool_assets
+    ]
+
+    async def set_governance_fee(governance_fee_share_x64: int, authority_keypair: Keypair):
+        tx = await swap_pool_program.rpc["set_governance_fee"](
+            governance_fee_share_x64,
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "dao_authority": authority_keypair.public_key
+                },
+                signers=[authority_keypair]
+            )
+        )
+        await confirm_transaction(provider, tx)
+
+    async def collect_governance_fees(destination_asset_wallets: list[PublicKey]):
+        remaining_accounts = []
+        for swap_pool_asset_wallet, destination_asset_wallet in zip(swap_pool_asset_wallets, destination_asset_wallets):
+            remaining_accounts.append(AccountMeta(swap_pool_asset_wallet, is_signer=False, is_writable=True))
+            remaining_accounts.append(AccountMeta(destination_asset_wallet, is_signer=False, is_writable=True))
+
+        tx = await swap_pool_program.rpc["collect_governance_fees"](
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "swap_pool_authority": swap_pool_authority,
+                    "token_program": TOKEN_PROGRAM_ID
+                },
+                remaining_accounts=remaining_accounts
+            )
+        )
+        await confirm_transaction(provider, tx)
+
+
+    # Only the dao authority may set the governance fee
+    with pytest.raises(Exception):
+        await set_governance_fee(governance_fee_share_x64, Keypair())
+
+    # The governance fee share cannot exceed the maximum
+    with pytest.raises(Exception):
+        await set_governance_fee(MAX_GOVERNANCE_FEE_SHARE_X64 + 1, dao_authority_keypair)
+
+    await set_governance_fee(governance_fee_share_x64, dao_authority_keypair)
+
+    tx = await swap_pool_program.rpc["set_vault_fee"](
+        vault_fee_x64,
+        ctx=Context(
+            accounts={
+                "swap_pool_state_account": swap_pool_state,
+                "dao_authority": dao_authority_keypair.public_key
+            },
+            signers=[dao_authority_keypair]
+        )
+    )
+    await confirm_transaction(provider, tx)
+
+
+    # Accrue the governance fee with a local swap
+    await mint_to(provider, swapper.token_accounts[0], swap_pool_assets[0], mint_authority, swap_amount)
+
+    await perform_local_swap(
+        swap_amount,
+        0,
+        False, # approx
+        swap_pool_assets[0],
+        swapper.token_accounts[0],
+        swapper.user_keypair,
+        swap_pool_assets[1],
+        swapper.token_accounts[1],
+        swap_pool_program,
+        swap_pool_state,
+        swap_pool_asset_wallets[0],
+        swap_pool_asset_wallets[1],
+        swap_pool_authority
+    )
+
+    vault_fee               = (swap_amount * vault_fee_x64) >> 64
+    expected_governance_fee = (vault_fee * governance_fee_share_x64) >> 64
+    assert expected_governance_fee > 0
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.accrued_governance_fees[0] == expected_governance_fee
+    assert swap_pool_state_data.accrued_governance_fees[1] == 0
+
+
+    # The fees cannot be collected until a destination is set
+    with pytest.raises(Exception):
+        await collect_governance_fees(destination_asset_wallets)
+
+    # Only the dao authority may set the destination
+    with pytest.raises(Exception):
+        tx = await swap_pool_program.rpc["set_governance_fee_destination"](
+            governance_fee_destination,
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "dao_authority": swapper.user_keypair.public_key
+                },
+                signers=[swapper.user_keypair]
+            )
+        )
+        await confirm_transaction(provider, tx)
+
+    tx = await swap_pool_program.rpc["set_governance_fee_destination"](
+        governance_fee_destination,
+        ctx=Context(
+            accounts={
+                "swap_pool_state_account": swap_pool_state,
+                "dao_authority": dao_authority_keypair.public_key
+            },
+            signers=[dao_authority_keypair]
+        )
+    )
+    await confirm_transaction(provider, tx)
+
+    # The destination wallets must be owned by the governance fee destination
+    with pytest.raises(Exception):
+        await collect_governance_fees(swapper.token_accounts)
+
+
+    # Collect the fees
+    swap_pool_asset_balance_before = (await get_account_info(provider, swap_pool_assets[0], swap_pool_asset_wallets[0])).amount
+
+    async with TxEventListener("CollectGovernanceFeesEvent") as ev_listener:
+        await collect_governance_fees(destination_asset_wallets)
+
+        collect_event = (await ev_listener.get_events(swap_pool_program))[0]
+
+    assert collect_event.data.swapPool == swap_pool_state
+    assert collect_event.data.governanceFeeDestination == governance_fee_destination
+    assert collect_event.data.collectedAssetAmounts == [expected_governance_fee, 0]
+    assert collect_event.data.destinationAssetWallets == [destination_asset_wallets[0], PublicKey(0)]
+
+    assert (await get_account_info(provider, swap_pool_assets[0], destination_asset_wallets[0])).amount == expected_governance_fee
+    assert (await get_account_info(provider, swap_pool_assets[1], destination_asset_wallets[1])).amount == 0
+    assert (await get_account_info(provider, swap_pool_assets[0], swap_pool_asset_wallets[0])).amount == swap_pool_asset_balance_before - expected_governance_fee
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    assert swap_pool_state_data.accrued_governance_fees == [0, 0]
@@ -1,5 +1,6 @@
 
 from functools import cache
+from hashlib import sha256
 from typing import Tuple
 from solana.publickey import PublicKey
 
@@ -8,6 +9,12 @@ POOL_TOKEN_MINT_SEED        : str = "poolMint"
 POOL_AUTHORITY              : str = "poolAuth"
 INTERFACE_SWAP_AUTHORITY    : str = "intSwapAuth"
 POOL_ESCROW_SEED            : str = "poolEscrow"
+POOL_LIQUIDITY_ESCROW_SEED  : str = "poolLiqEscrow"
+POOL_UNDERWRITE_SEED        : str = "poolUnderwrite"
+POLYMERASE_AUTHORITY_SEED   : str = "polymeraseAuthority"
+POLYMERASE_RECEIVER_CONFIG  : str = "receiverConfig"
+RECEIPT_SEED                : str = "receipt"
+CHANNEL_SEED                : str = "channel"
 
 
 @cache
@@ -58,13 +65,140 @@ def get_swap_pool_token_mint(
 def get_swap_pool_escrow_wallet(
     swap_pool_program_id: PublicKey,
     swap_pool_state_pubkey: PublicKey,
-    escrow_nonce: int
+    escrow_id: bytes
 ) -> Tuple[PublicKey, int]:
     return PublicKey.find_program_address(
         [
             swap_pool_state_pubkey.__bytes__(),
-            escrow_nonce.to_bytes(4, 'big'),
+            escrow_id,
             POOL_ESCROW_SEED.encode('utf-8')
         ],
         swap_pool_program_id
-    )
\ No newline at end of file
+    )
+
+
+@cache
+def get_swap_pool_liquidity_escrow(
+    swap_pool_program_id: PublicKey,
+    swap_pool_state_pubkey: PublicKey,
+    escrow_id: bytes
+) -> Tuple[PublicKey, int]:
+    return PublicKey.find_program_address(
+        [
+            swap_pool_state_pubkey.__bytes__(),
+            escrow_id,
+            POOL_LIQUIDITY_ESCROW_SEED.encode('utf-8')
+        ],
+        swap_pool_program_id
+    )
+
+
+def compute_asset_escrow_id(
+    destination: PublicKey,
+    units_x64: int,
+    amount: int,
+    asset: PublicKey,
+    block_number: int
+) -> bytes:
+    # Mirrors compute_asset_escrow_id of the IBC interface
+    return sha256(
+        destination.__bytes__() + units_x64.to_bytes(32, 'big') + amount.to_bytes(32, 'big') + asset.__bytes__() + block_number.to_bytes(4, 'big')
+    ).digest()
+
+@cache
+def get_polymerase_authority(
+    polymerase_endpoint_program_id: PublicKey
+) -> Tuple[PublicKey, int]:
+    return PublicKey.find_program_address(
+        [
+            POLYMERASE_AUTHORITY_SEED.encode('utf-8')
+        ],
+        polymerase_endpoint_program_id
+    )
+
+
+@cache
+def get_polymerase_receiver_config(
+    polymerase_receiver_program_id: PublicKey
+) -> Tuple[PublicKey, int]:
+    return PublicKey.find_program_address(
+        [
+            POLYMERASE_RECEIVER_CONFIG.encode('utf-8')
+        ],
+        polymerase_receiver_program_id
+    )
+
+
+def compute_liquidity_escrow_id(
+    destination: PublicKey,
+    units_x64: int,
+    amount: int,
+    block_number: int
+) -> bytes:
+    # Mirrors compute_liquidity_escrow_id of the IBC interface
+    return sha256(
+        destination.__bytes__() + units_x64.to_bytes(32, 'big') + amount.to_bytes(32, 'big') + block_number.to_bytes(4, 'big')
+    ).digest()
+
+
+def compute_payload_swap_hash(payload: bytes) -> bytes:
+    # Mirrors compute_send_asset_hash/compute_send_liquidity_hash of the IBC interface
+    to_account  = payload[164:196]
+    units       = payload[196:228]
+
+    if payload[0] == 0:     # Asset swap
+        return sha256(to_account + units + payload[261:293] + payload[326:358] + payload[358:362]).digest()
+
+    return sha256(to_account + units + payload[292:324] + payload[324:328]).digest()
+
+
+def compute_payload_underwrite_identifier(
+    source_chain_id: int,
+    swap_pool_state: PublicKey,
+    payload: bytes
+) -> bytes:
+    # Mirrors compute_underwrite_identifier of the IBC interface (for asset swap payloads)
+    return sha256(
+        source_chain_id.to_bytes(8, 'big') +
+        payload[34:66] +                        # Source pool
+        payload[358:362] +                      # Block number (nonce)
+        swap_pool_state.__bytes__() +
+        payload[228:229] +                      # Target asset index
+        payload[196:228] +                      # Units
+        payload[253:261] +                      # Min output (u64)
+        payload[164:196] +                      # Destination
+        payload[362:364]                        # Underwrite incentive
+    ).digest()
+
+
+def get_swap_pool_underwrite_escrow(
+    swap_pool_program_id: PublicKey,
+    swap_pool_state: PublicKey,
+    identifier: bytes
+) -> Tuple[PublicKey, int]:
+    return PublicKey.find_program_address(
+        [
+            swap_pool_state.__bytes__(),
+            identifier,
+            POOL_UNDERWRITE_SEED.encode('utf-8')
+        ],
+        swap_pool_program_id
+    )
+
+
+def get_swap_interface_receipt(
+    swap_interface_program_id: PublicKey,
+    swap_interface_state: PublicKey,
+    source_chain_id: int,
+    payload: bytes
+) -> Tuple[PublicKey, int]:
+    return PublicKey.find_program_address(
+        [
+            swap_interface_state.__bytes__(),
+            source_chain_id.to_bytes(8, 'little'),
+            payload[34:66],                             # Source pool
+            compute_payload_swap_hash(payload),
+            RECEIPT_SEED.encode('utf-8')
+        ],
+        swap_interface_program_id
+    )
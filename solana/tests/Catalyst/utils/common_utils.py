@@ -8,12 +8,12 @@ from solana.publickey import PublicKey
 
 from .account_utils import get_swap_pool_authority
 from .simulator_utils import create_and_verify_catalyst_simulator
+from .swap_interface_utils import SOLANA_CHAIN_ID
 from .swap_pool_utils import CreateAndSetupSwapPoolResult, create_and_setup_swap_pool, CreateConnectionResult, create_connection
 from .token_utils import fund_accounts, create_mint, create_token_account
 
 from catalyst_simulator import CatalystSimulator  # type: ignore
 
-SOLANA_CHAIN_ID       = 99
 SOLANA_CHAIN_ID_BYTES = SOLANA_CHAIN_ID.to_bytes(8, 'little')
 
 async def create_mints(provider: Provider, mint_authority: Keypair, count: int) -> list[PublicKey]:
@@ -75,7 +75,7 @@ async def setup_simple_pool_test_env(
     swap_interface_program: Program,
     swap_interface_setup_master_keypair: Keypair,
     polymerase_register_program: Program,
-    polymerase_sender_program: Program,
+    polymerase_receiver_program: Program,
     mint_authority: Keypair,
     generic_payer: Keypair
 ) -> SimplePoolContext:
@@ -123,7 +123,7 @@ async def setup_simple_pool_test_env(
         swap_interface_program,
         swap_interface_setup_master_keypair,
         polymerase_register_program,
-        polymerase_sender_program,
+        polymerase_receiver_program,
         swap_pool_state_keypair=swap_pool_state_keypair
     )
 
@@ -6,6 +6,7 @@ from solana.system_program import SYS_PROGRAM_ID
 from solana.rpc.commitment import Commitment
 from solders.signature import Signature
 
+from utils.account_utils import get_polymerase_receiver_config
 from utils.transaction_utils import confirm_transaction, DEFAULT_TX_COMMITMENT
 
 # Result classes ****************************************************************************************************************
@@ -42,4 +43,33 @@ async def initialize_polymerase_endpoint(
     )
     await confirm_transaction(polymerase_endpoint_program.provider, tx, commitment)
 
-    return InitializePolymeraseEndpointResult(tx, polymerase_endpoint_state_keypair.public_key)
\ No newline at end of file
+    return InitializePolymeraseEndpointResult(tx, polymerase_endpoint_state_keypair.public_key)
+
+
+async def initialize_polymerase_receiver(
+    polymerase_receiver_program: Program,
+    relayer: PublicKey,
+    commitment: Commitment = DEFAULT_TX_COMMITMENT
+) -> PublicKey:
+
+    receiver_config = get_polymerase_receiver_config(polymerase_receiver_program.program_id)[0]
+
+    # The receiver config is shared by all the tests (it may have been initialized by a previous test module)
+    account_info = await polymerase_receiver_program.provider.connection.get_account_info(receiver_config)
+    if account_info.value is not None:
+        return receiver_config
+
+    # Only the deployer (the provider wallet) may initialize the receiver config
+    tx = await polymerase_receiver_program.rpc["initialize"](
+        relayer,
+        ctx=Context(
+            accounts={
+                "receiver_config": receiver_config,
+                "admin": polymerase_receiver_program.provider.wallet.public_key,
+                "system_program": SYS_PROGRAM_ID
+            }
+        )
+    )
+    await confirm_transaction(polymerase_receiver_program.provider, tx, commitment)
+
+    return receiver_config
@@ -9,7 +9,11 @@ from solana.rpc.commitment import Commitment
 from solders.signature import Signature
 
 from utils.transaction_utils import confirm_transaction, DEFAULT_TX_COMMITMENT
-from utils.account_utils import get_swap_pool_authority
+from utils.account_utils import CHANNEL_SEED, get_swap_pool_authority
+
+
+SOLANA_CHAIN_ID   : int = 99
+SOLANA_CHANNEL_ID : str = ""     # Channel through which the (simulated) packets sent from Solana to Solana are received
 
 
 # Result classes ****************************************************************************************************************
@@ -25,6 +29,11 @@ class RegisterSwapInterfacePolymerasePortResult:
     tx: Signature
     polymerase_port: PublicKey
 
+@dataclass
+class CreateChannelResult:
+    tx: Signature
+    channel_state: PublicKey
+
 
 
 # Utils *************************************************************************************************************************
@@ -33,7 +42,7 @@ async def initialize_swap_interface_state(
     swap_interface_program: Program,
     swap_pool_program: Program,
     swap_pool_state: PublicKey,
-    polymerase_emulator_program: Program,
+    polymerase_endpoint_program: Program,
     swap_interface_setup_master_keypair: Keypair,
     commitment: Commitment = DEFAULT_TX_COMMITMENT
 ) -> InitializeSwapInterfaceResult:
@@ -53,7 +62,7 @@ async def initialize_swap_interface_state(
     tx = await swap_interface_program.rpc["initialize"](
         swap_pool_state,
         swap_pool_authority,
-        polymerase_emulator_program.program_id,
+        polymerase_endpoint_program.program_id,
         ctx=Context(
             accounts={
                 "configurator": swap_interface_setup_master_keypair.public_key,
@@ -104,6 +113,55 @@ async def register_swap_interface_polymerase_port(
     return RegisterSwapInterfacePolymerasePortResult(tx, port_registration_account)
 
 
+async def create_channel(
+    swap_interface_program: Program,
+    swap_interface_state: PublicKey,
+    channel_id: str,
+    chain_id: int,
+    swap_interface_configurator_keypair: Keypair,
+    commitment: Commitment = DEFAULT_TX_COMMITMENT
+) -> CreateChannelResult:
+
+    channel_state = get_channel_state_account(
+        swap_interface_program.program_id,
+        swap_interface_state,
+        channel_id
+    )[0]
+
+    tx = await swap_interface_program.rpc["create_channel"](
+        channel_id,
+        chain_id,
+        ctx=Context(
+            accounts={
+                "configurator": swap_interface_configurator_keypair.public_key,
+                "interface_state_account": swap_interface_state,
+                "channel_state_account": channel_state,
+                "system_program": SYS_PROGRAM_ID
+            },
+            signers=[swap_interface_configurator_keypair]
+        )
+    )
+    await confirm_transaction(swap_interface_program.provider, tx, commitment=commitment)
+
+    return CreateChannelResult(tx, channel_state)
+
+
+def get_channel_state_account(
+    swap_interface_program_id: PublicKey,
+    swap_interface_state: PublicKey,
+    channel_id: str
+) -> Tuple[PublicKey, int]:
+
+    return PublicKey.find_program_address(
+        [
+            swap_interface_state.__bytes__(),
+            channel_id.encode('utf-8'),
+            CHANNEL_SEED.encode('utf-8')
+        ],
+        swap_interface_program_id
+    )
+
+
 def get_connection_state_account(
     swap_interface_program_id: PublicKey,
     swap_interface_state: PublicKey,
@@ -11,14 +11,14 @@ from spl.token.constants import TOKEN_PROGRAM_ID
 from solana.sysvar import SYSVAR_RENT_PUBKEY
 from solders.signature import Signature
 
-from utils.account_utils import get_swap_pool_asset_wallet, get_swap_pool_authority, get_swap_pool_escrow_wallet, get_swap_pool_token_mint
-from utils.swap_interface_utils import InitializeSwapInterfaceResult, RegisterSwapInterfacePolymerasePortResult, get_connection_state_account, initialize_swap_interface_state, register_swap_interface_polymerase_port
+from utils.account_utils import compute_asset_escrow_id, compute_payload_underwrite_identifier, get_swap_pool_asset_wallet, get_swap_pool_authority, get_swap_pool_escrow_wallet, get_swap_pool_token_mint, get_swap_pool_underwrite_escrow
+from utils.swap_interface_utils import SOLANA_CHAIN_ID, SOLANA_CHANNEL_ID, InitializeSwapInterfaceResult, RegisterSwapInterfacePolymerasePortResult, create_channel, get_connection_state_account, initialize_swap_interface_state, register_swap_interface_polymerase_port
 from utils.token_utils import approve, create_token_account, mint_to
-from utils.transaction_utils import TxEventListener, confirm_transaction, DEFAULT_TX_COMMITMENT
+from utils.transaction_utils import TxEventListener, confirm_transaction, get_return_data, DEFAULT_TX_COMMITMENT
 from utils.verify_utils import int_to_u256_array, verify_local_swap_event, verify_out_swap_event
 
 
-POOL_MAX_ASSET_COUNT  = 3
+POOL_MAX_ASSET_COUNT  = 8
 
 # Result classes ****************************************************************************************************************
 
@@ -72,6 +72,23 @@ class PerformLocalSwapResult:
     tx: Signature
     local_swap_event: Any
 
+@dataclass
+class PerformDepositMixedResult:
+    tx: Signature
+    deposit_event: Any
+
+@dataclass
+class PerformWithdrawMixedResult:
+    tx: Signature
+    withdraw_event: Any
+
+@dataclass
+class UnderwriteSwapResult:
+    tx: Signature
+    identifier: bytes
+    underwrite_escrow: PublicKey
+    underwrite_event: Any
+
 # Utils *************************************************************************************************************************
 
 async def initialize_swap_pool_state(
@@ -145,8 +162,8 @@ async def initialize_swap_pool_state(
         ))
 
     args: list[Any] = [1] if amplification is None else [amplification]
-    args.append([(assets_balances[i] if i < mints_count else 0) for i in range(POOL_MAX_ASSET_COUNT)])
-    args.append([(assets_weights[i]  if i < mints_count else 0) for i in range(POOL_MAX_ASSET_COUNT)])
+    args.append(assets_balances)
+    args.append(assets_weights)
 
     tx = await swap_pool_program.rpc["initialize"](
         *args,
@@ -290,7 +307,7 @@ async def create_and_setup_swap_pool(
     swap_interface_program: Program,
     swap_interface_setup_master_keypair: Keypair,
     polymerase_register_program: Program,
-    polymerase_sender_program: Program,
+    polymerase_endpoint_program: Program,
     swap_pool_state_keypair: Keypair | None = None,
     commitment: Commitment = DEFAULT_TX_COMMITMENT
 ) -> CreateAndSetupSwapPoolResult:
@@ -315,7 +332,7 @@ async def create_and_setup_swap_pool(
         swap_interface_program,
         swap_pool_program,
         swap_pool_state,
-        polymerase_sender_program,
+        polymerase_endpoint_program,
         swap_interface_setup_master_keypair,
         commitment=commitment
     )
@@ -329,6 +346,16 @@ async def create_and_setup_swap_pool(
         swap_interface_setup_master_keypair
     )
 
+    # Link the channel used by the tests to deliver the Solana to Solana packets
+    await create_channel(
+        swap_interface_program,
+        initialize_swap_interface_result.swap_interface_state,
+        SOLANA_CHANNEL_ID,
+        SOLANA_CHAIN_ID,
+        swap_interface_setup_master_keypair,
+        commitment=commitment
+    )
+
     link_swap_interface_to_swap_pool_result = await link_swap_interface_to_swap_pool(
         swap_pool_program,
         swap_pool_state,
@@ -475,6 +502,128 @@ async def perform_local_swap(
 
 
 
+def get_pool_assets_remaining_accounts(
+    swap_pool_assets: list[PublicKey],
+    swap_pool_asset_wallets: list[PublicKey],
+    user_asset_wallets: list[PublicKey]
+) -> list[AccountMeta]:
+
+    # 3 accounts per pool asset (for deposits and withdrawals): asset mint, swap pool asset wallet, user asset wallet
+    remaining_accounts: list[AccountMeta] = []
+    for mint, swap_pool_asset_wallet, user_asset_wallet in zip(swap_pool_assets, swap_pool_asset_wallets, user_asset_wallets):
+        remaining_accounts.append(AccountMeta(pubkey=mint,                   is_signer=False, is_writable=False))
+        remaining_accounts.append(AccountMeta(pubkey=swap_pool_asset_wallet, is_signer=False, is_writable=True ))
+        remaining_accounts.append(AccountMeta(pubkey=user_asset_wallet,      is_signer=False, is_writable=True ))
+
+    return remaining_accounts
+
+
+async def perform_deposit_mixed(
+    amounts: list[int],
+    min_pool_tokens: int,
+    depositor_pool_token_wallet: PublicKey,
+    depositor_keypair: Keypair,
+    depositor_asset_wallets: list[PublicKey],
+    swap_pool_program: Program,
+    swap_pool_state: PublicKey,
+    swap_pool_assets: list[PublicKey],
+    swap_pool_asset_wallets: list[PublicKey],
+    swap_pool_token_mint: PublicKey,
+    swap_pool_authority: PublicKey,
+    commitment: Commitment = DEFAULT_TX_COMMITMENT
+) -> PerformDepositMixedResult:
+
+    # Give transfer allowance of the deposited assets to the pool authority
+    for depositor_asset_wallet, amount in zip(depositor_asset_wallets, amounts):
+        await approve(
+            swap_pool_program.provider,
+            depositor_asset_wallet,
+            depositor_keypair,
+            swap_pool_authority,
+            amount,
+            commitment=commitment
+        )
+
+    async with TxEventListener("DepositEvent") as ev_listener:
+        tx = await swap_pool_program.rpc["deposit_mixed"](
+            amounts,
+            min_pool_tokens,
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "depositor_pool_token_wallet": depositor_pool_token_wallet,
+                    "swap_pool_token_mint": swap_pool_token_mint,
+                    "swap_pool_authority": swap_pool_authority,
+                    "token_program": TOKEN_PROGRAM_ID
+                },
+                remaining_accounts=get_pool_assets_remaining_accounts(
+                    swap_pool_assets,
+                    swap_pool_asset_wallets,
+                    depositor_asset_wallets
+                )
+            )
+        )
+        await confirm_transaction(swap_pool_program.provider, tx, commitment=commitment)
+
+        deposit_event = (await ev_listener.get_events(swap_pool_program))[0]
+
+    return PerformDepositMixedResult(tx, deposit_event)
+
+
+async def perform_withdraw_mixed(
+    pool_tokens_amount: int,
+    withdraw_ratios_x64: list[int],
+    min_outputs: list[int],
+    withdrawer_pool_token_wallet: PublicKey,
+    withdrawer_keypair: Keypair,
+    withdrawer_asset_wallets: list[PublicKey],
+    swap_pool_program: Program,
+    swap_pool_state: PublicKey,
+    swap_pool_assets: list[PublicKey],
+    swap_pool_asset_wallets: list[PublicKey],
+    swap_pool_token_mint: PublicKey,
+    swap_pool_authority: PublicKey,
+    commitment: Commitment = DEFAULT_TX_COMMITMENT
+) -> PerformWithdrawMixedResult:
+
+    # Give pool token burn allowance to the pool authority
+    await approve(
+        swap_pool_program.provider,
+        withdrawer_pool_token_wallet,
+        withdrawer_keypair,
+        swap_pool_authority,
+        pool_tokens_amount,
+        commitment=commitment
+    )
+
+    async with TxEventListener("WithdrawEvent") as ev_listener:
+        tx = await swap_pool_program.rpc["withdraw_mixed"](
+            pool_tokens_amount,
+            [int_to_u256_array(ratio_x64) for ratio_x64 in withdraw_ratios_x64],
+            min_outputs,
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "withdrawer_pool_token_wallet": withdrawer_pool_token_wallet,
+                    "swap_pool_token_mint": swap_pool_token_mint,
+                    "swap_pool_authority": swap_pool_authority,
+                    "token_program": TOKEN_PROGRAM_ID
+                },
+                remaining_accounts=get_pool_assets_remaining_accounts(
+                    swap_pool_assets,
+                    swap_pool_asset_wallets,
+                    withdrawer_asset_wallets
+                )
+            )
+        )
+        await confirm_transaction(swap_pool_program.provider, tx, commitment=commitment)
+
+        withdraw_event = (await ev_listener.get_events(swap_pool_program))[0]
+
+    return PerformWithdrawMixedResult(tx, withdraw_event)
+
+
+
 async def perform_cross_chain_swap(
     input_amount: int,
     input_asset: PublicKey,
@@ -484,7 +633,6 @@ async def perform_cross_chain_swap(
     target_pool_id: PublicKey,
     output_asset_index: int,
     destination_wallet: PublicKey,
-    swap_escrow_nonce: int,
     swap_escrow_payer: Keypair,
     swap_pool_program: Program,
     swap_pool_state: PublicKey,
@@ -497,17 +645,46 @@ async def perform_cross_chain_swap(
     polymerase_payer: Keypair,
     min_output: int = 0,
     fallback_wallet: PublicKey | None = None,
+    underwrite_incentive_x16: int = 0,
     approx_from: bool = False,
-    approx_to:bool = False,
     commitment: Commitment = DEFAULT_TX_COMMITMENT
 ) -> PerformCrossChainSwapResult:
 
     # TODO polymerase accounts should be derived here
 
+    # The escrow is derived from the swap contents (including the swap units and the escrowed amount, i.e. without the
+    # vault fee) and a recent slot
+    block_number = (await swap_pool_program.provider.connection.get_slot()).value % 2**32
+
+    swap_pool_state_data = await swap_pool_program.account["SwapPoolState"].fetch(swap_pool_state)
+    vault_fee = (input_amount * swap_pool_state_data.vault_fee_x64) >> 64
+
+    # The swap units are quoted by simulating calc_send_asset (which performs the same computation as out_swap)
+    calc_send_asset_result = await swap_pool_program.simulate["calc_send_asset"](
+        input_amount,
+        approx_from,
+        ctx=Context(
+            accounts={
+                "swap_pool_state_account": swap_pool_state,
+                "input_asset_mint": input_asset,
+                "swap_pool_input_asset_wallet": swap_pool_input_asset_wallet
+            }
+        )
+    )
+    units_x64 = int.from_bytes(get_return_data(calc_send_asset_result.raw, swap_pool_program.program_id), 'little')
+
+    escrow_id = compute_asset_escrow_id(
+        destination_wallet,
+        units_x64,
+        input_amount - vault_fee,
+        input_asset,
+        block_number
+    )
+
     swap_escrow_wallet = get_swap_pool_escrow_wallet(
         swap_pool_program.program_id,
         swap_pool_state,
-        swap_escrow_nonce
+        escrow_id
     )[0]
 
     fallback_wallet = fallback_wallet or source_wallet
@@ -531,10 +708,10 @@ async def perform_cross_chain_swap(
             destination_wallet,
             input_amount,
             int_to_u256_array(min_output),
-            swap_escrow_nonce,
+            block_number,
             fallback_wallet,
+            underwrite_incentive_x16,
             approx_from,
-            approx_to,
             ctx=Context(
                 accounts={
                     "swap_pool_state_account": swap_pool_state,
@@ -575,7 +752,7 @@ async def perform_cross_chain_swap(
             deposited_asset_mint    = input_asset,
             depositor_asset_wallet  = source_wallet,
             deposited_asset_amount  = input_amount,
-            source_swap_id          = swap_escrow_nonce,
+            escrow_id               = escrow_id,
         )
 
     return PerformCrossChainSwapResult(
@@ -585,3 +762,128 @@ async def perform_cross_chain_swap(
         out_swap_event
     )
 
+
+
+async def set_pause_flags(
+    swap_pool_program: Program,
+    swap_pool_state: PublicKey,
+    authority_keypair: Keypair,     # Either dao_authority or guardian (pause only)
+    paused: bool = False,
+    local_swaps: bool = False,
+    outgoing_swaps: bool = False,
+    incoming_swaps: bool = False,
+    deposits: bool = False,
+    withdrawals: bool = False,
+    commitment: Commitment = DEFAULT_TX_COMMITMENT
+) -> Signature:
+
+    tx = await swap_pool_program.rpc["set_pause_flags"](
+        swap_pool_program.type["PauseFlags"](
+            paused         = paused,
+            local_swaps    = local_swaps,
+            outgoing_swaps = outgoing_swaps,
+            incoming_swaps = incoming_swaps,
+            deposits       = deposits,
+            withdrawals    = withdrawals
+        ),
+        ctx=Context(
+            accounts={
+                "authority": authority_keypair.public_key,
+                "swap_pool_state_account": swap_pool_state
+            },
+            signers=[authority_keypair]
+        )
+    )
+    await confirm_transaction(swap_pool_program.provider, tx, commitment=commitment)
+
+    return tx
+
+
+
+async def underwrite_swap(
+    payload: bytes,
+    source_chain_id: int,
+    output_asset: PublicKey,
+    underwriter_keypair: Keypair,
+    underwriter_asset_wallet: PublicKey,
+    underwrite_escrow_payer: Keypair,
+    swap_pool_program: Program,
+    swap_pool_state: PublicKey,
+    swap_pool_output_asset_wallet: PublicKey,
+    identifier: bytes | None = None,
+    approx: bool = False,
+    commitment: Commitment = DEFAULT_TX_COMMITMENT
+) -> UnderwriteSwapResult:
+
+    # The swap parameters are taken from the (asset swap) payload of the swap being underwritten
+    identifier = identifier or compute_payload_underwrite_identifier(source_chain_id, swap_pool_state, payload)
+
+    underwrite_escrow = get_swap_pool_underwrite_escrow(
+        swap_pool_program.program_id,
+        swap_pool_state,
+        identifier
+    )[0]
+
+    async with TxEventListener("UnderwriteEvent") as ev_listener:
+        tx = await swap_pool_program.rpc["underwrite"](
+            list(identifier),
+            source_chain_id,
+            PublicKey(payload[34:66]),                          # Source pool
+            int.from_bytes(payload[358:362], 'big'),            # Block number
+            payload[228],                                       # Output asset index
+            int_to_u256_array(int.from_bytes(payload[196:228], 'big')),
+            int.from_bytes(payload[253:261], 'big'),            # Min output (u64)
+            int.from_bytes(payload[362:364], 'big'),            # Underwrite incentive
+            approx,
+            ctx=Context(
+                accounts={
+                    "swap_pool_state_account": swap_pool_state,
+                    "output_asset_mint": output_asset,
+                    "output_asset_wallet": PublicKey(payload[164:196]),
+                    "swap_pool_output_asset_wallet": swap_pool_output_asset_wallet,
+                    "underwriter": underwriter_keypair.public_key,
+                    "underwriter_asset_wallet": underwriter_asset_wallet,
+                    "underwrite_escrow": underwrite_escrow,
+                    "underwrite_escrow_rent_payer": underwrite_escrow_payer.public_key,
+                    "token_program": TOKEN_PROGRAM_ID,
+                    "system_program": SYS_PROGRAM_ID
+                },
+                signers=[underwriter_keypair, underwrite_escrow_payer]
+            )
+        )
+        await confirm_transaction(swap_pool_program.provider, tx, commitment=commitment)
+
+        underwrite_event = (await ev_listener.get_events(swap_pool_program))[0]
+
+    return UnderwriteSwapResult(tx, identifier, underwrite_escrow, underwrite_event)
+
+
+
+async def expire_underwrite(
+    identifier: bytes,
+    caller_keypair: Keypair,
+    underwrite_escrow_payer: PublicKey,
+    swap_pool_program: Program,
+    swap_pool_state: PublicKey,
+    commitment: Commitment = DEFAULT_TX_COMMITMENT
+) -> Signature:
+
+    tx = await swap_pool_program.rpc["expire_underwrite"](
+        list(identifier),
+        ctx=Context(
+            accounts={
+                "swap_pool_state_account": swap_pool_state,
+                "caller": caller_keypair.public_key,
+                "underwrite_escrow": get_swap_pool_underwrite_escrow(
+                    swap_pool_program.program_id,
+                    swap_pool_state,
+                    identifier
+                )[0],
+                "underwrite_escrow_rent_payer": underwrite_escrow_payer
+            },
+            signers=[caller_keypair]
+        )
+    )
+    await confirm_transaction(swap_pool_program.provider, tx, commitment=commitment)
+
+    return tx
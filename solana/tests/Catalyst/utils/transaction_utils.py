@@ -1,6 +1,8 @@
+from base64 import b64decode
 from types import TracebackType
 from typing import Any, Type, cast
 from anchorpy import EventParser, Program, Provider
+from solana.publickey import PublicKey
 from solders.signature import Signature
 from solders.rpc.responses import GetSignatureStatusesResp
 from solana.rpc.commitment import Commitment, Confirmed
@@ -32,6 +34,17 @@ async def confirm_transaction(provider: Provider, tx: Signature, commitment: Com
     return confirmation
 
 
+def get_return_data(logs: list[str], program_id: PublicKey) -> bytes:
+    # The return data of an instruction is logged as 'Program return: <program id> <base64 data>'
+    prefix = f"Program return: {program_id} "
+
+    for log in reversed(logs):
+        if log.startswith(prefix):
+            return b64decode(log[len(prefix):])
+
+    raise Exception("No return data found for the given program.")
+
+
 class TxEventListener():
 
     ws: SolanaWsClientProtocol
@@ -255,7 +255,7 @@ def verify_out_swap_event(
     deposited_asset_mint     : PublicKey,
     depositor_asset_wallet   : PublicKey,
     deposited_asset_amount   : int,
-    source_swap_id           : int,
+    escrow_id                : bytes,
     withdrawn_pool_units_x64 : int | None = None
 ):
     assert out_swap_event.data.swapPool             == swap_pool
@@ -266,7 +266,7 @@ def verify_out_swap_event(
     assert out_swap_event.data.depositedAssetMint   == deposited_asset_mint
     assert out_swap_event.data.depositorAssetWallet == depositor_asset_wallet
     assert out_swap_event.data.depositedAssetAmount == deposited_asset_amount
-    assert out_swap_event.data.escrowNonce          == source_swap_id
+    assert bytes(out_swap_event.data.escrowId)      == escrow_id
 
     if withdrawn_pool_units_x64 is not None:
         assert u256_array_to_int(out_swap_event.data.withdrawnPoolUnitsX64) == withdrawn_pool_units_x64
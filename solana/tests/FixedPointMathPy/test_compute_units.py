@@ -0,0 +1,105 @@
+import re
+from typing import Any, List
+
+from anchorpy import Context, Program
+
+from solana.publickey import PublicKey
+from solana.rpc.commitment import Confirmed
+from utils import U256_MAX, confirm_transaction, int_to_u256_array
+
+# Benchmark the compute units consumed by the fixed point mathematical library.
+# Run with 'pytest -s' to get the compute units report printed to the console.
+
+ONE_X64 = 2**64
+
+COMPUTE_UNITS_LOG_REGEX = re.compile(r"Program \w+ consumed (\d+) of \d+ compute units")
+
+MUL_BENCHMARK_ARGS = [
+    [ONE_X64, ONE_X64],
+    [3 * ONE_X64 // 2, 7 * ONE_X64 // 3],
+    [2**127 + 12345, 2**100 + 6789],
+    [U256_MAX >> 64, ONE_X64],
+]
+
+
+async def get_consumed_compute_units(
+    program: Program,
+    result_account: PublicKey,
+    method_name: str,
+    args: List[int],
+    raw_args: List[Any] = []
+) -> int:
+
+    tx_result = await program.rpc[method_name](
+        *[int_to_u256_array(arg) for arg in args],
+        *raw_args,
+        ctx=Context(
+            accounts={
+                "calculation_data": result_account
+            }
+        )
+    )
+
+    await confirm_transaction(program.provider, tx_result, commitment=Confirmed)
+
+    tx_info = await program.provider.connection.get_transaction(tx_result, commitment=Confirmed)
+    log_messages = tx_info.value.transaction.meta.log_messages
+
+    for log in log_messages:
+        match = COMPUTE_UNITS_LOG_REGEX.match(log)
+        if match is not None:
+            return int(match.group(1))
+
+    raise Exception(f"Unable to find the consumed compute units of '{method_name}' in the transaction logs.")
+
+
+async def test_mul_compute_units(math_program: Program, result_account: PublicKey):
+
+    print("\nmul_x64 compute units (legacy => current)")
+
+    for args in MUL_BENCHMARK_ARGS:
+        legacy_units  = await get_consumed_compute_units(math_program, result_account, 'legacy_mul_x64', args)
+        current_units = await get_consumed_compute_units(math_program, result_account, 'mul_x64', args)
+
+        print(f"\t{[hex(arg) for arg in args]}: {legacy_units} => {current_units} ({legacy_units - current_units} saved)")
+
+        assert current_units < legacy_units
+
+
+async def test_mul_dependent_compute_units(math_program: Program, result_account: PublicKey):
+
+    # mul_x64 dominates the compute units of the following functions
+    benchmarks = [
+        ('exp_x64',     [ONE_X64 * 20 + ONE_X64 // 3]),
+        ('inv_exp_x64', [ONE_X64 * 10 + ONE_X64 // 3]),
+        ('pow_x64',     [ONE_X64 * 1000 + 1, ONE_X64 // 3]),
+    ]
+
+    print("\nCompute units")
+
+    for (method_name, args) in benchmarks:
+        units = await get_consumed_compute_units(math_program, result_account, method_name, args)
+        print(f"\t{method_name}{tuple(hex(arg) for arg in args)}: {units}")
+
+
+async def test_precision_compute_units(math_program: Program, result_account: PublicKey):
+
+    benchmarks = [
+        ('log2_x64', [ONE_X64 * 1000 + ONE_X64 // 3]),
+        ('pow2_x64', [ONE_X64 * 100 + ONE_X64 // 3]),
+    ]
+
+    print("\nCompute units (fixed precision => 32/48/64 fractional bits)")
+
+    for (method_name, args) in benchmarks:
+        units = await get_consumed_compute_units(math_program, result_account, method_name, args)
+
+        precision_units = [
+            await get_consumed_compute_units(math_program, result_account, method_name + '_with_precision', args, [bits])
+            for bits in [32, 48, 64]
+        ]
+
+        print(f"\t{method_name}{tuple(hex(arg) for arg in args)}: {units} => {precision_units}")
+
+        # Every extra fractional bit requires an extra iteration
+        assert precision_units[0] < precision_units[1] < precision_units[2]
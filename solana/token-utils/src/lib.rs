@@ -1,4 +1,4 @@
-use anchor_lang::{prelude::*, solana_program::{self, program::invoke_signed}};
+use anchor_lang::{prelude::*, solana_program::{self, program::{invoke, invoke_signed}}};
 use anchor_spl::token::{TokenAccount, InitializeAccount, initialize_account};
 
 
@@ -34,6 +34,34 @@ pub fn transfer_tokens_using_pda_authority<'info>(
 }
 
 
+pub fn transfer_tokens<'info>(
+    amount              : u64,
+    source_wallet       : AccountInfo<'info>,
+    destination_wallet  : AccountInfo<'info>,
+    authority           : AccountInfo<'info>,
+    token_program       : AccountInfo<'info>    // TODO Is this necessary?
+) {
+    anchor_lang::solana_program::program::invoke(
+        // Transfer instruction
+        &spl_token::instruction::transfer(  //TODO use transfer_checked?
+            &token_program.key(),
+            &source_wallet.key(),
+            &destination_wallet.key(),
+            &authority.key(),
+            &[],
+            amount
+        ).unwrap(),
+
+        // Accounts
+        &[
+            source_wallet,
+            destination_wallet,
+            authority
+        ]
+    ).unwrap();
+}
+
+
 pub fn mint_tokens_using_pda_authority<'info> (
     amount              : u64,
     destination_wallet  : AccountInfo<'info>,
@@ -170,3 +198,117 @@ pub fn create_pda_token_account<'info> (
 
     initialize_account(cpi_ctx).unwrap();
 }
+
+
+pub fn create_pda_account<'info> (
+    space          : usize,
+    owner          : &Pubkey,
+    account        : AccountInfo<'info>,
+    account_seeds  : &[&[u8]],
+    payer          : AccountInfo<'info>,
+    system_program : AccountInfo<'info>,
+) {
+
+    let rent_lamports: u64 = Rent::get().unwrap().minimum_balance(space);
+    let current_lamports: u64 = account.lamports();
+
+    // NOTE: The account may have been funded beforehand (to prevent its creation, as 'create_account' fails for accounts
+    // that hold lamports). Hence the lamports are topped up and the account allocated and assigned in that case.
+    if current_lamports == 0 {
+        let create_account_ix = solana_program::system_instruction::create_account(
+            &payer.key(),
+            &account.key(),
+            rent_lamports,
+            space as u64,
+            owner
+        );
+
+        invoke_signed(
+            &create_account_ix,
+            &[
+                payer,
+                account,
+                system_program
+            ],
+            &[account_seeds]
+        ).unwrap();
+
+        return;
+    }
+
+    let required_lamports = rent_lamports.saturating_sub(current_lamports);
+    if required_lamports > 0 {
+        let transfer_ix = solana_program::system_instruction::transfer(
+            &payer.key(),
+            &account.key(),
+            required_lamports
+        );
+
+        invoke(
+            &transfer_ix,
+            &[
+                payer,
+                account.clone(),
+                system_program.clone()
+            ]
+        ).unwrap();
+    }
+
+    let allocate_ix = solana_program::system_instruction::allocate(
+        &account.key(),
+        space as u64
+    );
+
+    invoke_signed(
+        &allocate_ix,
+        &[
+            account.clone(),
+            system_program.clone()
+        ],
+        &[account_seeds]
+    ).unwrap();
+
+    let assign_ix = solana_program::system_instruction::assign(
+        &account.key(),
+        owner
+    );
+
+    invoke_signed(
+        &assign_ix,
+        &[
+            account,
+            system_program
+        ],
+        &[account_seeds]
+    ).unwrap();
+}
+
+
+pub fn close_token_account_using_pda_authority<'info> (
+    account             : AccountInfo<'info>,
+    destination         : AccountInfo<'info>,   // Receiver of the account rent
+    pda_authority       : AccountInfo<'info>,
+    pda_authority_seeds : &[&[u8]],
+    token_program       : AccountInfo<'info>,
+) {
+    anchor_lang::solana_program::program::invoke_signed(
+        // Close account instruction
+        &spl_token::instruction::close_account(
+            &token_program.key(),
+            &account.key(),
+            &destination.key(),
+            &pda_authority.key(),
+            &[]
+        ).unwrap(),
+
+        // Accounts
+        &[
+            account,
+            destination,
+            pda_authority,
+        ],
+
+        // pda_authority seeds
+        &[pda_authority_seeds]
+    ).unwrap();
+}